@@ -2,25 +2,38 @@
 //! 
 //! This module contains the main application state and logic.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::sync::mpsc::{self, Receiver};
 
-use crate::data_structures::{SubtitleDownloader, DownloadJob, JobStatus};
+use crate::data_structures::{SubtitleDownloader, DownloadJob, JobChanges, JobStatus, JobStore, MissingVideo, ScanSummary};
+use crate::job_groups::JobListView;
+use crate::retry_policy::RetryPolicy;
+use crate::job_history::JobHistory;
 use crate::settings::Settings;
 use crate::python_manager::PythonManager;
 use crate::subtitle_utils::SubtitleUtils;
-use crate::helper_functions::Utils;
+use crate::download_pipeline::DownloadPipeline;
+use crate::audio_language::AudioLanguageFilter;
+use crate::settings::AudioLanguageRule;
+use crate::provider_accounts::ProviderAccounts;
+use crate::subtitle_backend::SubtitleBackends;
+use crate::video_hash::VideoHasher;
+use crate::library_index::{LibraryIndex, LibraryVideo, WalkOptions};
+use crate::config::SUBTITLE_EXTENSIONS;
+use crate::media_probe::MediaProbe;
 
 // Use the logging macros directly from the crate root
 use crate::{info, warn, debug, error};
 
 // Version check state
 use once_cell::sync::Lazy;
-static VERSION_PTR: Lazy<std::sync::Arc<std::sync::Mutex<(Option<String>, Option<String>, bool)>>> = Lazy::new(|| {
+/// Latest version, error message and whether the check has finished
+type VersionCheckState = (Option<String>, Option<String>, bool);
+static VERSION_PTR: Lazy<std::sync::Arc<std::sync::Mutex<VersionCheckState>>> = Lazy::new(|| {
     std::sync::Arc::new(std::sync::Mutex::new((None, None, false)))
 });
 
@@ -153,11 +166,10 @@ impl Default for SubtitleDownloader {
         let downloader = Self {
             downloads_completed: 0,
             total_downloads: 0,
-            is_downloading: false,
             downloading: false,
             download_thread_handle: None,
             cancel_flag: Arc::new(AtomicBool::new(false)),
-            download_jobs: Arc::new(Mutex::new(Vec::new())),
+            download_jobs: Arc::new(Mutex::new(JobStore::default())),
             python_installed,
             python_version,
             pipx_installed,
@@ -167,17 +179,43 @@ impl Default for SubtitleDownloader {
             python_install_result: Arc::new(Mutex::new(None)),
             subliminal_install_result,
             selected_languages: settings.selected_languages,
+            language_fallbacks: settings.language_fallbacks,
+            forced_languages: settings.forced_languages,
             force_download: settings.force_download,
             overwrite_existing: settings.overwrite_existing,
             ignore_local_extras: settings.ignore_local_extras,
+            auto_start_downloads: settings.auto_start_downloads,
             concurrent_downloads: settings.concurrent_downloads,
             keep_dropdown_open: false,
+            backend: settings.backend,
+            providers: settings.providers,
+            retry: settings.retry,
+            scan: settings.scan,
+            validation: settings.validation,
+            hearing_impaired: settings.hearing_impaired,
+            dual_subtitles: settings.dual_subtitles,
+            embedded: settings.embedded,
+            mux: settings.mux,
+            ocr: settings.ocr,
+            speech_to_text: settings.speech_to_text,
+            translation: settings.translation,
+            audio_language: settings.audio_language,
             folder_path: String::new(),
             scanned_videos: Arc::new(Mutex::new(Vec::new())),
             videos_missing_subs: Arc::new(Mutex::new(Vec::new())),
             scanning: false,
             scan_done_receiver: None,
             ignored_extra_folders: 0,
+            skipped_for_audio_language: 0,
+            audio_languages: HashMap::new(),
+            skipped_for_age: 0,
+            skipped_for_history: 0,
+            duplicate_videos: Vec::new(),
+            unreadable_paths: Vec::new(),
+            scan_preview: None,
+            history_video: None,
+            provider_accounts: ProviderAccounts::load(),
+            account_login_tests: Arc::new(Mutex::new(BTreeMap::new())),
             status: if python_installed && pipx_installed && !subliminal_installed {
                 "Python and pipx detected. Installing Subliminal...".to_string()
             } else {
@@ -188,7 +226,8 @@ impl Default for SubtitleDownloader {
             last_refresh_time: std::time::Instant::now(),
             refresh_interval: std::time::Duration::from_secs(2), // Check every 2 seconds
             cached_jobs: Vec::new(),
-            last_jobs_update: std::time::Instant::now(),
+            cached_jobs_generation: 0,
+            job_list: JobListView::default(),
             background_check_handle: Some(background_handle),
             background_check_sender: Some(tx),
             background_check_receiver: Some(rx),
@@ -230,16 +269,37 @@ impl Default for SubtitleDownloader {
 }
 
 impl SubtitleDownloader {
-    /// Save the current user settings to disk
-    pub fn save_current_settings(&self) {
-        let settings = Settings {
+    /// Snapshot the current user settings
+    pub fn current_settings(&self) -> Settings {
+        Settings {
             selected_languages: self.selected_languages.clone(),
+            language_fallbacks: self.language_fallbacks.clone(),
+            forced_languages: self.forced_languages.clone(),
             force_download: self.force_download,
             overwrite_existing: self.overwrite_existing,
             ignore_local_extras: self.ignore_local_extras,
+            auto_start_downloads: self.auto_start_downloads,
             concurrent_downloads: self.concurrent_downloads,
-        };
-        
+            backend: self.backend.clone(),
+            providers: self.providers.clone(),
+            retry: self.retry.clone(),
+            scan: self.scan.clone(),
+            validation: self.validation.clone(),
+            hearing_impaired: self.hearing_impaired.clone(),
+            dual_subtitles: self.dual_subtitles.clone(),
+            embedded: self.embedded.clone(),
+            mux: self.mux.clone(),
+            ocr: self.ocr.clone(),
+            speech_to_text: self.speech_to_text.clone(),
+            translation: self.translation.clone(),
+            audio_language: self.audio_language.clone(),
+        }
+    }
+
+    /// Save the current user settings to disk
+    pub fn save_current_settings(&self) {
+        let settings = self.current_settings();
+
         if let Err(e) = settings.save() {
             warn!("Failed to save settings: {}", e);
         } else {
@@ -247,13 +307,47 @@ impl SubtitleDownloader {
         }
     }
 
-    /// Scan the selected folder for video files and update the missing subtitles list
+    /// Save provider logins to the platform's credential store
+    pub fn save_provider_accounts(&mut self) {
+        if let Err(e) = self.provider_accounts.save() {
+            error!("Failed to save provider accounts: {}", e);
+            self.status = format!("Failed to save provider accounts: {}", e);
+        } else {
+            // Earlier test results may be for other credentials
+            self.account_login_tests.lock().unwrap().clear();
+            info!("Provider accounts saved");
+        }
+    }
+
+    /// Test a provider login in the background
+    pub fn test_provider_login(&self, provider: &str) {
+        let account = self.provider_accounts.accounts.get(provider).cloned().unwrap_or_default();
+        let provider = provider.to_string();
+        let tests = Arc::clone(&self.account_login_tests);
+        tests.lock().unwrap().insert(provider.clone(), None);
+        thread::spawn(move || {
+            let result = ProviderAccounts::test_login(&provider, &account);
+            tests.lock().unwrap().insert(provider, Some(result));
+        });
+    }
+
+    /// Scan the selected folder for video files and update the missing subtitles list,
+    /// re-reading only the directories that changed since the last scan
     pub fn scan_folder(&mut self) {
+        self.start_scan(false);
+    }
+
+    /// Scan the selected folder, reading every directory again instead of using the library index
+    pub fn full_rescan(&mut self) {
+        self.start_scan(true);
+    }
+
+    fn start_scan(&mut self, full_rescan: bool) {
         if self.folder_path.is_empty() || self.scanning {
             return;
         }
 
-        info!("Starting folder scan: {}", self.folder_path);
+        info!("Starting {}folder scan: {}", if full_rescan { "full " } else { "" }, self.folder_path);
         if self.ignore_local_extras {
             info!("Ignore Local Extras is enabled - will skip local extras folders during scan");
         }
@@ -265,70 +359,141 @@ impl SubtitleDownloader {
         let scanned_videos = Arc::clone(&self.scanned_videos);
         let videos_missing_subs = Arc::clone(&self.videos_missing_subs);
         let folder_path = self.folder_path.clone();
-        let selected_languages = self.selected_languages.clone();
+        let language_chains = SubtitleUtils::language_chains(&self.selected_languages, &self.language_fallbacks);
+        let audio_rule = self.audio_language.rule;
+        let forced_languages = self.forced_languages.clone();
+        let mux_enabled = self.mux.enabled;
+        let ocr_enabled = self.ocr.enabled;
         let overwrite_existing = self.overwrite_existing;
+        let max_age_days = self.providers.max_age_days;
+        let skip_recent_failures = self.retry.skip_recent_failures;
         let ignore_local_extras = self.ignore_local_extras;
-        let ignored_folders_count = Arc::new(Mutex::new(0));
+        let walk_options = WalkOptions {
+            ignore_extras: self.ignore_local_extras,
+            full_rescan,
+            threads: self.scan.threads,
+            follow_symlinks: self.scan.follow_symlinks,
+        };
 
         // Clear download jobs when folder changes
-        {
-            let mut jobs = self.download_jobs.lock().unwrap();
-            jobs.clear();
-        }
-        self.cached_jobs.clear(); // Also clear cached jobs
+        self.download_jobs.lock().unwrap().replace(Vec::new());
+        self.update_cached_jobs(); // Also clear cached jobs
 
         // Reset downloading flag when starting new scan
         self.downloading = false;
         self.ignored_extra_folders = 0; // Reset ignored folders count
+        self.skipped_for_audio_language = 0;
+        self.audio_languages.clear();
+        self.skipped_for_age = 0;
+        self.skipped_for_history = 0;
+        self.duplicate_videos.clear();
+        self.unreadable_paths.clear();
+        self.scan_preview = None;
 
-        let ignored_folders_count_clone = Arc::clone(&ignored_folders_count);
         thread::spawn(move || {
-            let mut found_videos = Vec::new();
             let mut missing_subtitles = Vec::new();
 
-            fn visit_dirs(dir: &Path, videos: &mut Vec<PathBuf>, ignore_extras: bool, ignored_count: &Arc<Mutex<usize>>) {
-                if let Ok(entries) = dir.read_dir() {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.is_dir() {
-                            // Check if this is a local extras folder that should be ignored
-                            if ignore_extras {
-                                if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-                                    let extras_folders = [
-                                        "Behind The Scenes", "Deleted Scenes", "Featurettes",
-                                        "Interviews", "Scenes", "Shorts", "Trailers", "Other"
-                                    ];
-                                    if extras_folders.contains(&dir_name) {
-                                        info!("Ignoring local extras folder: {}", path.display());
-                                        if let Ok(mut count) = ignored_count.lock() {
-                                            *count += 1;
-                                        }
-                                        continue; // Skip this folder and its contents
-                                    }
-                                }
-                            }
-                            visit_dirs(&path, videos, ignore_extras, ignored_count);
-                        } else if Utils::is_video_file(&path) {
-                            videos.push(path);
-                        }
-                    }
-                }
-            }
+            let mut index = LibraryIndex::load();
+            let library = index.walk(Path::new(&folder_path), &walk_options);
+            let found_videos: Vec<PathBuf> = library.videos.iter().map(|video| video.path.clone()).collect();
 
-            visit_dirs(Path::new(&folder_path), &mut found_videos, ignore_local_extras, &ignored_folders_count_clone);
+            // Leave out videos older than the age filter
+            let recent_videos: Vec<&LibraryVideo> = library.videos.iter()
+                .filter(|video| max_age_days == 0 || video.modified_within_days(max_age_days))
+                .collect();
+            let skipped_for_age = found_videos.len() - recent_videos.len();
+            if skipped_for_age > 0 {
+                info!("Skipping {} videos older than {} days", skipped_for_age, max_age_days);
+            }
 
-            if overwrite_existing {
+            let mut skipped_for_audio_language = 0;
+            let mut skipped_for_history = 0;
+            let mut probes_avoided = 0;
+            let mut missing_videos = Vec::new();
+            let mut audio_languages = HashMap::new();
+            if overwrite_existing && audio_rule == AudioLanguageRule::Off {
                 // If overwrite is enabled, include all videos regardless of existing subtitles
-                missing_subtitles = found_videos.clone();
-                info!("Overwrite mode enabled - including all {} videos", found_videos.len());
+                missing_subtitles = recent_videos.iter().map(|video| video.path.clone()).collect();
+                missing_videos = recent_videos.iter()
+                    .map(|video| MissingVideo { path: video.path.clone(), reason: "Overwriting existing subtitles".to_string() })
+                    .collect();
+                info!("Overwrite mode enabled - including all {} videos", recent_videos.len());
             } else {
                 // Only include videos that are missing subtitles
-                for video in &found_videos {
-                    if SubtitleUtils::video_missing_subtitle(video, &selected_languages) {
-                        missing_subtitles.push(video.clone());
+                for library_video in &recent_videos {
+                    let video = &library_video.path;
+                    // Sidecars come from the library index rather than a probe per language and extension
+                    let languages_checked = language_chains.iter().map(Vec::len).sum::<usize>() + 1 + forced_languages.len();
+                    probes_avoided += languages_checked * SUBTITLE_EXTENSIONS.len();
+                    let stem = library_video.stem();
+                    let mut missing_languages = SubtitleUtils::missing_languages_in(&stem, &library_video.sidecars, &language_chains);
+                    let mut missing_forced = SubtitleUtils::missing_forced_in(&stem, &library_video.sidecars, &forced_languages);
+                    if missing_languages.is_empty() && !missing_forced && !overwrite_existing {
+                        continue;
                     }
+                    // Only videos missing something have their audio probed, and the index keeps the result
+                    let mut chains = language_chains.clone();
+                    if audio_rule != AudioLanguageRule::Off {
+                        let audio = index.audio_language(library_video);
+                        audio_languages.insert(video.clone(), audio.clone());
+                        chains = AudioLanguageFilter::chains_for_video(video, &language_chains, audio_rule, Some(audio));
+                        if chains.is_empty() {
+                            debug!("Skipping {}: audio is already in a selected language", video.display());
+                            skipped_for_audio_language += 1;
+                            continue;
+                        }
+                        missing_languages = SubtitleUtils::missing_languages_in(&stem, &library_video.sidecars, &chains);
+                    }
+                    if mux_enabled && (!missing_languages.is_empty() || missing_forced) {
+                        // Muxed subtitles may have no sidecar left, so embedded tracks count as present
+                        let embedded = MediaProbe::streams(video, "s");
+                        missing_languages.retain(|lang| {
+                            !chains.iter()
+                                .find(|chain| chain.first() == Some(lang))
+                                .is_some_and(|chain| chain.iter().any(|l| SubtitleUtils::embedded_covers(&embedded, l, false, ocr_enabled)))
+                        });
+                        missing_forced = forced_languages.iter().any(|lang| {
+                            SubtitleUtils::missing_forced_in(&stem, &library_video.sidecars, std::slice::from_ref(lang))
+                                && !SubtitleUtils::embedded_covers(&embedded, lang, true, ocr_enabled)
+                        });
+                    }
+                    if skip_recent_failures && !overwrite_existing && !missing_forced {
+                        if let Some(due) = JobHistory::skip_until(video, &missing_languages) {
+                            debug!("Skipping {}: not found recently, next check after {}", video.display(), due.format("%Y-%m-%d"));
+                            skipped_for_history += 1;
+                            continue;
+                        }
+                    }
+                    let reason = if !missing_languages.is_empty() {
+                        let names: Vec<&str> = missing_languages.iter().map(|l| SubtitleUtils::language_code_to_name(l)).collect();
+                        format!("Missing {}", names.join(", "))
+                    } else if missing_forced {
+                        "Missing forced subtitles".to_string()
+                    } else if overwrite_existing {
+                        "Overwriting existing subtitles".to_string()
+                    } else {
+                        continue;
+                    };
+                    missing_subtitles.push(video.clone());
+                    missing_videos.push(MissingVideo { path: video.clone(), reason });
                 }
-                info!("Found {} videos, {} missing subtitles", found_videos.len(), missing_subtitles.len());
+                info!("Found {} videos, {} missing subtitles, {} skipped for audio language, {} not found recently", found_videos.len(), missing_subtitles.len(), skipped_for_audio_language, skipped_for_history);
+                info!("Checked sidecars with {} directory listings ({} reused from the index) instead of up to {} exists() probes",
+                    library.directories_read, library.directories_reused, probes_avoided);
+            }
+
+            index.save();
+
+            // Hash videos that share a size (cached by size and mtime) to spot copies in other folders
+            let sized_videos: Vec<(PathBuf, u64)> = library.videos.iter().map(|video| (video.path.clone(), video.size)).collect();
+            let duplicate_videos = VideoHasher::find_duplicates(&sized_videos);
+            // Videos in folders that couldn't be read aren't gone, so keep their hashes then
+            if library.unreadable_paths.is_empty() {
+                VideoHasher::prune_cache(Path::new(&folder_path), &found_videos);
+            }
+            VideoHasher::save_cache();
+            if !duplicate_videos.is_empty() {
+                info!("Found {} groups of duplicate videos", duplicate_videos.len());
             }
 
             let found_count = found_videos.len();
@@ -343,25 +508,32 @@ impl SubtitleDownloader {
                 info!("Folder scan completed - found {} videos, {} missing subtitles", found_count, missing_count);
             }
             
-            // Send the ignored folders count along with the completion signal
-            let ignored_count = if let Ok(count) = ignored_folders_count_clone.lock() {
-                *count
-            } else {
-                0
-            };
-            let _ = tx.send(ignored_count);
+            let _ = tx.send(ScanSummary { ignored_extra_folders: library.ignored_extra_folders, skipped_for_audio_language, skipped_for_age, skipped_for_history, duplicate_videos, missing_videos, unreadable_paths: library.unreadable_paths, audio_languages });
         });
     }
 
     /// Start subtitle downloads for all videos missing subtitles
     pub fn start_downloads(&mut self) {
+        let videos_missing = self.videos_missing_subs.lock().unwrap().clone();
+        self.start_downloads_for(videos_missing);
+    }
+
+    /// Start subtitle downloads for the videos chosen in the scan preview
+    pub fn start_selected_downloads(&mut self) {
+        let Some(preview) = self.scan_preview.take() else {
+            return;
+        };
+        self.start_downloads_for(preview.selected_paths());
+    }
+
+    /// Start subtitle downloads for the given videos
+    fn start_downloads_for(&mut self, videos_missing: Vec<PathBuf>) {
         if self.downloading || self.selected_languages.is_empty() {
             self.status = "Select at least one language and ensure no downloads are in progress.".to_string();
             warn!("Cannot start downloads: downloading={}, languages={:?}", self.downloading, self.selected_languages);
             return;
         }
 
-        let videos_missing = self.videos_missing_subs.lock().unwrap().clone();
         if videos_missing.is_empty() {
             self.status = "No videos missing subtitles.".to_string();
             info!("No videos to download subtitles for");
@@ -372,211 +544,140 @@ impl SubtitleDownloader {
         self.status = "Starting subtitle downloads...".to_string();
         self.downloads_completed = 0;
         self.total_downloads = 0;
-        self.is_downloading = true;
 
-        let langs = self.selected_languages.clone();
         let jobs: Vec<_> = videos_missing.into_iter()
-            .map(|video_path| DownloadJob { video_path, status: JobStatus::Pending, subtitle_paths: Vec::new() })
+            .map(|video| DownloadJob {
+                audio_language: self.audio_languages.get(&video).cloned(),
+                ..DownloadJob::new(video)
+            })
             .collect();
 
         self.total_downloads = jobs.len();
-        *self.download_jobs.lock().unwrap() = jobs;
-        self.cached_jobs.clear(); // Clear cached jobs when starting new downloads
-        self.downloading = true;
+        let pending_indexes = (0..jobs.len()).collect();
+        self.download_jobs.lock().unwrap().replace(jobs);
+        self.spawn_download_thread(pending_indexes);
+    }
 
+    /// Run failed and deferred jobs among the given ones again
+    pub fn retry_jobs(&mut self, indexes: &[usize]) {
+        if self.downloading {
+            warn!("Cannot retry jobs while downloads are in progress");
+            return;
+        }
+        let mut pending_indexes = VecDeque::new();
+        {
+            let mut store = self.download_jobs.lock().unwrap();
+            for &index in indexes {
+                let retryable = store.jobs().get(index)
+                    .is_some_and(|job| matches!(job.status, JobStatus::Failed(_) | JobStatus::Deferred(_)));
+                if retryable {
+                    store.update(index, |job| *job = DownloadJob {
+                        audio_language: job.audio_language.take(),
+                        ..DownloadJob::new(job.video_path.clone())
+                    });
+                    pending_indexes.push_back(index);
+                }
+            }
+        }
+        if pending_indexes.is_empty() {
+            return;
+        }
+        info!("Retrying {} jobs", pending_indexes.len());
+        self.status = format!("Retrying {} jobs...", pending_indexes.len());
+        self.spawn_download_thread(pending_indexes);
+    }
+
+    /// Process the jobs at the given indexes on a background thread
+    fn spawn_download_thread(&mut self, mut pending_indexes: VecDeque<usize>) {
+        self.downloading = true;
         self.cancel_flag.store(false, Ordering::SeqCst);
 
         let cancel_flag = Arc::clone(&self.cancel_flag);
         let jobs_arc = Arc::clone(&self.download_jobs);
         let max_concurrent = self.concurrent_downloads;
-        let force_download = self.force_download;
-        let overwrite_existing = self.overwrite_existing;
+        let settings = self.current_settings();
+        let backend = SubtitleBackends::from_settings(&settings, &self.provider_accounts);
+        let retry_policy = RetryPolicy::from_settings(&settings.retry);
 
-        info!("Starting download thread with {} concurrent downloads, force={}, overwrite={}", max_concurrent, force_download, overwrite_existing);
+        info!("Starting download thread with {} concurrent downloads, force={}, overwrite={}, backend={}, attempts={}", max_concurrent, settings.force_download, settings.overwrite_existing, backend.name(), retry_policy.max_attempts);
 
         self.download_thread_handle = Some(thread::spawn(move || {
-            let mut pending_indexes: VecDeque<usize> = (0..jobs_arc.lock().unwrap().len()).collect();
             let mut running_threads = Vec::new();
+            // Jobs waiting for their backoff delay, sent back by the job threads
+            let (retry_tx, retry_rx) = mpsc::channel::<(std::time::Instant, usize)>();
+            let mut waiting_retries: Vec<(std::time::Instant, usize)> = Vec::new();
 
-            while !pending_indexes.is_empty() || !running_threads.is_empty() {
+            while !pending_indexes.is_empty() || !running_threads.is_empty() || !waiting_retries.is_empty() {
                 running_threads.retain(|handle: &thread::JoinHandle<()>| !handle.is_finished());
+                waiting_retries.extend(retry_rx.try_iter());
+                let now = std::time::Instant::now();
+                waiting_retries.retain(|&(due, idx)| {
+                    if due <= now {
+                        pending_indexes.push_back(idx);
+                    }
+                    due > now
+                });
 
                 while running_threads.len() < max_concurrent && !pending_indexes.is_empty() {
                     if cancel_flag.load(Ordering::SeqCst) {
                         info!("Download cancelled by user");
-                        let mut jobs_lock = jobs_arc.lock().unwrap();
-                        for job in jobs_lock.iter_mut() {
-                            if job.status == JobStatus::Pending || job.status == JobStatus::Running {
-                                job.status = JobStatus::Failed("Cancelled".to_string());
-                            }
-                        }
+                        jobs_arc.lock().unwrap().update_where(
+                            |job| job.status == JobStatus::Pending || job.status == JobStatus::Running,
+                            |job| job.status = JobStatus::Failed("Cancelled".to_string()),
+                        );
+                        JobHistory::save();
                         return;
                     }
 
                     let idx = pending_indexes.pop_front().unwrap();
 
-                    {
-                        let mut jobs_lock = jobs_arc.lock().unwrap();
-                        if let Some(job) = jobs_lock.get_mut(idx) {
+                    let (job_path, attempt, audio_language) = {
+                        let mut store = jobs_arc.lock().unwrap();
+                        store.update(idx, |job| {
                             job.status = JobStatus::Running;
-                        }
-                    }
-
-                    let job_path = {
-                        let jobs_lock = jobs_arc.lock().unwrap();
-                        jobs_lock[idx].video_path.clone()
+                            job.attempt += 1;
+                        });
+                        let job = &store.jobs()[idx];
+                        (job.video_path.clone(), job.attempt, job.audio_language.clone())
                     };
 
-                    let langs_clone = langs.clone();
+                    let settings = settings.clone();
+                    let backend = Arc::clone(&backend);
+                    let retry_policy = retry_policy.clone();
+                    let retry_tx = retry_tx.clone();
                     let jobs_clone = Arc::clone(&jobs_arc);
                     let cancel_flag_clone = Arc::clone(&cancel_flag);
 
                     let handle = thread::spawn(move || {
                         if cancel_flag_clone.load(Ordering::SeqCst) {
-                            let mut jobs_lock = jobs_clone.lock().unwrap();
-                            if let Some(job) = jobs_lock.iter_mut().find(|j| j.video_path == job_path) {
-                                job.status = JobStatus::Failed("Cancelled".to_string());
-                            }
+                            jobs_clone.lock().unwrap().update(idx, |job| job.status = JobStatus::Failed("Cancelled".to_string()));
                             return;
                         }
 
                         debug!("Processing video: {}", job_path.display());
 
-                        // Create cache directory and set environment variables to fix DBM cache issues on Windows
-                        let cache_dir = PythonManager::ensure_cache_dir().unwrap_or_else(|_| std::env::temp_dir().join("subliminal_cache"));
-                        let mut env_vars = std::collections::HashMap::<String, String>::new();
-                        env_vars.insert("PYTHONIOENCODING".to_string(), "utf-8".to_string());
-                        env_vars.insert("SUBLIMINAL_CACHE_DIR".to_string(), cache_dir.to_string_lossy().to_string());
-                        env_vars.insert("PYTHONHASHSEED".to_string(), "0".to_string());
-                        
-                        // Additional environment variables to help with Windows DBM cache issues
-                        #[cfg(windows)]
-                        {
-                            env_vars.insert("SUBLIMINAL_CACHE_BACKEND".to_string(), "memory".to_string());
-                            env_vars.insert("PYTHONPATH".to_string(), std::env::var("PYTHONPATH").unwrap_or_default());
-                        }
-                        
-                        // Build command arguments with multiple -l flags for each language
-                        let mut args = vec!["download"];
-                        if force_download {
-                            args.push("--force");
-                        }
-                        if overwrite_existing {
-                            args.push("--force");
-                        }
-                        for lang in &langs_clone {
-                            args.push("-l");
-                            args.push(lang);
-                        }
-                        
-                        // Run subliminal with multiple failsafes
-                        let mut all_args = args.clone();
-                        all_args.push(job_path.to_str().unwrap());
-                        
-                        debug!("Running subliminal command: subliminal {}", all_args.join(" "));
-                        
-                        let output = PythonManager::run_command_hidden("subliminal", &all_args, &env_vars)
-                            .or_else(|_| {
-                                debug!("Subliminal direct command failed, trying python -m subliminal");
-                                let mut python_args = vec!["-m", "subliminal"];
-                                python_args.extend(&all_args);
-                                PythonManager::run_command_hidden("python", &python_args, &env_vars)
-                            })
-                            .or_else(|_| {
-                                debug!("Python command failed, trying py -m subliminal");
-                                let mut python_args = vec!["-m", "subliminal"];
-                                python_args.extend(&all_args);
-                                PythonManager::run_command_hidden("py", &python_args, &env_vars)
-                            })
-                            .or_else(|_| {
-                                debug!("Py command failed, trying python3 -m subliminal");
-                                let mut python_args = vec!["-m", "subliminal"];
-                                python_args.extend(&all_args);
-                                PythonManager::run_command_hidden("python3", &python_args, &env_vars)
-                            });
-
-                        let mut jobs_lock = jobs_clone.lock().unwrap();
-                        let job_opt = jobs_lock.iter_mut().find(|j| j.video_path == job_path);
-
-                        let embedded_phrases = [
-                            "embedded", "already exists", "no need to download", "subtitle(s) already present", "has embedded subtitles", "skipping"
-                        ];
-                        if let Ok(out) = output {
-                            let stdout_str = String::from_utf8_lossy(&out.stdout).to_lowercase();
-                            let stderr_str = String::from_utf8_lossy(&out.stderr).to_lowercase();
-                            let combined_output = format!("{}\n{}", stdout_str, stderr_str).trim().to_string();
-                            let subtitle_paths = SubtitleUtils::find_all_subtitle_files(&job_path, &langs_clone);
-                            
-                            // --- LOGGING: Full Subliminal output ---
-                            info!("Subliminal output for {}:\n{}", job_path.display(), combined_output);
-                            info!("END subliminal output");
-                            
-                            if let Some(job) = job_opt {
-                                // --- LOGGING: Video name and status ---
-                                let video_name = job_path.file_name().unwrap_or_default().to_string_lossy();
-                                let status_str = match &job.status {
-                                    JobStatus::Success => "Success",
-                                    JobStatus::EmbeddedExists(_) => "Embedded",
-                                    JobStatus::Failed(_) => "Failed",
-                                    JobStatus::Pending => "Pending",
-                                    JobStatus::Running => "Running",
-                                };
-                                info!("SUBTITLE JOBS OUTPUT: {} - {}", video_name, status_str);
-                                // --- LOGGING: Subtitle file paths ---
-                                for sub_path in &subtitle_paths {
-                                    info!("SUBTITLE JOBS OUTPUT: 📄 {}", sub_path.display());
-                                }
-                                // --- END LOGGING ---
-                                
-                                if combined_output.contains("downloaded 0 subtitle") {
-                                    if !subtitle_paths.is_empty() {
-                                        // If any subtitles were downloaded, always report Success (even if ignoring embedded)
-                                        job.status = JobStatus::Success;
-                                    } else if !force_download {
-                                        // Only check for embedded if not forcing download
-                                        if let Some(lang_name) = SubtitleUtils::has_embedded_subtitle(&job_path, &langs_clone) {
-                                            job.status = JobStatus::EmbeddedExists(format!("Embedded {} subtitles already exist (no external subtitles found online)", lang_name));
-                                        } else if embedded_phrases.iter().any(|phrase| combined_output.contains(phrase)) {
-                                            let lang_code = langs_clone.get(0).cloned().unwrap_or_else(|| "unknown".to_string());
-                                            let lang_name = SubtitleUtils::language_code_to_name(&lang_code).to_string();
-                                            job.status = JobStatus::EmbeddedExists(format!("Embedded {} subtitles already exist (no external subtitles found online)", lang_name));
-                                        } else {
-                                            job.status = JobStatus::Failed("No subtitles found (no embedded or external subtitles available)".to_string());
-                                        }
-                                    } else {
-                                        // Forced, but nothing downloaded
-                                        job.status = JobStatus::Failed("No subtitles found online".to_string());
-                                    }
-                                } else if combined_output.contains("error") || combined_output.contains("failed") {
-                                    // Check if this is a DBM cache error (which is often recoverable)
-                                    if combined_output.contains("dbm.error") || combined_output.contains("db type could not be determined") {
-                                        if !subtitle_paths.is_empty() {
-                                            // If subtitles were downloaded despite cache error, mark as success
-                                            job.status = JobStatus::Success;
-                                            warn!("DBM cache error occurred but subtitles were downloaded successfully for {}", job_path.display());
-                                        } else {
-                                            // Cache error with no subtitles - this might be recoverable
-                                            job.status = JobStatus::Failed("DBM cache error - try again later".to_string());
-                                            warn!("DBM cache error for {} - this is often recoverable", job_path.display());
-                                        }
-                                    } else if !subtitle_paths.is_empty() {
-                                        // Other error but subtitles were downloaded
-                                        job.status = JobStatus::Success;
-                                    } else {
-                                        // Other error with no subtitles
-                                        job.status = JobStatus::Failed("Subliminal error: see log".to_string());
-                                    }
-                                } else {
-                                    job.status = JobStatus::Success;
-                                }
-                                job.subtitle_paths = subtitle_paths;
-                            }
-                        } else {
-                            error!("Failed to run subliminal for {}", job_path.display());
-                            if let Some(job) = job_opt {
-                                job.status = JobStatus::Failed("Failed to run subliminal".to_string());
+                        let result = DownloadPipeline::process(&job_path, &settings, backend.as_ref(), audio_language);
+
+                        if let (Some(kind), JobStatus::Failed(error)) = (result.failure, &result.status) {
+                            if retry_policy.should_retry(kind, attempt) && !cancel_flag_clone.load(Ordering::SeqCst) {
+                                let delay = retry_policy.delay(attempt);
+                                info!("Attempt {} for {} failed ({}), retrying in {:.0?}", attempt, job_path.display(), error, delay);
+                                let error = error.clone();
+                                jobs_clone.lock().unwrap().update(idx, |job| {
+                                    job.status = JobStatus::Pending;
+                                    job.last_error = Some(error);
+                                });
+                                let _ = retry_tx.send((std::time::Instant::now() + delay, idx));
+                                return;
                             }
+                            debug!("Not retrying {}: {:?} failure after {} attempts", job_path.display(), kind, attempt);
                         }
+
+                        JobHistory::record(&job_path, &result.status, result.failure, &result.language_outcomes, &settings.selected_languages);
+                        jobs_clone.lock().unwrap().update(idx, |job| {
+                            job.last_error = None;
+                            result.apply_to(job);
+                        });
                     });
 
                     running_threads.push(handle);
@@ -584,30 +685,41 @@ impl SubtitleDownloader {
 
                 if cancel_flag.load(Ordering::SeqCst) {
                     info!("Download cancelled by user");
-                    let mut jobs_lock = jobs_arc.lock().unwrap();
-                    for job in jobs_lock.iter_mut() {
-                        if job.status == JobStatus::Pending || job.status == JobStatus::Running {
-                            job.status = JobStatus::Failed("Cancelled".to_string());
-                        }
-                    }
+                    jobs_arc.lock().unwrap().update_where(
+                        |job| job.status == JobStatus::Pending || job.status == JobStatus::Running,
+                        |job| job.status = JobStatus::Failed("Cancelled".to_string()),
+                    );
                     break;
                 }
 
                 thread::sleep(std::time::Duration::from_millis(200));
             }
             
+            JobHistory::save();
             info!("Download thread completed");
         }));
     }
 
-    /// Update cached jobs if needed (to avoid cloning every frame)
+    /// Copy the jobs that changed since the last frame into the cached list
     pub fn update_cached_jobs(&mut self) {
-        let now = std::time::Instant::now();
-        // Update cache every 500ms to improve performance
-        if now.duration_since(self.last_jobs_update) >= std::time::Duration::from_millis(500) {
-            if let Ok(jobs) = self.download_jobs.lock() {
-                self.cached_jobs = jobs.clone();
-                self.last_jobs_update = now;
+        let changes = match self.download_jobs.lock() {
+            Ok(mut store) => store.take_changes(self.cached_jobs_generation),
+            Err(_) => return,
+        };
+        match changes {
+            JobChanges::None => {}
+            JobChanges::Replaced(jobs, generation) => {
+                self.cached_jobs = jobs;
+                self.cached_jobs_generation = generation;
+                self.job_list.rebuild(&self.cached_jobs);
+            }
+            JobChanges::Updated(changed) => {
+                for (index, job) in changed {
+                    if let Some(cached) = self.cached_jobs.get_mut(index) {
+                        *cached = job;
+                    }
+                }
+                self.job_list.refresh(&self.cached_jobs);
             }
         }
     }
@@ -618,13 +730,13 @@ impl SubtitleDownloader {
             return;
         }
 
-        // Update cached jobs if needed
+        // Look at the thread before pulling changes so a finished session's last results are included
+        let thread_finished = self.download_thread_handle.as_ref().is_some_and(|handle| handle.is_finished());
         self.update_cached_jobs();
         
         // Use cached jobs for progress calculations
-        let success_count = self.cached_jobs.iter().filter(|j| j.status == JobStatus::Success || matches!(j.status, JobStatus::EmbeddedExists(_))).count();
-        let running_count = self.cached_jobs.iter().filter(|j| j.status == JobStatus::Running).count();
-        let failed_count = self.cached_jobs.iter().filter(|j| matches!(j.status, JobStatus::Failed(_))).count();
+        let totals = self.job_list.totals();
+        let (success_count, running_count, failed_count) = (totals.succeeded, totals.running, totals.failed);
         
         let previous_completed = self.downloads_completed;
         self.downloads_completed = success_count;
@@ -636,23 +748,24 @@ impl SubtitleDownloader {
         }
 
         // Check if download thread is finished
-        if let Some(handle) = &self.download_thread_handle {
-            if handle.is_finished() {
+        if self.download_thread_handle.is_some() {
+            if thread_finished {
                 self.downloading = false;
                 self.download_thread_handle = None;
                 
                 // Count completed jobs using cached jobs
-                let failed_count = self.cached_jobs.iter().filter(|j| matches!(j.status, JobStatus::Failed(_))).count();
-                let success_count = self.cached_jobs.iter().filter(|j| j.status == JobStatus::Success || matches!(j.status, JobStatus::EmbeddedExists(_))).count();
+                let deferred_count = totals.deferred;
                 
-                info!("Download session completed: {} successful, {} failed", success_count, failed_count);
+                info!("Download session completed: {} successful, {} failed, {} deferred", success_count, failed_count, deferred_count);
                 self.status = format!("Subliminal jobs completed: {} successful, {} failed", success_count, failed_count);
-                self.is_downloading = false;
+                if deferred_count > 0 {
+                    self.status.push_str(&format!(", {} deferred by provider limits", deferred_count));
+                }
             } else {
                 // Update status while downloading
                 if running_count > 0 {
                     self.status = format!("Downloading: {} completed, {} running, {} pending", 
-                        success_count, running_count, self.total_downloads - success_count - running_count);
+                        success_count, running_count, totals.pending);
                 }
             }
         }
@@ -878,8 +991,13 @@ impl SubtitleDownloader {
     pub fn get_ignore_local_extras(&self) -> bool { self.ignore_local_extras }
     pub fn get_ignore_local_extras_mut(&mut self) -> &mut bool { &mut self.ignore_local_extras }
     pub fn get_ignored_extra_folders(&self) -> usize { self.ignored_extra_folders }
+    pub fn get_skipped_for_audio_language(&self) -> usize { self.skipped_for_audio_language }
+    pub fn get_skipped_for_age(&self) -> usize { self.skipped_for_age }
+    pub fn get_skipped_for_history(&self) -> usize { self.skipped_for_history }
+    pub fn get_duplicate_videos(&self) -> &[Vec<PathBuf>] { &self.duplicate_videos }
+    pub fn get_unreadable_paths(&self) -> &[(PathBuf, String)] { &self.unreadable_paths }
     pub fn get_concurrent_downloads_mut(&mut self) -> &mut usize { &mut self.concurrent_downloads }
-    pub fn get_scan_done_receiver_mut(&mut self) -> &mut Option<Receiver<usize>> { &mut self.scan_done_receiver }
+    pub fn get_scan_done_receiver_mut(&mut self) -> &mut Option<Receiver<ScanSummary>> { &mut self.scan_done_receiver }
     pub fn get_background_check_sender(&self) -> Option<&mpsc::Sender<(bool, bool)>> { self.background_check_sender.as_ref() }
     pub fn get_background_check_handle_mut(&mut self) -> &mut Option<thread::JoinHandle<()>> { &mut self.background_check_handle }
 
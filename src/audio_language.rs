@@ -0,0 +1,99 @@
+//! Audio language rules for skipping unwanted subtitles
+//!
+//! This module reads the original audio language of a video with ffprobe and
+//! removes languages from the wanted language chains according to the
+//! configured rule, e.g. no English subtitles for English-speaking content.
+//! Scans cache the probed language in the library index and hand it to the
+//! download jobs, so a video is probed at most once per change.
+
+use std::path::Path;
+
+use crate::media_probe::MediaProbe;
+use crate::settings::AudioLanguageRule;
+use crate::subtitle_utils::SubtitleUtils;
+
+/// Filtering of wanted languages by a video's audio language
+pub struct AudioLanguageFilter;
+
+impl AudioLanguageFilter {
+    /// Language tag of the original audio track: the default track, else the first one
+    pub fn original_audio_language(video_path: &Path) -> Option<String> {
+        let streams = MediaProbe::streams(video_path, "a");
+        let original = streams.iter().find(|s| s.default).or(streams.first())?;
+        original.language.clone().filter(|l| l != "und")
+    }
+
+    /// Apply the rule to the language chains for a video with the given audio language.
+    /// A chain whose preferred language is spoken is dropped as a whole, as the audio
+    /// already covers it; other chains lose the fallbacks that are spoken.
+    /// Returns no chains when the video should be skipped entirely.
+    pub fn filter_chains(chains: &[Vec<String>], audio_language: Option<&str>, rule: AudioLanguageRule) -> Vec<Vec<String>> {
+        let Some(audio) = audio_language else {
+            // Unknown audio language, fetch everything
+            return chains.to_vec();
+        };
+        let matches = |lang: &String| SubtitleUtils::language_matches(audio, lang);
+        match rule {
+            AudioLanguageRule::Off => chains.to_vec(),
+            AudioLanguageRule::SkipMatchingLanguages => chains.iter()
+                .filter(|chain| !chain.first().is_some_and(matches))
+                .map(|chain| chain.iter().filter(|lang| !matches(lang)).cloned().collect())
+                .collect(),
+            AudioLanguageRule::OnlyForeignAudio => {
+                if chains.iter().flatten().any(matches) {
+                    Vec::new()
+                } else {
+                    chains.to_vec()
+                }
+            }
+        }
+    }
+
+    /// Apply the rule to a video's language chains, probing its audio language
+    /// unless a scan already did (`known` is None when it wasn't probed)
+    pub fn chains_for_video(video_path: &Path, chains: &[Vec<String>], rule: AudioLanguageRule, known: Option<Option<String>>) -> Vec<Vec<String>> {
+        if rule == AudioLanguageRule::Off {
+            return chains.to_vec();
+        }
+        let audio_language = known.unwrap_or_else(|| Self::original_audio_language(video_path));
+        crate::debug!("Original audio language of {}: {:?}", video_path.display(), audio_language);
+        Self::filter_chains(chains, audio_language.as_deref(), rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Chains = &'static [&'static [&'static str]];
+
+    fn chains(list: &[&[&str]]) -> Vec<Vec<String>> {
+        list.iter().map(|chain| chain.iter().map(|l| l.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn filters_chains_by_audio_language() {
+        use AudioLanguageRule::*;
+        let wanted = chains(&[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]);
+        let cases: [(AudioLanguageRule, Option<&str>, Chains); 10] = [
+            (Off, Some("eng"), &[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]),
+            // Unknown audio fetches everything
+            (SkipMatchingLanguages, None, &[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]),
+            (OnlyForeignAudio, None, &[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]),
+            // English audio covers the English chain, fallbacks included
+            (SkipMatchingLanguages, Some("eng"), &[&["pt-br", "pt", "es"], &["fr"]]),
+            (SkipMatchingLanguages, Some("en-US"), &[&["pt-br", "pt", "es"], &["fr"]]),
+            // Portuguese audio covers the pt-br chain and no chain falls back to Portuguese
+            (SkipMatchingLanguages, Some("por"), &[&["en", "es"], &["fr"]]),
+            // Spanish audio only removes Spanish fallbacks
+            (SkipMatchingLanguages, Some("spa"), &[&["en"], &["pt-br", "pt"], &["fr"]]),
+            (SkipMatchingLanguages, Some("jpn"), &[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]),
+            (OnlyForeignAudio, Some("fre"), &[]),
+            (OnlyForeignAudio, Some("jpn"), &[&["en", "es"], &["pt-br", "pt", "es"], &["fr"]]),
+        ];
+        for (rule, audio, expected) in cases {
+            assert_eq!(AudioLanguageFilter::filter_chains(&wanted, audio, rule), chains(expected), "{:?} {:?}", rule, audio);
+        }
+        assert!(AudioLanguageFilter::filter_chains(&chains(&[&["en"]]), Some("eng"), SkipMatchingLanguages).is_empty());
+    }
+}
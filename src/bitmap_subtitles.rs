@@ -0,0 +1,528 @@
+//! Decoding of bitmap subtitle streams
+//!
+//! This module turns the packets of PGS (Blu-ray) and VobSub (DVD) subtitle
+//! streams into timed black-on-white bitmaps that can be passed to OCR.
+
+use std::collections::HashMap;
+
+use crate::config::BITMAP_SUBTITLE_DEFAULT_DURATION_MS;
+use crate::media_probe::MediaPacket;
+
+/// Luminance above which an opaque pixel is treated as text fill
+const INK_MIN_LUMA: u8 = 100;
+
+/// A grayscale image where 0 is ink and 255 is background
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Bitmap {
+    /// A blank (all background) bitmap
+    pub fn blank(width: usize, height: usize) -> Self {
+        Self { width, height, pixels: vec![255; width * height] }
+    }
+
+    /// Whether the bitmap contains no ink at all
+    pub fn is_blank(&self) -> bool {
+        self.pixels.iter().all(|p| *p == 255)
+    }
+
+    /// Copy with a background border and integer upscaling, which helps OCR on small DVD text
+    pub fn padded_scaled(&self, padding: usize, scale: usize) -> Self {
+        let scale = scale.max(1);
+        let width = self.width * scale + padding * 2;
+        let height = self.height * scale + padding * 2;
+        let mut out = Self::blank(width, height);
+        for y in 0..self.height * scale {
+            for x in 0..self.width * scale {
+                out.pixels[(y + padding) * width + x + padding] = self.pixels[(y / scale) * self.width + x / scale];
+            }
+        }
+        out
+    }
+
+    /// Encode as a binary PGM image, which tesseract reads without extra libraries
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut data = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        data.extend_from_slice(&self.pixels);
+        data
+    }
+}
+
+/// A bitmap shown for a time range
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapCue {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub bitmap: Bitmap,
+}
+
+/// Read a big-endian u16
+fn be_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]))
+}
+
+/// A decoded PGS object (run-length encoded palette indices)
+#[derive(Default)]
+struct PgsObject {
+    width: usize,
+    height: usize,
+    rle: Vec<u8>,
+}
+
+/// Decoders for bitmap subtitle formats
+pub struct BitmapSubtitleDecoder;
+
+impl BitmapSubtitleDecoder {
+    /// Close the cue that is still on screen when a new display set starts
+    fn close_open_cue(cues: &mut [BitmapCue], open: &mut bool, end_ms: i64) {
+        if *open {
+            if let Some(last) = cues.last_mut() {
+                last.end_ms = end_ms.max(last.start_ms);
+            }
+            *open = false;
+        }
+    }
+
+    /// Decode PGS run-length data into palette indices
+    fn decode_pgs_rle(object: &PgsObject) -> Vec<u8> {
+        let mut indices = vec![0u8; object.width * object.height];
+        let (mut x, mut y, mut pos) = (0usize, 0usize, 0usize);
+        let data = &object.rle;
+        let mut put = |x: &mut usize, y: usize, color: u8, len: usize| {
+            for _ in 0..len {
+                if *x < object.width && y < object.height {
+                    indices[y * object.width + *x] = color;
+                }
+                *x += 1;
+            }
+        };
+        while pos < data.len() && y < object.height {
+            let b = data[pos];
+            pos += 1;
+            if b != 0 {
+                put(&mut x, y, b, 1);
+                continue;
+            }
+            let Some(&flags) = data.get(pos) else { break };
+            pos += 1;
+            if flags == 0 {
+                // End of line
+                x = 0;
+                y += 1;
+                continue;
+            }
+            let len = if flags & 0x40 != 0 {
+                let low = data.get(pos).copied().unwrap_or(0) as usize;
+                pos += 1;
+                (((flags & 0x3F) as usize) << 8) | low
+            } else {
+                (flags & 0x3F) as usize
+            };
+            let color = if flags & 0x80 != 0 {
+                let c = data.get(pos).copied().unwrap_or(0);
+                pos += 1;
+                c
+            } else {
+                0
+            };
+            put(&mut x, y, color, len);
+        }
+        indices
+    }
+
+    /// Decode the packets of a PGS (hdmv_pgs_subtitle) stream
+    pub fn decode_pgs(packets: &[MediaPacket]) -> Vec<BitmapCue> {
+        // Palette entries are (luma, alpha)
+        let mut palettes: HashMap<u8, [(u8, u8); 256]> = HashMap::new();
+        let mut objects: HashMap<u16, PgsObject> = HashMap::new();
+        let mut cues: Vec<BitmapCue> = Vec::new();
+        let mut open = false;
+
+        for packet in packets {
+            let pts_ms = (packet.pts_secs * 1000.0).round() as i64;
+            let mut data: &[u8] = &packet.data;
+            let mut composition: Vec<(u16, usize, usize)> = Vec::new();
+            let mut palette_id = 0u8;
+            let mut has_composition = false;
+
+            while data.len() >= 3 {
+                // Segments extracted from .sup files carry a "PG" timestamp header
+                if data.starts_with(b"PG") && data.len() >= 13 {
+                    data = &data[10..];
+                }
+                let kind = data[0];
+                let size = be_u16(data, 1).unwrap_or(0) as usize;
+                let Some(payload) = data.get(3..3 + size) else { break };
+                data = &data[3 + size..];
+
+                match kind {
+                    // Palette definition
+                    0x14 if payload.len() >= 2 => {
+                        let palette = palettes.entry(payload[0]).or_insert([(0, 0); 256]);
+                        for entry in payload[2..].chunks_exact(5) {
+                            palette[entry[0] as usize] = (entry[1], entry[4]);
+                        }
+                    }
+                    // Object definition, possibly split over several segments
+                    0x15 if payload.len() >= 4 => {
+                        let id = be_u16(payload, 0).unwrap_or(0);
+                        let first = payload[3] & 0x80 != 0;
+                        if first && payload.len() >= 11 {
+                            objects.insert(id, PgsObject {
+                                width: be_u16(payload, 7).unwrap_or(0) as usize,
+                                height: be_u16(payload, 9).unwrap_or(0) as usize,
+                                rle: payload[11..].to_vec(),
+                            });
+                        } else if let Some(object) = objects.get_mut(&id) {
+                            object.rle.extend_from_slice(&payload[4..]);
+                        }
+                    }
+                    // Presentation composition
+                    0x16 if payload.len() >= 11 => {
+                        has_composition = true;
+                        palette_id = payload[9];
+                        let count = payload[10] as usize;
+                        let mut pos = 11;
+                        for _ in 0..count {
+                            let (Some(id), Some(x), Some(y)) = (be_u16(payload, pos), be_u16(payload, pos + 4), be_u16(payload, pos + 6)) else { break };
+                            let cropped = payload.get(pos + 3).is_some_and(|f| f & 0x40 != 0);
+                            composition.push((id, x as usize, y as usize));
+                            pos += if cropped { 16 } else { 8 };
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if !has_composition {
+                continue;
+            }
+            Self::close_open_cue(&mut cues, &mut open, pts_ms);
+            if composition.is_empty() {
+                continue;
+            }
+
+            let Some(palette) = palettes.get(&palette_id) else { continue };
+            let placed: Vec<(&PgsObject, usize, usize)> = composition.iter()
+                .filter_map(|(id, x, y)| objects.get(id).map(|o| (o, *x, *y)))
+                .filter(|(o, _, _)| o.width > 0 && o.height > 0)
+                .collect();
+            if placed.is_empty() {
+                continue;
+            }
+            let left = placed.iter().map(|(_, x, _)| *x).min().unwrap_or(0);
+            let top = placed.iter().map(|(_, _, y)| *y).min().unwrap_or(0);
+            let right = placed.iter().map(|(o, x, _)| x + o.width).max().unwrap_or(0);
+            let bottom = placed.iter().map(|(o, _, y)| y + o.height).max().unwrap_or(0);
+            let mut bitmap = Bitmap::blank(right - left, bottom - top);
+            for (object, x, y) in placed {
+                let indices = Self::decode_pgs_rle(object);
+                for oy in 0..object.height {
+                    for ox in 0..object.width {
+                        let (luma, alpha) = palette[indices[oy * object.width + ox] as usize];
+                        if alpha >= 128 && luma >= INK_MIN_LUMA {
+                            bitmap.pixels[(y - top + oy) * bitmap.width + (x - left + ox)] = 0;
+                        }
+                    }
+                }
+            }
+            if bitmap.is_blank() {
+                continue;
+            }
+            let end_ms = packet.duration_secs
+                .filter(|d| *d > 0.0)
+                .map(|d| pts_ms + (d * 1000.0).round() as i64)
+                .unwrap_or(pts_ms + BITMAP_SUBTITLE_DEFAULT_DURATION_MS);
+            cues.push(BitmapCue { start_ms: pts_ms, end_ms, bitmap });
+            open = true;
+        }
+        cues
+    }
+
+    /// Parse the 16-colour palette from a VobSub .idx header ("palette: 000000, ffffff, ...")
+    pub fn parse_idx_palette(idx: &[u8]) -> Option<[u8; 16]> {
+        let text = String::from_utf8_lossy(idx);
+        let line = text.lines().find_map(|l| l.trim().strip_prefix("palette:"))?;
+        let mut lumas = [0u8; 16];
+        let mut count = 0;
+        for (i, entry) in line.split(',').take(16).enumerate() {
+            let rgb = u32::from_str_radix(entry.trim(), 16).ok()?;
+            let (r, g, b) = ((rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF);
+            lumas[i] = ((299 * r + 587 * g + 114 * b) / 1000) as u8;
+            count += 1;
+        }
+        if count == 16 { Some(lumas) } else { None }
+    }
+
+    /// Decode one interlaced field line of VobSub nibble run-length data
+    fn decode_vobsub_line(data: &[u8], nibble: &mut usize, width: usize, out: &mut [u8]) {
+        let read = |n: &mut usize| -> u16 {
+            let byte = data.get(*n / 2).copied().unwrap_or(0);
+            let value = if n.is_multiple_of(2) { byte >> 4 } else { byte & 0x0F };
+            *n += 1;
+            value as u16
+        };
+        let mut x = 0;
+        while x < width {
+            let mut code = read(nibble);
+            if code < 0x4 {
+                code = (code << 4) | read(nibble);
+                if code < 0x10 {
+                    code = (code << 4) | read(nibble);
+                    if code < 0x40 {
+                        code = (code << 4) | read(nibble);
+                    }
+                }
+            }
+            let color = (code & 0x3) as u8;
+            let len = match (code >> 2) as usize {
+                0 => width - x, // Fill to the end of the line
+                len => len.min(width - x),
+            };
+            out[x..x + len].fill(color);
+            x += len;
+            if *nibble / 2 >= data.len() {
+                break;
+            }
+        }
+        // Lines start on a byte boundary
+        if *nibble % 2 == 1 {
+            *nibble += 1;
+        }
+    }
+
+    /// Decode a single VobSub (DVD SPU) packet
+    fn decode_vobsub_packet(data: &[u8], lumas: Option<&[u8; 16]>) -> Option<(i64, Option<i64>, Bitmap)> {
+        let ctrl_offset = be_u16(data, 2)? as usize;
+        let (mut start_ms, mut stop_ms) = (0i64, None);
+        let mut colors = [0u8, 1, 2, 3];
+        let mut alphas = [0u8, 15, 15, 15];
+        let (mut x1, mut x2, mut y1, mut y2) = (0usize, 0usize, 0usize, 0usize);
+        let (mut field_offsets, mut pos) = ([0usize; 2], ctrl_offset);
+
+        loop {
+            let date = be_u16(data, pos)? as i64;
+            let next = be_u16(data, pos + 2)? as usize;
+            let delay_ms = date * 1024 / 90;
+            let mut p = pos + 4;
+            while let Some(&cmd) = data.get(p) {
+                p += 1;
+                match cmd {
+                    0x00 => {}
+                    0x01 => start_ms = delay_ms,
+                    0x02 => stop_ms = Some(delay_ms),
+                    0x03 | 0x04 => {
+                        let (b0, b1) = (*data.get(p)?, *data.get(p + 1)?);
+                        // Nibbles are ordered emphasis2, emphasis1, pattern, background
+                        let values = [b1 & 0x0F, b1 >> 4, b0 & 0x0F, b0 >> 4];
+                        if cmd == 0x03 { colors = values } else { alphas = values }
+                        p += 2;
+                    }
+                    0x05 => {
+                        let b = data.get(p..p + 6)?;
+                        x1 = ((b[0] as usize) << 4) | (b[1] as usize >> 4);
+                        x2 = (((b[1] & 0x0F) as usize) << 8) | b[2] as usize;
+                        y1 = ((b[3] as usize) << 4) | (b[4] as usize >> 4);
+                        y2 = (((b[4] & 0x0F) as usize) << 8) | b[5] as usize;
+                        p += 6;
+                    }
+                    0x06 => {
+                        field_offsets = [be_u16(data, p)? as usize, be_u16(data, p + 2)? as usize];
+                        p += 4;
+                    }
+                    _ => break, // 0xFF ends the sequence
+                }
+            }
+            if next == pos || next >= data.len() {
+                break;
+            }
+            pos = next;
+        }
+
+        if x2 < x1 || y2 < y1 {
+            return None;
+        }
+        let (width, height) = (x2 - x1 + 1, y2 - y1 + 1);
+        let pixel_data = data.get(..ctrl_offset)?;
+        let mut nibbles = [field_offsets[0] * 2, field_offsets[1] * 2];
+        let mut line = vec![0u8; width];
+        let mut bitmap = Bitmap::blank(width, height);
+        for y in 0..height {
+            Self::decode_vobsub_line(pixel_data, &mut nibbles[y % 2], width, &mut line);
+            for (x, slot) in line.iter().enumerate() {
+                let slot = *slot as usize;
+                let ink = match lumas {
+                    Some(lumas) => alphas[slot] >= 8 && lumas[colors[slot] as usize] >= INK_MIN_LUMA,
+                    // Without a palette, assume the pattern colour is the text fill
+                    None => slot == 1 && alphas[slot] >= 8,
+                };
+                if ink {
+                    bitmap.pixels[y * width + x] = 0;
+                }
+            }
+        }
+        Some((start_ms, stop_ms, bitmap))
+    }
+
+    /// Decode the packets of a VobSub (dvd_subtitle) stream
+    pub fn decode_vobsub(packets: &[MediaPacket], palette: Option<&[u8; 16]>) -> Vec<BitmapCue> {
+        let mut cues: Vec<BitmapCue> = Vec::new();
+        for (i, packet) in packets.iter().enumerate() {
+            let pts_ms = (packet.pts_secs * 1000.0).round() as i64;
+            let Some((start, stop, bitmap)) = Self::decode_vobsub_packet(&packet.data, palette) else {
+                crate::debug!("Skipping undecodable VobSub packet at {} ms", pts_ms);
+                continue;
+            };
+            if bitmap.is_blank() {
+                continue;
+            }
+            let start_ms = pts_ms + start;
+            let end_ms = stop.map(|s| pts_ms + s)
+                .or_else(|| packet.duration_secs.filter(|d| *d > 0.0).map(|d| pts_ms + (d * 1000.0).round() as i64))
+                .or_else(|| packets.get(i + 1).map(|p| (p.pts_secs * 1000.0).round() as i64))
+                .unwrap_or(start_ms + BITMAP_SUBTITLE_DEFAULT_DURATION_MS);
+            cues.push(BitmapCue { start_ms, end_ms: end_ms.max(start_ms), bitmap });
+        }
+        cues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PGS segment: type, big-endian size and payload
+    fn segment(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![kind];
+        data.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// Presentation composition placing the given objects at (x, y)
+    fn pgs_composition(objects: &[(u16, u16, u16)]) -> Vec<u8> {
+        let mut payload = vec![0x07, 0x80, 0x04, 0x38, 0x10, 0x00, 0x01, 0x80, 0x00, 0x00, objects.len() as u8];
+        for (id, x, y) in objects {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&[0x00, 0x00]);
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+        }
+        segment(0x16, &payload)
+    }
+
+    /// Segments of a display set showing a 4x2 object: "##.." over "####"
+    fn pgs_display_set_segments() -> Vec<Vec<u8>> {
+        // Palette 0: entry 0 transparent, entry 1 opaque white
+        let palette = segment(0x14, &[0x00, 0x00, 0x00, 0x10, 0x80, 0x80, 0x00, 0x01, 0xEB, 0x80, 0x80, 0xFF]);
+        let rle = [0x01, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x84, 0x01, 0x00, 0x00];
+        let mut object = vec![0x00, 0x00, 0x00, 0xC0];
+        object.extend_from_slice(&((rle.len() + 4) as u32).to_be_bytes()[1..]);
+        object.extend_from_slice(&[0x00, 0x04, 0x00, 0x02]);
+        object.extend_from_slice(&rle);
+        vec![pgs_composition(&[(0, 100, 900)]), palette, segment(0x15, &object), segment(0x80, &[])]
+    }
+
+    fn pgs_display_set() -> Vec<u8> {
+        pgs_display_set_segments().concat()
+    }
+
+    fn packet(pts_secs: f64, data: Vec<u8>) -> MediaPacket {
+        MediaPacket { pts_secs, duration_secs: None, data }
+    }
+
+    #[test]
+    fn decodes_pgs_display_sets() {
+        let mut clear = pgs_composition(&[]);
+        clear.extend(segment(0x80, &[]));
+        let packets = [packet(1.5, pgs_display_set()), packet(3.25, clear)];
+        let cues = BitmapSubtitleDecoder::decode_pgs(&packets);
+        assert_eq!(cues, vec![BitmapCue {
+            start_ms: 1500,
+            end_ms: 3250,
+            bitmap: Bitmap { width: 4, height: 2, pixels: vec![0, 0, 255, 255, 0, 0, 0, 0] },
+        }]);
+    }
+
+    #[test]
+    fn decodes_pgs_segments_with_sup_headers() {
+        let mut data = Vec::new();
+        for segment in pgs_display_set_segments() {
+            data.extend_from_slice(b"PG");
+            data.extend_from_slice(&[0; 8]);
+            data.extend(segment);
+        }
+        let cues = BitmapSubtitleDecoder::decode_pgs(&[packet(2.0, data)]);
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].end_ms, 2000 + BITMAP_SUBTITLE_DEFAULT_DURATION_MS);
+    }
+
+    #[test]
+    fn skips_pgs_without_visible_objects() {
+        let packets = [packet(1.0, pgs_composition(&[])), packet(2.0, pgs_composition(&[(7, 0, 0)]))];
+        assert!(BitmapSubtitleDecoder::decode_pgs(&packets).is_empty());
+    }
+
+    /// A VobSub packet showing "##.." over "####" from 0 to 512 ms
+    fn vobsub_packet(control: &[u8]) -> Vec<u8> {
+        let pixels = [0x98, 0x11];
+        let ctrl_offset = 4 + pixels.len();
+        let first_len = 4 + control.len() + 1 + 7 + 5 + 1;
+        let second = ctrl_offset + first_len;
+        let mut data = vec![0, 0];
+        data.extend_from_slice(&(ctrl_offset as u16).to_be_bytes());
+        data.extend_from_slice(&pixels);
+        // Start, optional colour commands, area (0,0)-(3,1), field offsets 4 and 5
+        data.extend_from_slice(&[0x00, 0x00]);
+        data.extend_from_slice(&(second as u16).to_be_bytes());
+        data.extend_from_slice(control);
+        data.push(0x01);
+        data.extend_from_slice(&[0x05, 0x00, 0x00, 0x03, 0x00, 0x00, 0x01]);
+        data.extend_from_slice(&[0x06, 0x00, 0x04, 0x00, 0x05]);
+        data.push(0xFF);
+        // Stop after 45 ticks of 1024/90 ms
+        data.extend_from_slice(&[0x00, 0x2D]);
+        data.extend_from_slice(&(second as u16).to_be_bytes());
+        data.extend_from_slice(&[0x02, 0xFF]);
+        let size = data.len() as u16;
+        data[..2].copy_from_slice(&size.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_vobsub_packets() {
+        let expected = Bitmap { width: 4, height: 2, pixels: vec![0, 0, 255, 255, 0, 0, 0, 0] };
+        let cues = BitmapSubtitleDecoder::decode_vobsub(&[packet(2.0, vobsub_packet(&[]))], None);
+        assert_eq!(cues, vec![BitmapCue { start_ms: 2000, end_ms: 2512, bitmap: expected.clone() }]);
+
+        // With a palette, slot 1 maps to colour 15 (white) and slot 0 to colour 0 (black)
+        let mut idx = String::from("# VobSub index file\npalette: ");
+        let colours: Vec<&str> = (0..16).map(|i| if i == 15 { "ffffff" } else { "000000" }).collect();
+        idx.push_str(&colours.join(", "));
+        let palette = BitmapSubtitleDecoder::parse_idx_palette(idx.as_bytes()).unwrap();
+        assert_eq!(palette[15], 255);
+        let control = [0x03, 0x00, 0xF0, 0x04, 0x0F, 0xF0];
+        let cues = BitmapSubtitleDecoder::decode_vobsub(&[packet(2.0, vobsub_packet(&control))], Some(&palette));
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].bitmap, expected);
+    }
+
+    #[test]
+    fn rejects_incomplete_idx_palettes() {
+        assert!(BitmapSubtitleDecoder::parse_idx_palette(b"palette: 000000, ffffff").is_none());
+        assert!(BitmapSubtitleDecoder::parse_idx_palette(b"size: 720x480").is_none());
+    }
+
+    #[test]
+    fn pads_and_scales_bitmaps() {
+        let bitmap = Bitmap { width: 2, height: 1, pixels: vec![0, 255] };
+        let scaled = bitmap.padded_scaled(1, 2);
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert_eq!(&scaled.pixels[6..12], &[255, 0, 0, 255, 255, 255]);
+        assert!(scaled.to_pgm().starts_with(b"P5\n6 4\n255\n"));
+    }
+}
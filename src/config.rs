@@ -22,6 +22,89 @@ pub static DEFAULT_CONCURRENT_DOWNLOADS: usize = 25;
 /// Maximum concurrent downloads
 pub static MAX_CONCURRENT_DOWNLOADS: usize = 100;
 
+/// Providers shipped with Subliminal, used to exclude a provider when retrying
+pub static SUBLIMINAL_PROVIDERS: &[&str] = &[
+    "addic7ed", "bsplayer", "gestdown", "napiprojekt", "opensubtitles",
+    "opensubtitlescom", "podnapisi", "subtitulamos", "tvsubtitles"
+];
+
+/// Providers that accept a username and password, passed to Subliminal as --<provider>
+pub static ACCOUNT_PROVIDERS: &[&str] = &["addic7ed", "opensubtitles", "opensubtitlescom"];
+
+/// Default base URL of the OpenSubtitles.com REST API
+pub static OPENSUBTITLES_API_URL: &str = "https://api.opensubtitles.com/api/v1";
+
+/// Default minimum Subliminal score (percent) for movies, which have fewer matchable properties
+pub static DEFAULT_MIN_SCORE_MOVIE: u32 = 50;
+
+/// Default minimum Subliminal score (percent) for episodes
+pub static DEFAULT_MIN_SCORE_EPISODE: u32 = 70;
+
+/// Default limit on Subliminal runs per minute across all download threads (0 = unlimited, the limit is opt-in)
+pub static DEFAULT_REQUESTS_PER_MINUTE: u32 = 0;
+
+/// Largest accepted requests-per-minute limit
+pub static MAX_REQUESTS_PER_MINUTE: u32 = 600;
+
+/// Seconds a provider is paused after it rejects requests for coming too fast
+pub static RATE_LIMIT_COOLDOWN_SECS: u64 = 300;
+
+/// Largest accepted age filter in days
+pub static MAX_AGE_FILTER_DAYS: u32 = 3650;
+
+/// Default number of attempts for a job that fails with a transient error
+pub static DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+
+/// Largest accepted number of attempts per job
+pub static MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Default delay in seconds before the first retry, doubled for each further retry
+pub static DEFAULT_RETRY_DELAY_SECS: u32 = 10;
+
+/// Largest accepted first retry delay in seconds
+pub static MAX_RETRY_DELAY_SECS: u32 = 600;
+
+/// Upper bound in seconds on the delay between two attempts
+pub static MAX_RETRY_BACKOFF_SECS: u64 = 900;
+
+/// Default number of threads reading directories during a scan
+pub static DEFAULT_SCAN_THREADS: usize = 4;
+
+/// Largest accepted number of scan threads
+pub static MAX_SCAN_THREADS: usize = 32;
+
+/// Days before a video that failed 1, 2, or 3+ times in a row is searched again
+pub static HISTORY_RECHECK_DAYS: [i64; 3] = [1, 7, 30];
+
+/// Attempts kept in the job history per video
+pub static MAX_HISTORY_ENTRIES_PER_VIDEO: usize = 50;
+
+/// Sidecar subtitle extensions that count as an existing subtitle
+pub static SUBTITLE_EXTENSIONS: [&str; 5] = ["srt", "sub", "ssa", "ass", "vtt"];
+
+/// Plex local extras folders skipped when "Ignore Extra Folders" is on
+pub static LOCAL_EXTRAS_FOLDERS: [&str; 8] = [
+    "Behind The Scenes", "Deleted Scenes", "Featurettes", "Interviews", "Scenes", "Shorts", "Trailers", "Other",
+];
+
+/// Seconds a subtitle may run past the end of the video before it is flagged
+pub static SUBTITLE_DURATION_OVERRUN_SECS: f64 = 60.0;
+
+/// Minimum share of the video duration the last subtitle cue must reach
+pub static SUBTITLE_MIN_COVERAGE_RATIO: f64 = 0.6;
+
+/// Maximum share of overlapping cues before a subtitle is flagged
+pub static SUBTITLE_MAX_OVERLAP_RATIO: f64 = 0.1;
+
+/// Maximum share of U+FFFD replacement characters before a subtitle is flagged
+pub static SUBTITLE_MAX_REPLACEMENT_CHAR_RATIO: f64 = 0.02;
+
+/// Largest constant offset between two languages that dual subtitles will correct
+pub static DUAL_SUBTITLE_MAX_OFFSET_MS: i64 = 5_000;
+
+/// Maximum start time distance for snapping a secondary cue to a primary cue
+pub static DUAL_SUBTITLE_MATCH_WINDOW_MS: i64 = 750;
+
 /// Python installer URL (Windows-specific)
 #[cfg(windows)]
 pub static PYTHON_INSTALLER_URL: &str = "https://www.python.org/ftp/python/3.13.5/python-3.13.5-amd64.exe";
@@ -34,6 +117,9 @@ pub static PYTHON_INSTALLER_URL: &str = "https://www.python.org/ftp/python/3.13.
 #[cfg(target_os = "linux")]
 pub static PYTHON_INSTALLER_URL: &str = "https://www.python.org/ftp/python/3.13.5/python-3.13.5-amd64.exe";
 
+/// Display time for bitmap subtitles that carry no end time of their own
+pub static BITMAP_SUBTITLE_DEFAULT_DURATION_MS: i64 = 5_000;
+
 /// Default window size
 pub static WINDOW_SIZE: [f32; 2] = [800.0, 580.0];
 
@@ -3,31 +3,285 @@
 //! This module contains the core data structures including download jobs,
 //! application state, and shared data types used throughout the application.
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use crate::filename_parser::{FilenameParser, VideoMetadata};
+use crate::job_groups::JobListView;
+use crate::provider_accounts::ProviderAccounts;
+use crate::settings::{AudioLanguageSettings, BackendSettings, DualSubtitleSettings, EmbeddedSubtitleSettings, HearingImpairedSettings, MuxSettings, OcrSettings, ProviderSettings, RetrySettings, ScanSettings, SpeechToTextSettings, TranslationSettings, ValidationSettings};
+
 /// Type alias for shared download jobs
-pub type DownloadJobs = Arc<Mutex<Vec<DownloadJob>>>;
+pub type DownloadJobs = Arc<Mutex<JobStore>>;
 
 /// Type alias for shared paths
 pub type SharedPaths = Arc<Mutex<Vec<PathBuf>>>;
 
+/// Login test state per provider: None while running, then the result
+pub type LoginTests = Arc<Mutex<BTreeMap<String, Option<Result<(), String>>>>>;
+
 /// Status of a subtitle download job
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum JobStatus {
     Pending,
     Running,
     Success,
+    /// Subtitles were downloaded and muxed into the video container
+    Muxed,
     EmbeddedExists(String), // full message
+    /// A provider hit its quota or rate limit; the job can be retried later
+    Deferred(String),
     Failed(String),
 }
 
+impl JobStatus {
+    /// Whether the job finished with subtitles available
+    pub fn is_success(&self) -> bool {
+        matches!(self, JobStatus::Success | JobStatus::Muxed | JobStatus::EmbeddedExists(_))
+    }
+}
+
+/// How the subtitle for a language was obtained
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubtitleOrigin {
+    Downloaded,
+    /// Extracted from an embedded text track
+    Extracted,
+    /// Converted from an embedded bitmap track with OCR
+    Ocr,
+    /// Machine-generated from the audio track with speech recognition
+    SpeechToText,
+    /// Machine-translated from another downloaded language
+    Translated,
+}
+
+impl SubtitleOrigin {
+    /// Short note shown for subtitles that weren't written by a person
+    pub fn machine_generated_note(&self) -> Option<&'static str> {
+        match self {
+            SubtitleOrigin::SpeechToText => Some("machine-generated from audio"),
+            SubtitleOrigin::Translated => Some("machine-translated"),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a download job for a single selected language
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguageOutcome {
+    pub language: String,
+    /// Sidecar subtitle for this language, if one exists after the job
+    pub subtitle_path: Option<PathBuf>,
+    pub origin: SubtitleOrigin,
+    pub provider: Option<String>,
+    /// Match score in percent reported by the provider
+    pub score: Option<f64>,
+}
+
+impl LanguageOutcome {
+    /// Whether a subtitle exists for this language
+    pub fn succeeded(&self) -> bool {
+        self.subtitle_path.is_some()
+    }
+}
+
 /// Represents a single subtitle download job
 #[derive(Clone)]
 pub struct DownloadJob {
     pub video_path: PathBuf,
+    /// Title, season and episode read from the file and folder names
+    pub metadata: VideoMetadata,
     pub status: JobStatus,
     pub subtitle_paths: Vec<PathBuf>,
+    pub warnings: Vec<String>,
+    pub language_outcomes: Vec<LanguageOutcome>,
+    /// Attempts started so far (0 before the first run)
+    pub attempt: u32,
+    /// Failure of the previous attempt while the job waits to be retried
+    pub last_error: Option<String>,
+    /// Original audio language found by the scan, so the pipeline needn't probe again
+    pub audio_language: Option<Option<String>>,
+}
+
+impl DownloadJob {
+    /// Create a pending job for a video
+    pub fn new(video_path: PathBuf) -> Self {
+        Self {
+            metadata: FilenameParser::parse(&video_path),
+            video_path,
+            status: JobStatus::Pending,
+            subtitle_paths: Vec::new(),
+            warnings: Vec::new(),
+            language_outcomes: Vec::new(),
+            attempt: 0,
+            last_error: None,
+            audio_language: None,
+        }
+    }
+}
+
+/// Changes to a `JobStore` since the reader last looked
+pub enum JobChanges {
+    None,
+    /// The whole list was replaced; carries the new list and its generation
+    Replaced(Vec<DownloadJob>, u64),
+    /// Individual jobs changed, by index
+    Updated(Vec<(usize, DownloadJob)>),
+}
+
+/// Job list shared with the download threads that records which jobs changed,
+/// so the GUI copies only those instead of the whole list
+#[derive(Default)]
+pub struct JobStore {
+    jobs: Vec<DownloadJob>,
+    /// Bumped whenever the whole list is replaced
+    generation: u64,
+    changed: BTreeSet<usize>,
+}
+
+impl JobStore {
+    pub fn jobs(&self) -> &[DownloadJob] {
+        &self.jobs
+    }
+
+    /// Replace the whole list, e.g. when a download session starts
+    pub fn replace(&mut self, jobs: Vec<DownloadJob>) {
+        self.jobs = jobs;
+        self.generation += 1;
+        self.changed.clear();
+    }
+
+    /// Change a job and remember that it changed
+    pub fn update(&mut self, index: usize, change: impl FnOnce(&mut DownloadJob)) {
+        if let Some(job) = self.jobs.get_mut(index) {
+            change(job);
+            self.changed.insert(index);
+        }
+    }
+
+    /// Change every job matching a condition
+    pub fn update_where(&mut self, condition: impl Fn(&DownloadJob) -> bool, change: impl Fn(&mut DownloadJob)) {
+        for (index, job) in self.jobs.iter_mut().enumerate() {
+            if condition(job) {
+                change(job);
+                self.changed.insert(index);
+            }
+        }
+    }
+
+    /// Changes since the reader last saw `seen_generation`: the whole list if it
+    /// was replaced since, otherwise copies of the jobs that changed
+    pub fn take_changes(&mut self, seen_generation: u64) -> JobChanges {
+        if seen_generation != self.generation {
+            self.changed.clear();
+            return JobChanges::Replaced(self.jobs.clone(), self.generation);
+        }
+        if self.changed.is_empty() {
+            return JobChanges::None;
+        }
+        let changed = std::mem::take(&mut self.changed);
+        JobChanges::Updated(changed.into_iter().filter_map(|i| Some((i, self.jobs.get(i)?.clone()))).collect())
+    }
+}
+
+/// A video the scan found missing subtitles, with the reason
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingVideo {
+    pub path: PathBuf,
+    /// e.g. "Missing English, French" or "Overwriting existing subtitles"
+    pub reason: String,
+}
+
+/// One row of the scan preview: a folder or a video in it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScanPreviewRow {
+    /// Index into `ScanPreview::folders`
+    Folder(usize),
+    /// Index into `ScanPreview::videos`
+    Video(usize),
+}
+
+/// Videos missing subtitles awaiting review before downloads start
+#[derive(Clone, Debug, Default)]
+pub struct ScanPreview {
+    pub videos: Vec<MissingVideo>,
+    pub selected: Vec<bool>,
+    /// Folders with the indexes of their videos, sorted by path
+    pub folders: Vec<(PathBuf, Vec<usize>)>,
+    /// Folders followed by their videos, for rendering
+    pub rows: Vec<ScanPreviewRow>,
+}
+
+impl ScanPreview {
+    /// Preview with every video selected
+    pub fn new(videos: Vec<MissingVideo>) -> Self {
+        let mut by_folder: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+        for (index, video) in videos.iter().enumerate() {
+            let folder = video.path.parent().map(PathBuf::from).unwrap_or_default();
+            by_folder.entry(folder).or_default().push(index);
+        }
+        let folders: Vec<(PathBuf, Vec<usize>)> = by_folder.into_iter().collect();
+        let rows = folders.iter().enumerate()
+            .flat_map(|(folder, (_, indexes))| {
+                std::iter::once(ScanPreviewRow::Folder(folder)).chain(indexes.iter().map(|&i| ScanPreviewRow::Video(i)))
+            })
+            .collect();
+        Self { selected: vec![true; videos.len()], videos, folders, rows }
+    }
+
+    /// Number of selected videos in a folder
+    pub fn folder_selected_count(&self, folder: usize) -> usize {
+        self.folders.get(folder)
+            .map(|(_, indexes)| indexes.iter().filter(|&&i| self.selected[i]).count())
+            .unwrap_or(0)
+    }
+
+    /// Select or deselect every video in a folder
+    pub fn set_folder_selected(&mut self, folder: usize, selected: bool) {
+        if let Some((_, indexes)) = self.folders.get(folder) {
+            for &i in indexes {
+                self.selected[i] = selected;
+            }
+        }
+    }
+
+    pub fn set_all_selected(&mut self, selected: bool) {
+        self.selected.iter_mut().for_each(|s| *s = selected);
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.selected.iter().filter(|s| **s).count()
+    }
+
+    /// Paths of the selected videos
+    pub fn selected_paths(&self) -> Vec<PathBuf> {
+        self.videos.iter()
+            .zip(&self.selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(video, _)| video.path.clone())
+            .collect()
+    }
+}
+
+/// Counts reported by a finished folder scan
+#[derive(Clone, Debug, Default)]
+pub struct ScanSummary {
+    pub ignored_extra_folders: usize,
+    /// Videos skipped because their audio is already in a wanted language
+    pub skipped_for_audio_language: usize,
+    /// Videos skipped because they are older than the age filter
+    pub skipped_for_age: usize,
+    /// Videos skipped because their subtitles weren't found recently
+    pub skipped_for_history: usize,
+    /// Groups of videos with identical content found in different places
+    pub duplicate_videos: Vec<Vec<PathBuf>>,
+    /// Videos missing subtitles and why, for the scan preview
+    pub missing_videos: Vec<MissingVideo>,
+    /// Folders that couldn't be read, with the error
+    pub unreadable_paths: Vec<(PathBuf, String)>,
+    /// Original audio language of the videos the scan probed
+    pub audio_languages: HashMap<PathBuf, Option<String>>,
 }
 
 /// Main application state for the subtitle downloader
@@ -35,7 +289,6 @@ pub struct SubtitleDownloader {
     // Download state
     pub downloads_completed: usize,
     pub total_downloads: usize,
-    pub is_downloading: bool,
     pub downloading: bool,
     pub download_thread_handle: Option<std::thread::JoinHandle<()>>,
     pub cancel_flag: Arc<std::sync::atomic::AtomicBool>,
@@ -53,19 +306,51 @@ pub struct SubtitleDownloader {
 
     // User settings
     pub selected_languages: Vec<String>,
+    pub language_fallbacks: BTreeMap<String, Vec<String>>,
+    pub forced_languages: Vec<String>,
     pub force_download: bool,
     pub overwrite_existing: bool,
     pub concurrent_downloads: usize,
     pub ignore_local_extras: bool,
+    pub auto_start_downloads: bool,
     pub keep_dropdown_open: bool,
+    pub backend: BackendSettings,
+    pub providers: ProviderSettings,
+    pub retry: RetrySettings,
+    pub scan: ScanSettings,
+    pub validation: ValidationSettings,
+    pub hearing_impaired: HearingImpairedSettings,
+    pub dual_subtitles: DualSubtitleSettings,
+    pub embedded: EmbeddedSubtitleSettings,
+    pub mux: MuxSettings,
+    pub ocr: OcrSettings,
+    pub speech_to_text: SpeechToTextSettings,
+    pub translation: TranslationSettings,
+    pub audio_language: AudioLanguageSettings,
 
     // Folder and scan state
     pub folder_path: String,
     pub scanned_videos: SharedPaths,
     pub videos_missing_subs: SharedPaths,
     pub scanning: bool,
-    pub scan_done_receiver: Option<std::sync::mpsc::Receiver<usize>>,
+    pub scan_done_receiver: Option<std::sync::mpsc::Receiver<ScanSummary>>,
     pub ignored_extra_folders: usize,
+    pub skipped_for_audio_language: usize,
+    /// Original audio languages probed by the last scan, handed to the download jobs
+    pub audio_languages: HashMap<PathBuf, Option<String>>,
+    pub skipped_for_age: usize,
+    pub skipped_for_history: usize,
+    pub duplicate_videos: Vec<Vec<PathBuf>>,
+    /// Folders the last scan couldn't read, with the error
+    pub unreadable_paths: Vec<(PathBuf, String)>,
+    /// Scan results awaiting review when downloads don't start automatically
+    pub scan_preview: Option<ScanPreview>,
+    /// Video whose job history window is open
+    pub history_video: Option<PathBuf>,
+
+    // Provider logins, kept out of the settings file
+    pub provider_accounts: ProviderAccounts,
+    pub account_login_tests: LoginTests,
 
     // UI status
     pub status: String,
@@ -78,9 +363,10 @@ pub struct SubtitleDownloader {
     #[allow(dead_code)]
     pub refresh_interval: std::time::Duration,
     
-    // Cached jobs for UI rendering (to avoid cloning every frame)
+    // Cached jobs for UI rendering, updated from the job store's changes
     pub cached_jobs: Vec<DownloadJob>,
-    pub last_jobs_update: std::time::Instant,
+    pub cached_jobs_generation: u64,
+    pub job_list: JobListView,
     
     // Background installation status checking
     pub background_check_handle: Option<std::thread::JoinHandle<()>>,
@@ -91,4 +377,67 @@ pub struct SubtitleDownloader {
     pub latest_version: Option<String>,
     pub version_check_error: Option<String>,
     pub version_checked: bool,
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(names: &[&str]) -> Vec<DownloadJob> {
+        names.iter().map(|name| DownloadJob::new(PathBuf::from(name))).collect()
+    }
+
+    /// Indexes and statuses of an `Updated` change, panicking on anything else
+    fn updates(changes: JobChanges) -> Vec<(usize, JobStatus)> {
+        match changes {
+            JobChanges::Updated(changed) => changed.into_iter().map(|(index, job)| (index, job.status)).collect(),
+            JobChanges::Replaced(..) => panic!("expected updates, got a replaced list"),
+            JobChanges::None => panic!("expected updates, got none"),
+        }
+    }
+
+    #[test]
+    fn take_changes_sends_the_whole_list_after_a_generation_bump() {
+        let mut store = JobStore::default();
+        store.replace(jobs(&["a.mkv", "b.mkv"]));
+        let generation = match store.take_changes(0) {
+            JobChanges::Replaced(list, generation) => {
+                assert_eq!(list.len(), 2);
+                generation
+            }
+            _ => panic!("a reader on an older generation should get the whole list"),
+        };
+        assert!(matches!(store.take_changes(generation), JobChanges::None));
+
+        // Updates made before the next replacement are dropped with the old list
+        store.update(1, |job| job.status = JobStatus::Running);
+        store.replace(jobs(&["c.mkv"]));
+        match store.take_changes(generation) {
+            JobChanges::Replaced(list, newer) => {
+                assert_eq!(newer, generation + 1);
+                assert_eq!(list[0].video_path, PathBuf::from("c.mkv"));
+                assert_eq!(list[0].status, JobStatus::Pending);
+            }
+            _ => panic!("a reader on the previous generation should get the new list"),
+        }
+        assert!(matches!(store.take_changes(generation + 1), JobChanges::None));
+    }
+
+    #[test]
+    fn take_changes_coalesces_updates_and_ignores_stale_indexes() {
+        let mut store = JobStore::default();
+        store.replace(jobs(&["a.mkv", "b.mkv", "c.mkv"]));
+        let generation = 1;
+        let _ = store.take_changes(0);
+
+        store.update(2, |job| job.status = JobStatus::Running);
+        store.update(0, |job| job.status = JobStatus::Running);
+        store.update(2, |job| job.status = JobStatus::Success);
+        // Indexes past the end of the list change nothing
+        store.update(7, |job| job.status = JobStatus::Running);
+        assert_eq!(updates(store.take_changes(generation)), vec![(0, JobStatus::Running), (2, JobStatus::Success)]);
+        assert!(matches!(store.take_changes(generation), JobChanges::None));
+
+        store.update_where(|job| job.status == JobStatus::Pending, |job| job.status = JobStatus::Failed("Cancelled".to_string()));
+        assert_eq!(updates(store.take_changes(generation)), vec![(1, JobStatus::Failed("Cancelled".to_string()))]);
+    }
+}
@@ -0,0 +1,754 @@
+//! Per-video download pipeline
+//!
+//! This module runs every step for a single download job: extracting
+//! embedded tracks, running Subliminal for the remaining languages,
+//! validating the results and the optional post-processing steps. It
+//! returns a result that the scheduler applies to the shared job list.
+
+use std::path::{Path, PathBuf};
+
+use crate::audio_language::AudioLanguageFilter;
+use crate::config::SUBLIMINAL_PROVIDERS;
+use crate::data_structures::{DownloadJob, JobStatus, LanguageOutcome, SubtitleOrigin};
+use crate::dual_subtitles::DualSubtitleMerger;
+use crate::embedded_subtitles::{EmbeddedSubtitles, ExtractedSubtitle};
+use crate::filename_parser::FilenameParser;
+use crate::forced_subtitles::ForcedSubtitles;
+use crate::helper_functions::Utils;
+use crate::media_probe::MediaProbe;
+use crate::muxer::SubtitleMuxer;
+use crate::provider_limits::LimitKind;
+use crate::retry_policy::{FailureKind, RetryPolicy};
+use crate::ocr::SubtitleOcr;
+use crate::sdh_stripper::SdhStripper;
+use crate::sidecar_map::SidecarMap;
+use crate::speech_to_text::SpeechToText;
+use crate::settings::{DualSubtitleSettings, Settings, ValidationSettings};
+use crate::subliminal::{SubliminalOptions, SubliminalReport};
+use crate::subtitle_backend::SubtitleBackend;
+use crate::subtitle_utils::SubtitleUtils;
+use crate::subtitle_validation::SubtitleValidator;
+use crate::translation::SubtitleTranslator;
+use crate::{info, warn, error};
+
+/// Everything a finished job reports back to the job list
+pub struct JobResult {
+    pub status: JobStatus,
+    /// Why the job failed, judged from the status and the downloader's output
+    pub failure: Option<FailureKind>,
+    pub subtitle_paths: Vec<PathBuf>,
+    pub warnings: Vec<String>,
+    pub language_outcomes: Vec<LanguageOutcome>,
+}
+
+impl JobResult {
+    /// A failed job with no subtitles
+    fn failed(message: &str) -> Self {
+        let status = JobStatus::Failed(message.to_string());
+        Self {
+            failure: RetryPolicy::classify(&status, ""),
+            status,
+            subtitle_paths: Vec::new(),
+            warnings: Vec::new(),
+            language_outcomes: Vec::new(),
+        }
+    }
+
+    /// A job with nothing to do, reported as a success with an explanation
+    fn skipped(message: &str) -> Self {
+        Self {
+            status: JobStatus::Success,
+            failure: None,
+            subtitle_paths: Vec::new(),
+            warnings: vec![message.to_string()],
+            language_outcomes: Vec::new(),
+        }
+    }
+
+    /// Copy the result into a job in the shared list
+    pub fn apply_to(self, job: &mut DownloadJob) {
+        job.status = self.status;
+        job.subtitle_paths = self.subtitle_paths;
+        job.warnings = self.warnings;
+        job.language_outcomes = self.language_outcomes;
+    }
+}
+
+/// Per-video download steps
+pub struct DownloadPipeline;
+
+impl DownloadPipeline {
+    /// Run all download and post-processing steps for a single video, reusing the
+    /// original audio language when the scan already probed it
+    pub fn process(video_path: &Path, settings: &Settings, backend: &dyn SubtitleBackend, audio_language: Option<Option<String>>) -> JobResult {
+        let chains = AudioLanguageFilter::chains_for_video(
+            video_path,
+            &SubtitleUtils::language_chains(&settings.selected_languages, &settings.language_fallbacks),
+            settings.audio_language.rule,
+            audio_language,
+        );
+        if chains.is_empty() {
+            info!("Skipping {}: audio is already in a selected language", video_path.display());
+            return JobResult::skipped("Audio is already in a selected language");
+        }
+        let langs: Vec<String> = chains.iter().flatten().cloned().collect();
+
+        // Extract embedded text tracks first so those languages need no provider request
+        let extracted = if settings.embedded.extract_text_tracks && !settings.force_download {
+            Self::extract_chains(video_path, &chains, settings.overwrite_existing)
+        } else {
+            Vec::new()
+        };
+        let remaining: Vec<Vec<String>> = chains.iter()
+            .filter(|chain| !chain.iter().any(|lang| extracted.iter().any(|e| &e.language == lang && !e.forced)))
+            .cloned()
+            .collect();
+        let remaining_langs: Vec<String> = remaining.iter().flatten().cloned().collect();
+        // Listed once per job and again only after a step wrote subtitles
+        let mut sidecars = SidecarMap::for_video(video_path);
+
+        let options = SubliminalOptions {
+            languages: Vec::new(),
+            force: settings.force_download || settings.overwrite_existing,
+            providers: settings.providers.provider_args(),
+            hearing_impaired: settings.hearing_impaired.preference,
+            foreign_only: false,
+            min_score: Some(if FilenameParser::parse(video_path).is_episode() {
+                settings.providers.min_score_episode
+            } else {
+                settings.providers.min_score_movie
+            }),
+            max_age_days: Some(settings.providers.max_age_days),
+            accounts: Vec::new(),
+            requests_per_minute: settings.providers.requests_per_minute,
+            directory: None,
+        };
+        let report = if remaining.is_empty() {
+            info!("All languages extracted from embedded tracks for {}, skipping providers", video_path.display());
+            SubliminalReport::default()
+        } else {
+            match Self::download_chains(video_path, &mut sidecars, &remaining, &options, backend) {
+                Ok(report) => report,
+                Err(e) => {
+                    error!("{} download failed for {}: {}", backend.name(), video_path.display(), e);
+                    return JobResult::failed(&e);
+                }
+            }
+        };
+
+        let mut forced_warnings = Vec::new();
+        let forced_paths = Self::fetch_forced_subtitles(video_path, &sidecars, settings, &options, backend, &mut forced_warnings);
+
+        // Forced subtitles only cover foreign parts, so they skip validation and SDH stripping
+        let mut subtitle_paths = SubtitleUtils::find_all_subtitle_files(video_path, &sidecars, &langs);
+        let mut warnings = if settings.validation.enabled {
+            Self::validate_downloads(video_path, &mut sidecars, &mut subtitle_paths, &report, &options, &settings.validation, backend)
+        } else {
+            Vec::new()
+        };
+        warnings.extend(forced_warnings);
+        let ocr_converted = if settings.ocr.enabled {
+            Self::ocr_missing_languages(video_path, &sidecars, &remaining, &mut warnings)
+        } else {
+            Vec::new()
+        };
+        subtitle_paths.extend(ocr_converted.iter().map(|c| c.path.clone()));
+        if settings.hearing_impaired.strip_sdh {
+            Self::strip_sdh_variants(&mut subtitle_paths);
+        }
+        subtitle_paths.extend(forced_paths);
+        let mut language_outcomes = Self::collect_language_outcomes(video_path, &sidecars, &chains, &extracted, &ocr_converted, &report);
+
+        let mut status = if remaining.is_empty() {
+            JobStatus::Success
+        } else {
+            Self::determine_status(video_path, &report, &subtitle_paths, &remaining_langs, settings.force_download, settings.ocr.enabled)
+        };
+        for hit in &report.limit_hits {
+            warnings.push(format!("{} paused: {}", hit.provider, hit.kind.label()));
+        }
+        // Nothing found while a provider was limited: the subtitle may still be there later
+        if matches!(status, JobStatus::Failed(_)) && !report.limit_hits.is_empty() {
+            let kind = if report.limit_hits.iter().any(|h| h.kind == LimitKind::Quota) {
+                LimitKind::Quota
+            } else {
+                LimitKind::RateLimit
+            };
+            info!("Deferring {}: providers limited ({})", video_path.display(), kind.label());
+            status = JobStatus::Deferred(kind.label().to_string());
+        }
+        // Speech recognition is the last resort once nothing was found anywhere
+        let nothing_found = matches!(&status, JobStatus::Failed(msg) if msg.starts_with("No subtitles found"));
+        if settings.speech_to_text.enabled && nothing_found {
+            let transcribed = Self::transcribe_missing_languages(video_path, &mut language_outcomes, settings, &mut warnings);
+            if !transcribed.is_empty() {
+                subtitle_paths.extend(transcribed);
+                status = JobStatus::Success;
+            }
+        }
+
+        if settings.translation.enabled {
+            let translated = Self::translate_missing_languages(video_path, &mut language_outcomes, settings, &mut warnings);
+            if !translated.is_empty() {
+                subtitle_paths.extend(translated);
+                if matches!(status, JobStatus::Failed(_)) {
+                    status = JobStatus::Success;
+                }
+            }
+        }
+
+        if settings.dual_subtitles.enabled {
+            if let Some(merged) = Self::merge_dual_subtitles(video_path, &language_outcomes, &settings.dual_subtitles) {
+                subtitle_paths.push(merged);
+            }
+        }
+        if settings.mux.enabled && status == JobStatus::Success && !subtitle_paths.is_empty() {
+            if !SubtitleMuxer::supports(video_path) {
+                info!("Not muxing {}: only MKV files are supported", video_path.display());
+            } else {
+                match SubtitleMuxer::mux(video_path, &subtitle_paths, settings.mux.delete_sidecars) {
+                    Ok(muxed) if !muxed.is_empty() => {
+                        if settings.mux.delete_sidecars {
+                            subtitle_paths.retain(|p| !muxed.contains(p));
+                        }
+                        status = JobStatus::Muxed;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to mux subtitles into {}: {}", video_path.display(), e);
+                        warnings.push(format!("Muxing failed: {}", e));
+                    }
+                }
+            }
+        }
+
+        // --- LOGGING: Video name and status ---
+        let video_name = Utils::get_file_name(video_path);
+        let status_str = match &status {
+            JobStatus::Success => "Success",
+            JobStatus::Muxed => "Muxed",
+            JobStatus::EmbeddedExists(_) => "Embedded",
+            JobStatus::Deferred(_) => "Deferred",
+            JobStatus::Failed(_) => "Failed",
+            JobStatus::Pending => "Pending",
+            JobStatus::Running => "Running",
+        };
+        info!("SUBTITLE JOBS OUTPUT: {} - {}", video_name, status_str);
+        // --- LOGGING: Subtitle file paths ---
+        for sub_path in &subtitle_paths {
+            info!("SUBTITLE JOBS OUTPUT: 📄 {}", sub_path.display());
+        }
+        // --- END LOGGING ---
+
+        let failure = RetryPolicy::classify(&status, &report.combined_output);
+        JobResult { status, failure, subtitle_paths, warnings, language_outcomes }
+    }
+
+    /// Work out the job status from Subliminal's output and the subtitles found on disk
+    fn determine_status(
+        video_path: &Path,
+        report: &SubliminalReport,
+        subtitle_paths: &[PathBuf],
+        langs: &[String],
+        force_download: bool,
+        ocr_enabled: bool,
+    ) -> JobStatus {
+        let combined_output = &report.combined_output;
+        let embedded_phrases = [
+            "embedded", "already exists", "no need to download", "subtitle(s) already present", "has embedded subtitles", "skipping"
+        ];
+        if report.downloaded_nothing() {
+            if !subtitle_paths.is_empty() {
+                // If any subtitles were downloaded, always report Success (even if ignoring embedded)
+                JobStatus::Success
+            } else if !force_download {
+                // Only check for embedded if not forcing download
+                if let Some(lang_name) = SubtitleUtils::has_embedded_subtitle(video_path, langs, ocr_enabled) {
+                    JobStatus::EmbeddedExists(format!("Embedded {} subtitles already exist (no external subtitles found online)", lang_name))
+                } else if embedded_phrases.iter().any(|phrase| combined_output.contains(phrase)) {
+                    let lang_code = langs.first().cloned().unwrap_or_else(|| "unknown".to_string());
+                    let lang_name = SubtitleUtils::language_code_to_name(&lang_code).to_string();
+                    JobStatus::EmbeddedExists(format!("Embedded {} subtitles already exist (no external subtitles found online)", lang_name))
+                } else {
+                    JobStatus::Failed("No subtitles found (no embedded or external subtitles available)".to_string())
+                }
+            } else {
+                // Forced, but nothing downloaded
+                JobStatus::Failed("No subtitles found online".to_string())
+            }
+        } else if combined_output.contains("error") || combined_output.contains("failed") {
+            // Check if this is a DBM cache error (which is often recoverable)
+            if combined_output.contains("dbm.error") || combined_output.contains("db type could not be determined") {
+                if !subtitle_paths.is_empty() {
+                    // If subtitles were downloaded despite cache error, mark as success
+                    warn!("DBM cache error occurred but subtitles were downloaded successfully for {}", video_path.display());
+                    JobStatus::Success
+                } else {
+                    // Cache error with no subtitles - this might be recoverable
+                    warn!("DBM cache error for {} - this is often recoverable", video_path.display());
+                    JobStatus::Failed("DBM cache error - try again later".to_string())
+                }
+            } else if !subtitle_paths.is_empty() {
+                // Other error but subtitles were downloaded
+                JobStatus::Success
+            } else {
+                // Other error with no subtitles
+                JobStatus::Failed("Subliminal error: see log".to_string())
+            }
+        } else {
+            JobStatus::Success
+        }
+    }
+
+    /// Validate downloaded subtitles, optionally replacing invalid ones, and return job warnings
+    fn validate_downloads(
+        video_path: &Path,
+        sidecars: &mut SidecarMap,
+        subtitle_paths: &mut Vec<PathBuf>,
+        report: &SubliminalReport,
+        options: &SubliminalOptions,
+        settings: &ValidationSettings,
+        backend: &dyn SubtitleBackend,
+    ) -> Vec<String> {
+        if subtitle_paths.is_empty() {
+            return Vec::new();
+        }
+        let duration = MediaProbe::duration_secs(video_path);
+        let mut warnings = Vec::new();
+
+        for sub_path in subtitle_paths.clone() {
+            let issues = SubtitleValidator::validate(&sub_path, duration);
+            if issues.is_empty() {
+                continue;
+            }
+            let file_name = Utils::get_file_name(&sub_path);
+            let summary = issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
+            warn!("Subtitle validation failed for {}: {}", sub_path.display(), summary);
+
+            let lang = SubtitleUtils::subtitle_language(video_path, &sub_path);
+            let provider = lang.as_deref().and_then(|l| report.provider_for(l));
+            // Retry with every other enabled provider so the next best candidate is picked
+            let other_providers: Vec<String> = if options.providers.is_empty() {
+                SUBLIMINAL_PROVIDERS.iter().map(|p| p.to_string()).collect()
+            } else {
+                options.providers.clone()
+            }
+            .into_iter()
+            .filter(|p| Some(p.as_str()) != provider)
+            .collect();
+            let (Some(lang), Some(provider), true, false) =
+                (lang.clone(), provider, settings.retry_invalid, other_providers.is_empty()) else {
+                warnings.push(format!("{}: {}", file_name, summary));
+                continue;
+            };
+
+            info!("Deleting {} and retrying {} without provider {}", sub_path.display(), lang, provider);
+            if let Err(e) = std::fs::remove_file(&sub_path) {
+                warn!("Failed to delete invalid subtitle {}: {}", sub_path.display(), e);
+                warnings.push(format!("{}: {}", file_name, summary));
+                continue;
+            }
+            subtitle_paths.retain(|p| p != &sub_path);
+            let retry_options = SubliminalOptions {
+                languages: vec![lang.clone()],
+                force: true,
+                providers: other_providers,
+                ..options.clone()
+            };
+            if let Err(e) = backend.download(&retry_options, video_path) {
+                error!("Retry with {} failed for {}: {}", backend.name(), video_path.display(), e);
+            }
+            *sidecars = SidecarMap::for_video(video_path);
+            match SubtitleUtils::find_language_subtitle(video_path, sidecars, &lang) {
+                Some(new_path) => {
+                    let retry_issues = SubtitleValidator::validate(&new_path, duration);
+                    if retry_issues.is_empty() {
+                        warnings.push(format!("{}: {} (replaced with a subtitle from another provider)", file_name, summary));
+                    } else {
+                        let retry_summary = retry_issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
+                        warnings.push(format!("{}: {} (replacement also failed: {})", file_name, summary, retry_summary));
+                    }
+                    subtitle_paths.push(new_path);
+                }
+                None => {
+                    warnings.push(format!("{}: {} (deleted, no other candidate found)", file_name, summary));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Write SDH-free variants of downloaded subtitles, adding the kept originals to the job
+    fn strip_sdh_variants(subtitle_paths: &mut Vec<PathBuf>) {
+        for sub_path in subtitle_paths.clone() {
+            match SdhStripper::process_file(&sub_path) {
+                Ok(Some(sdh_path)) => subtitle_paths.push(sdh_path),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to strip SDH annotations from {}: {}", sub_path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// OCR embedded bitmap tracks for language chains that providers found nothing for
+    fn ocr_missing_languages(video_path: &Path, sidecars: &SidecarMap, chains: &[Vec<String>], warnings: &mut Vec<String>) -> Vec<ExtractedSubtitle> {
+        let mut converted: Vec<ExtractedSubtitle> = Vec::new();
+        for chain in chains {
+            let found = |lang: &String| SubtitleUtils::find_language_subtitle(video_path, sidecars, lang).is_some()
+                || converted.iter().any(|c| &c.language == lang);
+            if chain.iter().any(found) {
+                continue;
+            }
+            for lang in chain {
+                match SubtitleOcr::convert(video_path, lang) {
+                    Ok(Some(path)) => {
+                        converted.push(ExtractedSubtitle { language: lang.clone(), path, forced: false });
+                        break;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("OCR failed for {} ({}): {}", video_path.display(), lang, e);
+                        warnings.push(format!("OCR failed for {}: {}", lang, e));
+                    }
+                }
+            }
+        }
+        converted
+    }
+
+    /// Generate subtitles from the audio for languages that are still missing
+    fn transcribe_missing_languages(
+        video_path: &Path,
+        outcomes: &mut [LanguageOutcome],
+        settings: &Settings,
+        warnings: &mut Vec<String>,
+    ) -> Vec<PathBuf> {
+        let mut transcribed = Vec::new();
+        for outcome in outcomes.iter_mut().filter(|o| !o.succeeded()) {
+            match SpeechToText::transcribe(video_path, &outcome.language, &settings.speech_to_text) {
+                Ok(path) => {
+                    outcome.subtitle_path = Some(path.clone());
+                    outcome.origin = SubtitleOrigin::SpeechToText;
+                    outcome.provider = None;
+                    outcome.score = None;
+                    transcribed.push(path);
+                }
+                Err(e) => {
+                    warn!("Speech recognition failed for {} ({}): {}", video_path.display(), outcome.language, e);
+                    warnings.push(format!("Speech recognition failed for {}: {}", outcome.language, e));
+                }
+            }
+        }
+        transcribed
+    }
+
+    /// Translate the first language written by a person into the languages that are still missing
+    fn translate_missing_languages(
+        video_path: &Path,
+        outcomes: &mut [LanguageOutcome],
+        settings: &Settings,
+        warnings: &mut Vec<String>,
+    ) -> Vec<PathBuf> {
+        let Some((source_lang, source_path)) = outcomes.iter()
+            .filter(|o| o.origin.machine_generated_note().is_none())
+            .find_map(|o| Some((o.language.clone(), o.subtitle_path.clone()?)))
+        else {
+            return Vec::new();
+        };
+        let mut translated = Vec::new();
+        for outcome in outcomes.iter_mut().filter(|o| !o.succeeded()) {
+            match SubtitleTranslator::translate_file(video_path, &source_path, &source_lang, &outcome.language, &settings.translation) {
+                Ok(path) => {
+                    outcome.subtitle_path = Some(path.clone());
+                    outcome.origin = SubtitleOrigin::Translated;
+                    outcome.provider = None;
+                    outcome.score = None;
+                    translated.push(path);
+                }
+                Err(e) => {
+                    warn!("Translation to {} failed for {}: {}", outcome.language, video_path.display(), e);
+                    warnings.push(format!("Translation to {} failed: {}", outcome.language, e));
+                }
+            }
+        }
+        translated
+    }
+
+    /// Record which language of each chain ended up with a sidecar subtitle
+    fn collect_language_outcomes(
+        video_path: &Path,
+        sidecars: &SidecarMap,
+        chains: &[Vec<String>],
+        extracted: &[ExtractedSubtitle],
+        ocr_converted: &[ExtractedSubtitle],
+        report: &SubliminalReport,
+    ) -> Vec<LanguageOutcome> {
+        let local = |list: &[ExtractedSubtitle], lang: &String| list.iter()
+            .find(|e| &e.language == lang && !e.forced)
+            .map(|e| e.path.clone());
+        let outcome_for = |lang: &String| {
+            let (subtitle_path, origin) = if let Some(path) = local(extracted, lang) {
+                (Some(path), SubtitleOrigin::Extracted)
+            } else if let Some(path) = local(ocr_converted, lang) {
+                (Some(path), SubtitleOrigin::Ocr)
+            } else {
+                let path = SubtitleUtils::find_language_subtitle(video_path, sidecars, lang);
+                // A translation left by an earlier run isn't a source for new ones
+                let translated = path.as_deref()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .is_some_and(SidecarMap::is_machine_translated);
+                (path, if translated { SubtitleOrigin::Translated } else { SubtitleOrigin::Downloaded })
+            };
+            let (provider, score) = match origin {
+                SubtitleOrigin::Downloaded => (report.provider_for(lang).map(|p| p.to_string()), report.score_for(lang)),
+                _ => (None, None),
+            };
+            LanguageOutcome { language: lang.clone(), subtitle_path, origin, provider, score }
+        };
+        chains.iter()
+            .filter_map(|chain| {
+                // The first chain member with a subtitle wins, otherwise report the preferred language
+                chain.iter()
+                    .map(outcome_for)
+                    .find(|o| o.succeeded())
+                    .or_else(|| chain.first().map(outcome_for))
+            })
+            .collect()
+    }
+
+    /// Extract embedded text tracks chain by chain, stopping at the first member found
+    fn extract_chains(video_path: &Path, chains: &[Vec<String>], overwrite: bool) -> Vec<ExtractedSubtitle> {
+        let mut extracted = Vec::new();
+        for chain in chains {
+            for lang in chain {
+                let found = EmbeddedSubtitles::extract_text_tracks(video_path, std::slice::from_ref(lang), false, overwrite);
+                let satisfied = !found.is_empty();
+                extracted.extend(found);
+                if satisfied {
+                    break;
+                }
+            }
+        }
+        extracted
+    }
+
+    /// Get the forced variant of each forced language: an existing sidecar, an
+    /// extracted embedded track or a provider download
+    fn fetch_forced_subtitles(
+        video_path: &Path,
+        sidecars: &SidecarMap,
+        settings: &Settings,
+        options: &SubliminalOptions,
+        backend: &dyn SubtitleBackend,
+        warnings: &mut Vec<String>,
+    ) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for lang in &settings.forced_languages {
+            let existing = SubtitleUtils::find_language_subtitle(video_path, sidecars, &format!("{}.forced", lang));
+            if let (Some(path), false) = (&existing, settings.overwrite_existing) {
+                paths.push(path.clone());
+                continue;
+            }
+            if !settings.force_download {
+                if settings.embedded.extract_text_tracks {
+                    if let Some(e) = EmbeddedSubtitles::extract_text_tracks(video_path, std::slice::from_ref(lang), true, settings.overwrite_existing).pop() {
+                        paths.push(e.path);
+                        continue;
+                    }
+                }
+                // A forced track in the container already covers the foreign parts
+                if ForcedSubtitles::has_embedded_forced(video_path, lang) {
+                    info!("Embedded forced {} track found in {}, not downloading", lang, video_path.display());
+                    continue;
+                }
+            }
+            match ForcedSubtitles::download(video_path, lang, options, backend) {
+                Ok(Some(path)) => paths.push(path),
+                Ok(None) => warnings.push(format!("No forced {} subtitle found", lang)),
+                Err(e) => {
+                    error!("Failed to download forced {} subtitle for {}: {}", lang, video_path.display(), e);
+                    warnings.push(format!("Forced {} subtitle failed: {}", lang, e));
+                }
+            }
+        }
+        paths
+    }
+
+    /// Run Subliminal in rounds: first every chain's preferred language, then the
+    /// next fallback for each chain that is still missing, and so on. The folder
+    /// is listed again after each round.
+    fn download_chains(
+        video_path: &Path,
+        sidecars: &mut SidecarMap,
+        chains: &[Vec<String>],
+        options: &SubliminalOptions,
+        backend: &dyn SubtitleBackend,
+    ) -> Result<SubliminalReport, String> {
+        let mut report = SubliminalReport::default();
+        let mut pending: Vec<&Vec<String>> = chains.iter().collect();
+        for round in 0.. {
+            let languages: Vec<String> = pending.iter().filter_map(|chain| chain.get(round).cloned()).collect();
+            if languages.is_empty() {
+                break;
+            }
+            if round > 0 {
+                info!("Trying fallback languages {:?} for {}", languages, video_path.display());
+            }
+            let round_options = SubliminalOptions { languages, ..options.clone() };
+            let round_report = backend.download(&round_options, video_path)?;
+            report.merge(round_report);
+            *sidecars = SidecarMap::for_video(video_path);
+            pending.retain(|chain| {
+                chain.get(round).is_some_and(|lang| SubtitleUtils::find_language_subtitle(video_path, sidecars, lang).is_none())
+            });
+        }
+        Ok(report)
+    }
+
+    /// Merge the primary and secondary languages into one file when both succeeded
+    fn merge_dual_subtitles(video_path: &Path, outcomes: &[LanguageOutcome], settings: &DualSubtitleSettings) -> Option<PathBuf> {
+        let find = |lang: &str| outcomes.iter()
+            .find(|o| o.language == lang)
+            .and_then(|o| o.subtitle_path.clone());
+        let primary = find(&settings.primary_language)?;
+        let secondary = find(&settings.secondary_language)?;
+        match DualSubtitleMerger::merge_files(
+            video_path,
+            (&settings.primary_language, &primary),
+            (&settings.secondary_language, &secondary),
+            settings.format,
+        ) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Failed to merge dual-language subtitles for {}: {}", video_path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    use crate::settings::AudioLanguageRule;
+    use crate::sidecar_map::LISTINGS;
+
+    /// Backend writing a subtitle for the requested languages it has
+    struct FakeBackend {
+        available: &'static [&'static str],
+        requests: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl SubtitleBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn download(&self, options: &SubliminalOptions, video_path: &Path) -> Result<SubliminalReport, String> {
+            self.requests.lock().unwrap().push(options.languages.clone());
+            let stem = video_path.file_stem().unwrap().to_string_lossy();
+            for lang in options.languages.iter().filter(|l| self.available.contains(&l.as_str())) {
+                std::fs::write(video_path.with_file_name(format!("{}.{}.srt", stem, lang)), "1\n").unwrap();
+            }
+            Ok(SubliminalReport::default())
+        }
+    }
+
+    /// Settings with every step that needs external tools turned off
+    fn offline_settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.audio_language.rule = AudioLanguageRule::Off;
+        settings.embedded.extract_text_tracks = false;
+        settings.validation.enabled = false;
+        settings.ocr.enabled = false;
+        settings.speech_to_text.enabled = false;
+        settings.translation.enabled = false;
+        settings.dual_subtitles.enabled = false;
+        settings.mux.enabled = false;
+        settings
+    }
+
+    #[test]
+    fn leaves_forced_subtitles_out_of_validation_and_sdh_stripping() {
+        let folder = std::env::temp_dir().join(format!("rustitles-pipeline-forced-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        let video = folder.join("Movie.mkv");
+        std::fs::write(&video, "").unwrap();
+        let forced = folder.join("Movie.en.forced.srt");
+        let forced_srt = "1\n00:00:01,000 --> 00:00:02,000\n[IN SPANISH] Where is the station?\n\n";
+        std::fs::write(&forced, forced_srt).unwrap();
+
+        let mut settings = Settings {
+            selected_languages: vec!["en".to_string()],
+            forced_languages: vec!["en".to_string()],
+            ..offline_settings()
+        };
+        settings.validation.enabled = true;
+        settings.hearing_impaired.strip_sdh = true;
+        let backend = FakeBackend { available: &["en"], requests: Mutex::new(Vec::new()) };
+
+        let result = DownloadPipeline::process(&video, &settings, &backend, None);
+
+        assert!(result.subtitle_paths.contains(&forced));
+        assert_eq!(std::fs::read_to_string(&forced).unwrap(), forced_srt);
+        assert!(!folder.join("Movie.en.forced.sdh.srt").exists());
+        assert!(!result.warnings.iter().any(|w| w.contains("forced")), "{:?}", result.warnings);
+
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+
+    #[test]
+    fn lists_the_folder_once_per_download_round() {
+        let folder = std::env::temp_dir().join(format!("rustitles-pipeline-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        // A season folder full of other episodes and their subtitles
+        for episode in 1..=200 {
+            std::fs::write(folder.join(format!("Show.S01E{:03}.mkv", episode)), "").unwrap();
+            for lang in ["en", "fr", "de"] {
+                std::fs::write(folder.join(format!("Show.S01E{:03}.{}.srt", episode, lang)), "1\n").unwrap();
+            }
+        }
+        let video = folder.join("Show.S01E201.mkv");
+        std::fs::write(&video, "").unwrap();
+
+        let settings = Settings {
+            selected_languages: vec!["pt-br".to_string(), "en".to_string(), "nl".to_string()],
+            language_fallbacks: BTreeMap::from([
+                ("pt-br".to_string(), vec!["pt".to_string(), "es".to_string()]),
+                ("nl".to_string(), vec!["de".to_string()]),
+            ]),
+            ..offline_settings()
+        };
+        let backend = FakeBackend { available: &["en", "es", "de"], requests: Mutex::new(Vec::new()) };
+
+        LISTINGS.with(|count| count.set(0));
+        let result = DownloadPipeline::process(&video, &settings, &backend, None);
+        let listings = LISTINGS.with(|count| count.get());
+
+        let requests = backend.requests.lock().unwrap().clone();
+        assert_eq!(requests, vec![
+            vec!["pt-br".to_string(), "en".to_string(), "nl".to_string()],
+            vec!["pt".to_string(), "de".to_string()],
+            vec!["es".to_string()],
+        ]);
+        // Once for the job and once after each of the three rounds
+        assert_eq!(listings, 1 + requests.len());
+        assert_eq!(result.status, JobStatus::Success);
+        let found: Vec<(&str, Option<PathBuf>)> = result.language_outcomes.iter()
+            .map(|o| (o.language.as_str(), o.subtitle_path.clone()))
+            .collect();
+        assert_eq!(found, vec![
+            ("es", Some(folder.join("Show.S01E201.es.srt"))),
+            ("en", Some(folder.join("Show.S01E201.en.srt"))),
+            ("de", Some(folder.join("Show.S01E201.de.srt"))),
+        ]);
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+//! Dual-language subtitle generation
+//!
+//! This module combines two sidecar subtitles into a single file for language
+//! learners, either as ASS with the secondary language at the top of the
+//! screen or as SRT with the secondary lines stacked under the primary ones.
+//! The secondary track is aligned to the timing of the primary track.
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{DUAL_SUBTITLE_MAX_OFFSET_MS, DUAL_SUBTITLE_MATCH_WINDOW_MS};
+use crate::settings::DualSubtitleFormat;
+use crate::subtitle_model::{Cue, Subtitle};
+
+/// Merging of two subtitle languages into one file
+pub struct DualSubtitleMerger;
+
+impl DualSubtitleMerger {
+    /// Estimate the constant offset to add to the secondary track so it lines up with the primary
+    fn estimate_offset(primary: &Subtitle, secondary: &Subtitle) -> i64 {
+        let mut deltas: Vec<i64> = secondary.cues.iter()
+            .filter_map(|s| {
+                primary.cues.iter()
+                    .map(|p| p.start_ms - s.start_ms)
+                    .filter(|d| d.abs() <= DUAL_SUBTITLE_MAX_OFFSET_MS)
+                    .min_by_key(|d| d.abs())
+            })
+            .collect();
+        if deltas.is_empty() {
+            return 0;
+        }
+        deltas.sort_unstable();
+        deltas[deltas.len() / 2]
+    }
+
+    /// Find the primary cue that best matches a secondary cue
+    fn best_match(primary: &[Cue], cue: &Cue) -> Option<usize> {
+        primary.iter().enumerate()
+            .map(|(i, p)| {
+                let overlap = p.end_ms.min(cue.end_ms) - p.start_ms.max(cue.start_ms);
+                (i, overlap, (p.start_ms - cue.start_ms).abs())
+            })
+            .filter(|(_, overlap, distance)| *overlap > 0 || *distance <= DUAL_SUBTITLE_MATCH_WINDOW_MS)
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)))
+            .map(|(i, _, _)| i)
+    }
+
+    /// Align the secondary cues to the primary timing. Each secondary cue is
+    /// paired with the index of the primary cue it was snapped to, if any.
+    pub fn align(primary: &Subtitle, secondary: &Subtitle) -> Vec<(Option<usize>, Cue)> {
+        let offset = Self::estimate_offset(primary, secondary);
+        secondary.cues.iter()
+            .map(|cue| {
+                let shifted = Cue {
+                    start_ms: cue.start_ms + offset,
+                    end_ms: cue.end_ms + offset,
+                    text: cue.text.clone(),
+                };
+                match Self::best_match(&primary.cues, &shifted) {
+                    Some(i) => {
+                        let p = &primary.cues[i];
+                        (Some(i), Cue { start_ms: p.start_ms, end_ms: p.end_ms, text: shifted.text })
+                    }
+                    None => (None, shifted),
+                }
+            })
+            .collect()
+    }
+
+    /// Build an SRT with the secondary lines stacked under each primary cue
+    pub fn merge_srt(primary: &Subtitle, secondary: &Subtitle) -> Subtitle {
+        let aligned = Self::align(primary, secondary);
+        let mut cues = primary.cues.clone();
+        let mut unmatched = Vec::new();
+        for (index, cue) in aligned {
+            match index {
+                Some(i) => {
+                    let text = &mut cues[i].text;
+                    if !text.contains(&cue.text) {
+                        text.push('\n');
+                        text.push_str(&cue.text);
+                    }
+                }
+                None => unmatched.push(cue),
+            }
+        }
+        cues.extend(unmatched);
+        cues.sort_by_key(|c| c.start_ms);
+        Subtitle { cues }
+    }
+
+    /// Format milliseconds as an ASS timestamp (h:mm:ss.cc)
+    fn format_ass_timestamp(ms: i64) -> String {
+        let ms = ms.max(0);
+        format!("{}:{:02}:{:02}.{:02}", ms / 3_600_000, (ms / 60_000) % 60, (ms / 1000) % 60, (ms % 1000) / 10)
+    }
+
+    /// Build an ASS file with the primary language at the bottom and the secondary on top
+    pub fn merge_ass(primary: &Subtitle, secondary: &Subtitle) -> String {
+        let mut out = String::from(
+            "[Script Info]\n\
+             ScriptType: v4.00+\n\
+             PlayResX: 1920\n\
+             PlayResY: 1080\n\
+             WrapStyle: 0\n\
+             \n\
+             [V4+ Styles]\n\
+             Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+             Style: Primary,Arial,64,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,1,2,40,40,50,1\n\
+             Style: Secondary,Arial,52,&H0000FFFF,&H000000FF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,3,1,8,40,40,50,1\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        );
+        let mut push = |style: &str, cue: &Cue| {
+            out.push_str(&format!(
+                "Dialogue: 0,{},{},{},,0,0,0,,{}\n",
+                Self::format_ass_timestamp(cue.start_ms),
+                Self::format_ass_timestamp(cue.end_ms),
+                style,
+                cue.text.replace('\n', "\\N")
+            ));
+        };
+        for cue in &primary.cues {
+            push("Primary", cue);
+        }
+        for (_, cue) in Self::align(primary, secondary) {
+            push("Secondary", &cue);
+        }
+        out
+    }
+
+    /// Path of the merged file (e.g. movie.en+ja.ass)
+    pub fn output_path(video_path: &Path, primary_lang: &str, secondary_lang: &str, format: DualSubtitleFormat) -> Option<PathBuf> {
+        let stem = video_path.file_stem()?.to_str()?;
+        let ext = match format {
+            DualSubtitleFormat::Ass => "ass",
+            DualSubtitleFormat::Srt => "srt",
+        };
+        Some(video_path.with_file_name(format!("{}.{}+{}.{}", stem, primary_lang, secondary_lang, ext)))
+    }
+
+    /// Merge two sidecar files and write the result next to the video
+    pub fn merge_files(
+        video_path: &Path,
+        primary: (&str, &Path),
+        secondary: (&str, &Path),
+        format: DualSubtitleFormat,
+    ) -> Result<PathBuf, String> {
+        let primary_sub = Subtitle::load(primary.1)?;
+        let secondary_sub = Subtitle::load(secondary.1)?;
+        let output = Self::output_path(video_path, primary.0, secondary.0, format)
+            .ok_or_else(|| format!("Invalid video path: {}", video_path.display()))?;
+        let content = match format {
+            DualSubtitleFormat::Ass => Self::merge_ass(&primary_sub, &secondary_sub),
+            DualSubtitleFormat::Srt => Self::merge_srt(&primary_sub, &secondary_sub).to_srt(),
+        };
+        std::fs::write(&output, content).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        crate::info!("Wrote dual-language subtitle {}", output.display());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subtitle(cues: &[(i64, i64, &str)]) -> Subtitle {
+        Subtitle {
+            cues: cues.iter()
+                .map(|&(start_ms, end_ms, text)| Cue { start_ms, end_ms, text: text.to_string() })
+                .collect(),
+        }
+    }
+
+    fn timings(aligned: &[(Option<usize>, Cue)]) -> Vec<(Option<usize>, i64, i64)> {
+        aligned.iter().map(|(index, cue)| (*index, cue.start_ms, cue.end_ms)).collect()
+    }
+
+    #[test]
+    fn snaps_a_constantly_offset_track_to_the_primary_cues() {
+        let primary = subtitle(&[(1_000, 3_000, "One"), (10_000, 12_000, "Two"), (20_000, 22_000, "Three"), (30_000, 32_000, "Four")]);
+        // 1.5 s early with a little jitter, plus one badly timed cue the median ignores
+        let secondary = subtitle(&[(-500, 1_500, "Un"), (8_480, 10_500, "Deux"), (18_520, 20_500, "Trois"), (29_000, 31_000, "Quatre")]);
+        assert_eq!(DualSubtitleMerger::estimate_offset(&primary, &secondary), 1_500);
+        assert_eq!(timings(&DualSubtitleMerger::align(&primary, &secondary)), vec![
+            (Some(0), 1_000, 3_000),
+            (Some(1), 10_000, 12_000),
+            (Some(2), 20_000, 22_000),
+            (Some(3), 30_000, 32_000),
+        ]);
+        let merged = DualSubtitleMerger::merge_srt(&primary, &secondary);
+        let texts: Vec<&str> = merged.cues.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["One\nUn", "Two\nDeux", "Three\nTrois", "Four\nQuatre"]);
+    }
+
+    #[test]
+    fn ignores_offsets_above_the_limit() {
+        let primary = subtitle(&[(0, 2_000, "One"), (30_000, 32_000, "Two")]);
+        let late = DUAL_SUBTITLE_MAX_OFFSET_MS + 3_000;
+        let secondary = subtitle(&[(late, late + 2_000, "Un"), (30_000 + late, 32_000 + late, "Deux")]);
+        assert_eq!(DualSubtitleMerger::estimate_offset(&primary, &secondary), 0);
+        // Too far from any primary cue to be snapped, so the timing is kept
+        assert_eq!(timings(&DualSubtitleMerger::align(&primary, &secondary)), vec![
+            (None, late, late + 2_000),
+            (None, 30_000 + late, 32_000 + late),
+        ]);
+    }
+
+    #[test]
+    fn keeps_unmatched_secondary_cues_as_their_own_entries() {
+        let primary = subtitle(&[(1_000, 3_000, "One"), (20_000, 22_000, "Two")]);
+        let secondary = subtitle(&[(1_100, 3_000, "Un"), (10_000, 12_000, "Sans pareil"), (20_050, 22_000, "Deux")]);
+        assert_eq!(timings(&DualSubtitleMerger::align(&primary, &secondary)), vec![
+            (Some(0), 1_000, 3_000),
+            (None, 9_950, 11_950),
+            (Some(1), 20_000, 22_000),
+        ]);
+        let merged = DualSubtitleMerger::merge_srt(&primary, &secondary);
+        let cues: Vec<(i64, &str)> = merged.cues.iter().map(|c| (c.start_ms, c.text.as_str())).collect();
+        assert_eq!(cues, vec![(1_000, "One\nUn"), (9_950, "Sans pareil"), (20_000, "Two\nDeux")]);
+    }
+}
@@ -0,0 +1,72 @@
+//! Extraction of embedded subtitle tracks to sidecar files
+//!
+//! This module finds text subtitle tracks in a selected language inside a
+//! video container and extracts them with ffmpeg to `{stem}.{lang}.srt`, or
+//! `{stem}.{lang}.forced.srt` for tracks flagged as forced.
+
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::Ffmpeg;
+use crate::media_probe::{MediaProbe, MediaStream};
+use crate::subtitle_utils::SubtitleUtils;
+
+/// A subtitle track extracted from the container
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractedSubtitle {
+    pub language: String,
+    pub path: PathBuf,
+    pub forced: bool,
+}
+
+/// Extraction of embedded subtitle tracks
+pub struct EmbeddedSubtitles;
+
+impl EmbeddedSubtitles {
+    /// Sidecar path for an extracted track (video.en.srt or video.en.forced.srt)
+    pub fn sidecar_path(video_path: &Path, lang: &str, forced: bool) -> Option<PathBuf> {
+        let stem = video_path.file_stem()?.to_str()?;
+        let name = if forced {
+            format!("{}.{}.forced.srt", stem, lang)
+        } else {
+            format!("{}.{}.srt", stem, lang)
+        };
+        Some(video_path.with_file_name(name))
+    }
+
+    /// Pick the text track to extract for a language, preferring the default track
+    fn pick_track<'a>(streams: &'a [MediaStream], lang: &str, forced: bool) -> Option<&'a MediaStream> {
+        let mut candidates: Vec<&MediaStream> = streams.iter()
+            .filter(|s| s.is_text_subtitle() && s.is_forced() == forced)
+            .filter(|s| s.language.as_deref().is_some_and(|l| SubtitleUtils::language_matches(l, lang)))
+            .collect();
+        candidates.sort_by_key(|s| !s.default);
+        candidates.into_iter().next()
+    }
+
+    /// Extract text subtitle tracks for the selected languages, either the full
+    /// or the forced variant. Existing sidecars are kept unless `overwrite` is
+    /// set, and are reported as already extracted.
+    pub fn extract_text_tracks(video_path: &Path, langs: &[String], forced: bool, overwrite: bool) -> Vec<ExtractedSubtitle> {
+        let streams = MediaProbe::streams(video_path, "s");
+        let mut extracted = Vec::new();
+        for lang in langs {
+            let Some(stream) = Self::pick_track(&streams, lang, forced) else { continue };
+            let Some(output) = Self::sidecar_path(video_path, lang, forced) else { continue };
+            if output.exists() && !overwrite {
+                crate::debug!("Sidecar {} already exists, not extracting", output.display());
+                extracted.push(ExtractedSubtitle { language: lang.clone(), path: output, forced });
+                continue;
+            }
+            match Ffmpeg::extract_subtitle_to_srt(video_path, stream.index, &output) {
+                Ok(()) => {
+                    crate::info!("Extracted embedded {} track {} to {}", lang, stream.index, output.display());
+                    extracted.push(ExtractedSubtitle { language: lang.clone(), path: output, forced });
+                }
+                Err(e) => {
+                    crate::warn!("Failed to extract embedded {} track from {}: {}", lang, video_path.display(), e);
+                }
+            }
+        }
+        extracted
+    }
+}
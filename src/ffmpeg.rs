@@ -0,0 +1,41 @@
+//! ffmpeg invocation helpers
+//!
+//! This module runs ffmpeg for stream extraction and conversion, hiding the
+//! console window on Windows and surfacing ffmpeg's error output on failure.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use crate::helper_functions::Utils;
+
+/// ffmpeg command line runner
+pub struct Ffmpeg;
+
+impl Ffmpeg {
+    /// Run ffmpeg with the given arguments, returning its error output on failure
+    pub fn run(args: &[OsString]) -> Result<(), String> {
+        let mut cmd = Utils::hidden_command("ffmpeg");
+        cmd.arg("-hide_banner").arg("-v").arg("error").arg("-y").args(args);
+        crate::debug!("Running ffmpeg {:?}", args);
+        let output = cmd.output().map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+
+    /// Extract a single subtitle stream from a container and convert it to SRT
+    pub fn extract_subtitle_to_srt(video_path: &Path, stream_index: u32, output: &Path) -> Result<(), String> {
+        let args: Vec<OsString> = vec![
+            "-i".into(), video_path.into(),
+            "-map".into(), format!("0:{}", stream_index).into(),
+            "-c:s".into(), "srt".into(),
+            output.into(),
+        ];
+        Self::run(&args).inspect_err(|_| {
+            // Don't leave a partial sidecar behind
+            let _ = std::fs::remove_file(output);
+        })
+    }
+}
@@ -0,0 +1,279 @@
+//! Video metadata from file and folder names
+//!
+//! This module reads the title, year, season and episode numbers, anime
+//! absolute episode numbers, resolution, source and release group from a
+//! video's file name in the spirit of guessit. The parent folders fill in
+//! what the file name leaves out, e.g. `Show/Season 2/05.mkv`.
+
+use std::path::Path;
+
+/// Release sources recognized in file names, as (token, display name)
+const SOURCES: &[(&str, &str)] = &[
+    ("bluray", "BluRay"), ("blu-ray", "BluRay"), ("bdrip", "BluRay"), ("brrip", "BluRay"), ("bdremux", "BluRay"),
+    ("remux", "BluRay"), ("web-dl", "WEB-DL"), ("webdl", "WEB-DL"), ("webrip", "WEBRip"), ("web", "WEB"),
+    ("hdtv", "HDTV"), ("pdtv", "HDTV"), ("dvdrip", "DVD"), ("dvd", "DVD"), ("hdrip", "HDRip"),
+];
+
+/// Whether a video is a movie or an episode of a series
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VideoKind {
+    #[default]
+    Movie,
+    Episode,
+}
+
+/// What the file and folder names say about a video
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VideoMetadata {
+    pub kind: VideoKind,
+    pub title: String,
+    pub year: Option<u32>,
+    pub season: Option<u32>,
+    /// Episode numbers, more than one for multi-episode files (S01E01E02)
+    pub episodes: Vec<u32>,
+    /// Absolute episode number used by anime releases ("Show - 105")
+    pub absolute_episode: Option<u32>,
+    pub resolution: Option<String>,
+    pub source: Option<String>,
+    pub release_group: Option<String>,
+}
+
+impl VideoMetadata {
+    pub fn is_episode(&self) -> bool {
+        self.kind == VideoKind::Episode
+    }
+}
+
+/// Parser for video file and folder names
+pub struct FilenameParser;
+
+impl FilenameParser {
+    /// Season and episodes from S01E02, S01E01E02, S01E01-E02 or 1x02
+    fn parse_episode_token(token: &str) -> Option<(u32, Vec<u32>)> {
+        let lower = token.to_lowercase();
+        if let Some(rest) = lower.strip_prefix('s') {
+            let season_len = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            if season_len == 0 || season_len > 2 || !rest[season_len..].starts_with('e') {
+                return None;
+            }
+            let season = rest[..season_len].parse().ok()?;
+            let episodes: Vec<u32> = rest[season_len..]
+                .split(['e', '-'])
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse().ok())
+                .collect::<Option<_>>()?;
+            return (!episodes.is_empty()).then_some((season, episodes));
+        }
+        // 1x02, but not resolutions like 1920x1080
+        let (season, episode) = lower.split_once('x')?;
+        let valid = (1..=2).contains(&season.len()) && episode.len() == 2
+            && season.chars().chain(episode.chars()).all(|c| c.is_ascii_digit());
+        if !valid {
+            return None;
+        }
+        Some((season.parse().ok()?, vec![episode.parse().ok()?]))
+    }
+
+    fn parse_year(token: &str) -> Option<u32> {
+        let year: u32 = token.parse().ok().filter(|_| token.len() == 4)?;
+        (1900..=2099).contains(&year).then_some(year)
+    }
+
+    fn parse_resolution(token: &str) -> Option<String> {
+        let lower = token.to_lowercase();
+        if lower == "4k" || lower == "uhd" {
+            return Some("2160p".to_string());
+        }
+        let digits = lower.strip_suffix('p').or_else(|| lower.strip_suffix('i'))?;
+        let lines: u32 = digits.parse().ok()?;
+        [360, 480, 540, 576, 720, 1080, 1440, 2160, 4320].contains(&lines).then(|| lower.clone())
+    }
+
+    fn parse_source(token: &str) -> Option<String> {
+        let lower = token.to_lowercase();
+        SOURCES.iter().find(|(name, _)| *name == lower).map(|(_, display)| display.to_string())
+    }
+
+    /// Season number from a folder named "Season 2", "S02" or "Specials"
+    fn season_from_folder(name: &str) -> Option<u32> {
+        let lower = name.to_lowercase();
+        if lower == "specials" {
+            return Some(0);
+        }
+        let number = lower.strip_prefix("season").or_else(|| lower.strip_prefix('s'))?;
+        number.trim_matches([' ', '.', '_']).parse().ok()
+    }
+
+    /// Parse a video path
+    pub fn parse(path: &Path) -> VideoMetadata {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").trim();
+        let mut metadata = VideoMetadata::default();
+        let mut name = stem;
+
+        // Anime releases put the group first: "[Group] Show - 05 [1080p]"
+        let mut anime_style = false;
+        if let Some(rest) = name.strip_prefix('[') {
+            if let Some((group, rest)) = rest.split_once(']') {
+                metadata.release_group = Some(group.trim().to_string());
+                name = rest.trim();
+                anime_style = true;
+            }
+        }
+        // Scene releases put it last: "Show.S01E02.720p.HDTV.x264-GROUP"
+        if metadata.release_group.is_none() {
+            if let Some((rest, group)) = name.rsplit_once('-') {
+                // "WEB-DL" is a source, not a group called "DL"
+                let last_word = rest.rsplit(['.', '_', ' ']).next().unwrap_or("");
+                // "S01E01-E02" and "S01E01-02" are episode ranges, not a group called "E02"
+                let episode_range = group.strip_prefix(['e', 'E']).is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+                    || Self::parse_episode_token(&format!("{}-{}", last_word, group)).is_some();
+                let is_group = !group.is_empty() && group.len() <= 20
+                    && group.chars().all(|c| c.is_ascii_alphanumeric())
+                    && !rest.ends_with(' ')
+                    && !episode_range
+                    && Self::parse_source(group).is_none()
+                    && Self::parse_source(&format!("{}-{}", last_word, group)).is_none()
+                    && group.parse::<u32>().is_err()
+                    && rest.contains(['.', '_']);
+                if is_group {
+                    metadata.release_group = Some(group.to_string());
+                    name = rest;
+                }
+            }
+        }
+
+        let tokens: Vec<&str> = name
+            .split(['.', '_', ' ', '(', ')', '[', ']', '{', '}'])
+            .filter(|t| !t.is_empty())
+            .collect();
+        // Only the last year counts, earlier ones belong to the title ("Blade Runner 2049 (2017)")
+        let year_index = tokens.iter().rposition(|t| Self::parse_year(t).is_some()).filter(|i| *i > 0);
+        let mut title_end = None;
+        for (i, token) in tokens.iter().enumerate() {
+            let mut recognized = true;
+            let after_dash = i > 0 && tokens[i - 1] == "-";
+            if let Some((season, episodes)) = Self::parse_episode_token(token) {
+                metadata.season.get_or_insert(season);
+                if metadata.episodes.is_empty() {
+                    metadata.episodes = episodes;
+                }
+            } else if Some(i) == year_index {
+                metadata.year = Self::parse_year(token);
+            } else if let Some(resolution) = Self::parse_resolution(token) {
+                metadata.resolution.get_or_insert(resolution);
+            } else if let Some(source) = Self::parse_source(token) {
+                metadata.source.get_or_insert(source);
+            } else if let (Ok(number), true) = (token.parse::<u32>(), (anime_style || after_dash) && year_index.is_none()) {
+                // A bare number after the title is an anime absolute episode ("Show - 105")
+                if i > 0 && title_end.is_none() && token.len() <= 4 && metadata.episodes.is_empty() {
+                    metadata.absolute_episode = Some(number);
+                } else {
+                    recognized = false;
+                }
+            } else {
+                recognized = false;
+            }
+            if recognized && title_end.is_none() {
+                title_end = Some(i);
+            }
+        }
+        metadata.title = tokens[..title_end.unwrap_or(tokens.len())]
+            .iter()
+            .filter(|t| **t != "-")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // Folders fill in the season and the series title ("Show/Season 2/05.mkv")
+        let parent = path.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str());
+        let grandparent = path.parent().and_then(|p| p.parent()).and_then(|p| p.file_name()).and_then(|n| n.to_str());
+        let folder_season = parent.and_then(Self::season_from_folder);
+        if folder_season.is_some() && metadata.season.is_none() {
+            metadata.season = folder_season;
+        }
+        // A bare number inside a season folder is the episode ("Season 2/05.mkv"),
+        // anywhere else it's the title ("300.mkv", "1917.mkv")
+        if let (Ok(number), Some(_), true) = (metadata.title.parse::<u32>(), folder_season, metadata.episodes.is_empty()) {
+            metadata.episodes = vec![number];
+            metadata.title.clear();
+        }
+        if metadata.title.is_empty() {
+            let folder_title = if folder_season.is_some() { grandparent } else { parent };
+            if let Some(folder) = folder_title {
+                // Folder names may contain dots, so the extension is appended rather than replaced
+                let folder_metadata = Self::parse(Path::new(&format!("{}.mkv", folder)));
+                metadata.title = folder_metadata.title;
+                metadata.year = metadata.year.or(folder_metadata.year);
+            }
+        }
+        if metadata.absolute_episode.is_some() && metadata.episodes.is_empty() && folder_season.is_some() {
+            // "Show/Season 2/Show - 05.mkv" is episode 5 of that season
+            metadata.episodes = metadata.absolute_episode.take().into_iter().collect();
+        }
+
+        metadata.kind = if !metadata.episodes.is_empty() || metadata.absolute_episode.is_some() {
+            VideoKind::Episode
+        } else {
+            VideoKind::Movie
+        };
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expected title, year, season, episodes, absolute episode and release group
+    type Expected = (&'static str, Option<u32>, Option<u32>, &'static [u32], Option<u32>, Option<&'static str>);
+
+    #[test]
+    fn parses_file_and_folder_names() {
+        let cases: &[(&str, Expected)] = &[
+            ("Movies/The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv", ("The Matrix", Some(1999), None, &[], None, Some("GROUP"))),
+            ("Movies/Blade Runner 2049 (2017).mkv", ("Blade Runner 2049", Some(2017), None, &[], None, None)),
+            ("Movies/300.mkv", ("300", None, None, &[], None, None)),
+            ("Movies/1917.mkv", ("1917", None, None, &[], None, None)),
+            ("Movies/1917.2019.2160p.WEB-DL.mkv", ("1917", Some(2019), None, &[], None, None)),
+            ("TV/Show.S01E02.720p.HDTV.x264-GRP.mkv", ("Show", None, Some(1), &[2], None, Some("GRP"))),
+            ("TV/Show.S01E01E02.mkv", ("Show", None, Some(1), &[1, 2], None, None)),
+            ("TV/Show.S01E01-E02.mkv", ("Show", None, Some(1), &[1, 2], None, None)),
+            ("TV/Show.S01E01-02.mkv", ("Show", None, Some(1), &[1, 2], None, None)),
+            ("TV/Show.S01E01-E02.1080p.WEB-DL-GRP.mkv", ("Show", None, Some(1), &[1, 2], None, Some("GRP"))),
+            ("TV/Show.2x05.mkv", ("Show", None, Some(2), &[5], None, None)),
+            // A resolution is not season 1920, episode 1080
+            ("TV/Show.1920x1080.mkv", ("Show 1920x1080", None, None, &[], None, None)),
+            ("Show/Season 2/05.mkv", ("Show", None, Some(2), &[5], None, None)),
+            ("Show (2010)/Season 02/Show - 07.mkv", ("Show", None, Some(2), &[7], None, None)),
+            ("Show/Specials/Show.S00E01.mkv", ("Show", None, Some(0), &[1], None, None)),
+            ("Anime/[SubGroup] Some Anime - 105 [1080p].mkv", ("Some Anime", None, None, &[], Some(105), Some("SubGroup"))),
+            ("Movies/Spider-Man.2002.mkv", ("Spider-Man", Some(2002), None, &[], None, None)),
+        ];
+        for (path, (title, year, season, episodes, absolute, group)) in cases {
+            let metadata = FilenameParser::parse(Path::new(path));
+            assert_eq!(metadata.title, *title, "title of {}", path);
+            assert_eq!(metadata.year, *year, "year of {}", path);
+            assert_eq!(metadata.season, *season, "season of {}", path);
+            assert_eq!(metadata.episodes, *episodes, "episodes of {}", path);
+            assert_eq!(metadata.absolute_episode, *absolute, "absolute episode of {}", path);
+            assert_eq!(metadata.release_group.as_deref(), *group, "release group of {}", path);
+            let expected_kind = if episodes.is_empty() && absolute.is_none() { VideoKind::Movie } else { VideoKind::Episode };
+            assert_eq!(metadata.kind, expected_kind, "kind of {}", path);
+        }
+    }
+
+    #[test]
+    fn reads_resolution_and_source() {
+        let cases = [
+            ("Movie.2010.1080p.BluRay.mkv", Some("1080p"), Some("BluRay")),
+            ("Movie.2010.4K.WEB-DL.mkv", Some("2160p"), Some("WEB-DL")),
+            ("Movie.2010.720p.WEBRip.mkv", Some("720p"), Some("WEBRip")),
+            ("Movie.2010.mkv", None, None),
+        ];
+        for (name, resolution, source) in cases {
+            let metadata = FilenameParser::parse(Path::new(name));
+            assert_eq!(metadata.resolution.as_deref(), resolution, "{}", name);
+            assert_eq!(metadata.source.as_deref(), source, "{}", name);
+        }
+    }
+}
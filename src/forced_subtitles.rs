@@ -0,0 +1,128 @@
+//! Forced (foreign parts only) subtitle downloads
+//!
+//! This module searches providers for forced variants of a language and
+//! stores them as `{stem}.{lang}.forced.srt`, next to and independent of the
+//! regular full subtitle for the same language. Downloads go to a temporary
+//! folder first, so the regular subtitle is never moved or overwritten.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::embedded_subtitles::EmbeddedSubtitles;
+use crate::media_probe::MediaProbe;
+use crate::sidecar_map::SidecarMap;
+use crate::subliminal::SubliminalOptions;
+use crate::subtitle_backend::SubtitleBackend;
+use crate::subtitle_utils::SubtitleUtils;
+
+/// Counter keeping temporary download folders unique across concurrent jobs
+static DOWNLOAD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Downloads of forced subtitles
+pub struct ForcedSubtitles;
+
+impl ForcedSubtitles {
+    /// Whether the container has a track flagged as forced in a language
+    pub fn has_embedded_forced(video_path: &Path, lang: &str) -> bool {
+        MediaProbe::streams(video_path, "s").iter()
+            .any(|s| s.is_forced() && s.language.as_deref().is_some_and(|l| SubtitleUtils::language_matches(l, lang)))
+    }
+
+    /// Move a downloaded file into place, copying when the temporary folder is on another drive
+    fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(from, to).or_else(|_| std::fs::copy(from, to).map(|_| ()))
+    }
+
+    /// Download the forced variant of a language to {stem}.{lang}.forced.srt.
+    /// Returns Ok(None) when no provider has one.
+    pub fn download(
+        video_path: &Path,
+        lang: &str,
+        options: &SubliminalOptions,
+        backend: &dyn SubtitleBackend,
+    ) -> Result<Option<PathBuf>, String> {
+        let forced_path = EmbeddedSubtitles::sidecar_path(video_path, lang, true).ok_or("invalid video file name")?;
+        let stem = video_path.file_stem().and_then(|s| s.to_str()).ok_or("invalid video file name")?;
+
+        // Subliminal writes forced subtitles under the regular name, so download
+        // into a folder of our own and leave the regular subtitle untouched
+        let directory = std::env::temp_dir().join(format!(
+            "rustitles-forced-{}-{}",
+            std::process::id(),
+            DOWNLOAD_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+        let forced_options = SubliminalOptions {
+            languages: vec![lang.to_string()],
+            force: true,
+            foreign_only: true,
+            directory: Some(directory.clone()),
+            ..options.clone()
+        };
+        let result = backend.download(&forced_options, video_path);
+
+        let written = SidecarMap::read(&directory).map(|map| map.find_all(stem, lang)).unwrap_or_default();
+        let moved = written.iter()
+            .find(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("srt")))
+            .map(|path| Self::move_file(path, &forced_path)
+                .map_err(|e| format!("Failed to write {}: {}", forced_path.display(), e)))
+            .transpose();
+        if let Err(e) = std::fs::remove_dir_all(&directory) {
+            crate::warn!("Failed to remove {}: {}", directory.display(), e);
+        }
+
+        result?;
+        Ok(moved?.map(|_| forced_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subliminal::SubliminalReport;
+
+    /// Backend writing a fixed subtitle under the regular name in the target folder
+    struct FakeBackend {
+        content: Option<&'static str>,
+    }
+
+    impl SubtitleBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn download(&self, options: &SubliminalOptions, video_path: &Path) -> Result<SubliminalReport, String> {
+            let folder = options.directory.clone().unwrap_or_else(|| video_path.parent().unwrap().to_path_buf());
+            if let Some(content) = self.content {
+                let stem = video_path.file_stem().unwrap().to_string_lossy();
+                std::fs::write(folder.join(format!("{}.{}.srt", stem, options.languages[0])), content).unwrap();
+            }
+            Ok(SubliminalReport::default())
+        }
+    }
+
+    #[test]
+    fn downloads_without_touching_the_regular_subtitle() {
+        let folder = std::env::temp_dir().join(format!("rustitles-forced-test-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let video = folder.join("movie.mkv");
+        let regular = folder.join("movie.en.srt");
+        std::fs::write(&regular, "regular").unwrap();
+
+        let backend = FakeBackend { content: Some("forced") };
+        let forced = ForcedSubtitles::download(&video, "en", &SubliminalOptions::default(), &backend).unwrap();
+        assert_eq!(forced, Some(folder.join("movie.en.forced.srt")));
+        assert_eq!(std::fs::read_to_string(folder.join("movie.en.forced.srt")).unwrap(), "forced");
+        assert_eq!(std::fs::read_to_string(&regular).unwrap(), "regular");
+
+        let backend = FakeBackend { content: None };
+        assert_eq!(ForcedSubtitles::download(&video, "fr", &SubliminalOptions::default(), &backend).unwrap(), None);
+        let mut names: Vec<String> = std::fs::read_dir(&folder).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["movie.en.forced.srt", "movie.en.srt"]);
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
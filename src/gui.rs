@@ -5,8 +5,14 @@
 use eframe::egui;
 use rfd::FileDialog;
 use crate::{
-    config::APP_VERSION,
-    data_structures::{SubtitleDownloader, JobStatus},
+    config::{APP_VERSION, ACCOUNT_PROVIDERS, MAX_AGE_FILTER_DAYS, MAX_REQUESTS_PER_MINUTE, MAX_RETRY_ATTEMPTS, MAX_RETRY_DELAY_SECS, MAX_SCAN_THREADS, SUBLIMINAL_PROVIDERS},
+    provider_accounts::{CredentialStorage, ProviderAccounts},
+    provider_limits::ProviderLimits,
+    job_history::{HistoryOutcome, JobHistory},
+    data_structures::{DownloadJob, ScanPreview, ScanPreviewRow, SubtitleDownloader, JobStatus},
+    job_groups::{JobDetail, JobGroupCounts, JobListRow},
+    settings::{AudioLanguageRule, DownloadBackend, DualSubtitleFormat, HearingImpairedPreference, TranslationBackend},
+    subtitle_utils::SubtitleUtils,
     helper_functions::{Utils, Validation},
     info, warn, debug,
 };
@@ -171,13 +177,7 @@ impl SubtitleDownloader {
         if self.is_version_checked() {
             if let Some(latest) = self.get_latest_version() {
                 if Self::is_outdated(APP_VERSION, latest) {
-                    let exe_url = if cfg!(target_os = "windows") {
-                        format!("https://github.com/lanec/rustitles/releases/tag/{}", latest)
-                    } else if cfg!(target_os = "linux") {
-                        format!("https://github.com/lanec/rustitles/releases/tag/{}", latest)
-                    } else {
-                        format!("https://github.com/lanec/rustitles/releases/tag/{}", latest)
-                    };
+                    let exe_url = format!("https://github.com/lanec/rustitles/releases/tag/{}", latest);
                     let link_text = format!("-> Rustitles {}", latest);
                     let link_rich = egui::RichText::new(link_text).color(egui::Color32::from_rgb(80, 160, 255));
                     ui.horizontal_wrapped(|ui| {
@@ -230,11 +230,14 @@ impl SubtitleDownloader {
 
         ui.horizontal(|ui| {
             // Button that looks like ComboBox (no dropdown arrow)
-            let selected_languages = self.get_selected_languages_mut();
-            let selected_text = if selected_languages.is_empty() {
+            let selected_text = if self.selected_languages.is_empty() {
                 "Select Languages".to_string()
             } else {
-                selected_languages.join(", ")
+                // Show fallback chains as pt-br>pt>es
+                SubtitleUtils::language_chains(&self.selected_languages, &self.language_fallbacks).iter()
+                    .map(|chain| chain.join(">"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
             };
             
             let button_response = ui.add_sized([130.0, ui.spacing().interact_size.y], egui::Button::new(selected_text));
@@ -279,6 +282,14 @@ impl SubtitleDownloader {
                     self.scan_folder();
                 }
             }
+
+            let auto_start_response = ui.checkbox(&mut self.auto_start_downloads, "Start Downloads After Scan")
+                .on_hover_text("When off, the scan results are listed for review before anything is downloaded");
+            if auto_start_response.changed() {
+                info!("(Start Downloads After Scan) changed to: {}", self.auto_start_downloads);
+                self.set_keep_dropdown_open(false); // Close dropdown when checkbox is clicked
+                self.save_current_settings(); // Save settings when changed
+            }
         });
         
         // Simple popup that shows when button is clicked
@@ -301,6 +312,7 @@ impl SubtitleDownloader {
                                     debug!("Language selected: {}", code);
                                 } else {
                                     selected_languages.retain(|c| c != code);
+                                    self.forced_languages.retain(|c| c != code);
                                     debug!("Language deselected: {}", code);
                                 }
                                 
@@ -308,6 +320,65 @@ impl SubtitleDownloader {
                             }
                         }
                     });
+
+                if !self.selected_languages.is_empty() {
+                    ui.separator();
+                    ui.label(egui::RichText::new("Fallbacks and forced subtitles").strong())
+                        .on_hover_text("Fallbacks are tried in order when a language isn't found. Once one succeeds, the rest of its chain is skipped");
+                    let mut changed = false;
+                    for lang in self.selected_languages.clone() {
+                        let mut forced = self.forced_languages.contains(&lang);
+                        let fallbacks = self.language_fallbacks.entry(lang.clone()).or_default();
+                        ui.horizontal_wrapped(|ui| {
+                            if ui.checkbox(&mut forced, "").on_hover_text("Also fetch forced (foreign parts only) subtitles as .forced.srt").changed() {
+                                changed = true;
+                            }
+                            ui.label(format!("{} →", lang));
+                            let mut removed = None;
+                            for (i, fallback) in fallbacks.iter().enumerate() {
+                                if ui.small_button(format!("{} ✖", fallback)).on_hover_text("Remove fallback").clicked() {
+                                    removed = Some(i);
+                                }
+                            }
+                            if let Some(i) = removed {
+                                debug!("Fallback {} removed from {}", fallbacks[i], lang);
+                                fallbacks.remove(i);
+                                changed = true;
+                            }
+                            egui::ComboBox::from_id_source(format!("fallback_{}", lang))
+                                .selected_text("+")
+                                .width(30.0)
+                                .show_ui(ui, |ui| {
+                                    for (code, name) in &language_list {
+                                        if *code == lang || fallbacks.iter().any(|f| f == code) {
+                                            continue;
+                                        }
+                                        if ui.selectable_label(false, format!("{} [{}]", name, code)).clicked() {
+                                            debug!("Fallback {} added to {}", code, lang);
+                                            fallbacks.push(code.to_string());
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                        });
+                        if forced != self.forced_languages.contains(&lang) {
+                            debug!("Forced subtitles for {} set to {}", lang, forced);
+                            if forced {
+                                self.forced_languages.push(lang.clone());
+                            } else {
+                                self.forced_languages.retain(|l| l != &lang);
+                            }
+                        }
+                    }
+                    self.language_fallbacks.retain(|_, fallbacks| !fallbacks.is_empty());
+                    if changed {
+                        self.save_current_settings();
+                        // A new fallback can satisfy videos that were missing subtitles
+                        if !self.get_folder_path().is_empty() {
+                            self.scan_folder();
+                        }
+                    }
+                }
             });
         }
     }
@@ -338,6 +409,320 @@ impl SubtitleDownloader {
         });
     }
 
+    /// Render the collapsible advanced settings section
+    pub fn render_advanced_settings(&mut self, ui: &mut egui::Ui) {
+        let response = egui::CollapsingHeader::new("Advanced Settings")
+            .id_source("advanced_settings")
+            .show(ui, |ui| {
+                let mut changed = false;
+
+                changed |= self.render_backend_settings(ui);
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Subtitle checks").strong());
+                changed |= ui.checkbox(&mut self.validation.enabled, "Validate downloaded subtitles")
+                    .on_hover_text("Warn about empty files, HTML pages, unreadable text, broken timing and subtitles that don't match the video length")
+                    .changed();
+                ui.add_enabled_ui(self.validation.enabled, |ui| {
+                    changed |= ui.checkbox(&mut self.validation.retry_invalid, "Delete invalid subtitles and retry with another provider")
+                        .changed();
+                });
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Providers").strong());
+                ui.horizontal_wrapped(|ui| {
+                    for provider in SUBLIMINAL_PROVIDERS {
+                        let mut enabled = self.providers.enabled_providers.iter().any(|p| p == provider);
+                        // Keep at least one provider enabled
+                        let is_last = enabled && self.providers.enabled_providers.len() == 1;
+                        if ui.add_enabled(!is_last, egui::Checkbox::new(&mut enabled, *provider)).changed() {
+                            if enabled {
+                                self.providers.enabled_providers.push(provider.to_string());
+                            } else {
+                                self.providers.enabled_providers.retain(|p| p != provider);
+                            }
+                            changed = true;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Minimum score for movies:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.providers.min_score_movie).clamp_range(0..=100).suffix("%")).changed();
+                    ui.label("episodes:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.providers.min_score_episode).clamp_range(0..=100).suffix("%")).changed();
+                }).response.on_hover_text("Subtitles scoring below this are not downloaded. 0 accepts any match");
+                ui.horizontal(|ui| {
+                    ui.label("Max Subliminal runs per minute:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.providers.requests_per_minute).clamp_range(0..=MAX_REQUESTS_PER_MINUTE)).changed();
+                    ui.label("(0 = unlimited)");
+                }).response.on_hover_text("Shared by all concurrent downloads. Providers that report a quota or rate limit are paused automatically");
+                ui.horizontal(|ui| {
+                    ui.label("Attempts per video:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.retry.max_attempts).clamp_range(1..=MAX_RETRY_ATTEMPTS)).changed();
+                    ui.label("First retry after");
+                    changed |= ui.add(egui::DragValue::new(&mut self.retry.base_delay_secs).clamp_range(1..=MAX_RETRY_DELAY_SECS).suffix(" s")).changed();
+                }).response.on_hover_text("Network errors and Subliminal cache errors are retried with a growing delay. 1 attempt disables retries");
+                changed |= ui.checkbox(&mut self.retry.skip_recent_failures, "Skip videos whose subtitles weren't found recently")
+                    .on_hover_text("Searched again 1 day, 1 week and then 1 month after the last failure")
+                    .changed();
+                let mut age_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("Only videos modified in the last");
+                    let age_response = ui.add(egui::DragValue::new(&mut self.providers.max_age_days).clamp_range(0..=MAX_AGE_FILTER_DAYS));
+                    ui.label("days (0 = all)");
+                    changed |= age_response.changed();
+                    // Rescan once the value is settled instead of on every drag step
+                    age_changed = age_response.drag_stopped() || (age_response.changed() && !age_response.dragged());
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Folders read at once while scanning:");
+                    changed |= ui.add(egui::DragValue::new(&mut self.scan.threads).clamp_range(1..=MAX_SCAN_THREADS)).changed();
+                }).response.on_hover_text("More threads hide the latency of network shares");
+                let symlinks_changed = ui.checkbox(&mut self.scan.follow_symlinks, "Follow symbolic links to folders")
+                    .on_hover_text("Folders reached again through a link loop are scanned once")
+                    .changed();
+                changed |= symlinks_changed;
+                self.render_provider_accounts(ui);
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Audio language").strong());
+                let mut rule_changed = false;
+                ui.horizontal(|ui| {
+                    ui.label("When the audio is in a selected language:");
+                    egui::ComboBox::from_id_source("audio_language_rule")
+                        .selected_text(self.audio_language.rule.label())
+                        .show_ui(ui, |ui| {
+                            for rule in AudioLanguageRule::ALL {
+                                rule_changed |= ui.selectable_value(&mut self.audio_language.rule, rule, rule.label()).changed();
+                            }
+                        });
+                }).response.on_hover_text("Reads the default audio track language with ffprobe while scanning");
+                changed |= rule_changed;
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Embedded subtitles").strong());
+                changed |= ui.checkbox(&mut self.embedded.extract_text_tracks, "Extract embedded text subtitles to sidecar files")
+                    .on_hover_text("Languages with an embedded SRT/ASS track are extracted with ffmpeg instead of downloaded. Not used when 'Ignore Embedded Subtitles' is checked")
+                    .changed();
+                changed |= ui.checkbox(&mut self.ocr.enabled, "OCR embedded PGS/VobSub tracks when nothing is found online")
+                    .on_hover_text("Requires tesseract with the language data for the selected languages")
+                    .changed();
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Hearing impaired").strong());
+                ui.horizontal(|ui| {
+                    ui.label("SDH subtitles:");
+                    egui::ComboBox::from_id_source("hearing_impaired_preference")
+                        .selected_text(self.hearing_impaired.preference.label())
+                        .show_ui(ui, |ui| {
+                            for preference in HearingImpairedPreference::ALL {
+                                changed |= ui.selectable_value(&mut self.hearing_impaired.preference, preference, preference.label()).changed();
+                            }
+                        });
+                });
+                changed |= ui.checkbox(&mut self.hearing_impaired.strip_sdh, "Write SDH-free copies of downloaded subtitles")
+                    .on_hover_text("Removes [MUSIC], (door slams) and JOHN: speaker labels. The original is kept as .sdh.srt")
+                    .changed();
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Dual-language subtitles").strong());
+                changed |= ui.checkbox(&mut self.dual_subtitles.enabled, "Merge two downloaded languages into one file")
+                    .on_hover_text("Runs after both languages are downloaded, e.g. movie.en+ja.ass")
+                    .changed();
+                ui.add_enabled_ui(self.dual_subtitles.enabled, |ui| {
+                    let languages = self.selected_languages.clone();
+                    ui.horizontal(|ui| {
+                        ui.label("Primary:");
+                        changed |= Self::render_language_combo(ui, "dual_primary_language", &mut self.dual_subtitles.primary_language, &languages);
+                        ui.label("Secondary (top):");
+                        changed |= Self::render_language_combo(ui, "dual_secondary_language", &mut self.dual_subtitles.secondary_language, &languages);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Format:");
+                        changed |= ui.radio_value(&mut self.dual_subtitles.format, DualSubtitleFormat::Ass, "ASS (secondary on top)").changed();
+                        changed |= ui.radio_value(&mut self.dual_subtitles.format, DualSubtitleFormat::Srt, "SRT (stacked lines)").changed();
+                    });
+                });
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Machine translation").strong());
+                changed |= ui.checkbox(&mut self.translation.enabled, "Translate a downloaded language into missing ones")
+                    .on_hover_text("Keeps the timing of the source subtitle and saves e.g. movie.fr.machine-translated.srt")
+                    .changed();
+                ui.add_enabled_ui(self.translation.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        changed |= ui.radio_value(&mut self.translation.backend, TranslationBackend::LibreTranslate, "LibreTranslate").changed();
+                        changed |= ui.radio_value(&mut self.translation.backend, TranslationBackend::Command, "Local command").changed();
+                    });
+                    match self.translation.backend {
+                        TranslationBackend::LibreTranslate => {
+                            ui.horizontal(|ui| {
+                                ui.label("Endpoint:");
+                                changed |= ui.text_edit_singleline(&mut self.translation.endpoint).changed();
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("API key:");
+                                changed |= ui.add(egui::TextEdit::singleline(&mut self.translation.api_key).password(true)).changed();
+                            });
+                        }
+                        TranslationBackend::Command => {
+                            ui.horizontal(|ui| {
+                                ui.label("Command:");
+                                changed |= ui.text_edit_singleline(&mut self.translation.command)
+                                    .on_hover_text("Reads one cue per line on stdin and prints one translation per line. {source} and {target} are replaced with language codes. Quote paths that contain spaces")
+                                    .changed();
+                            });
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Cues per request:");
+                        changed |= ui.add(egui::DragValue::new(&mut self.translation.batch_size).clamp_range(1..=500)).changed();
+                    });
+                });
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Speech recognition").strong());
+                changed |= ui.checkbox(&mut self.speech_to_text.enabled, "Generate subtitles from audio when none are found")
+                    .on_hover_text("Last resort: transcribes the audio track with a local whisper.cpp build. Slow on CPU and marked as machine-generated")
+                    .changed();
+                ui.add_enabled_ui(self.speech_to_text.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("whisper.cpp binary:");
+                        changed |= ui.text_edit_singleline(&mut self.speech_to_text.binary_path).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Model:");
+                        changed |= ui.text_edit_singleline(&mut self.speech_to_text.model_path).changed();
+                        if ui.button("Browse").clicked() {
+                            if let Some(model) = FileDialog::new().add_filter("whisper model", &["bin"]).pick_file() {
+                                self.speech_to_text.model_path = model.display().to_string();
+                                changed = true;
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Threads:");
+                        changed |= ui.add(egui::DragValue::new(&mut self.speech_to_text.threads).clamp_range(1..=64)).changed();
+                    });
+                });
+
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Muxing").strong());
+                changed |= ui.checkbox(&mut self.mux.enabled, "Mux subtitles into MKV files")
+                    .on_hover_text("Adds the downloaded subtitles as tracks with language tags using ffmpeg. Other containers keep sidecar files")
+                    .changed();
+                ui.add_enabled_ui(self.mux.enabled, |ui| {
+                    changed |= ui.checkbox(&mut self.mux.delete_sidecars, "Delete sidecar files after muxing")
+                        .changed();
+                });
+
+                if changed {
+                    info!("Advanced settings changed: validation={}, retry={}, hearing_impaired={:?}, strip_sdh={}, audio_rule={:?}",
+                        self.validation.enabled, self.validation.retry_invalid,
+                        self.hearing_impaired.preference, self.hearing_impaired.strip_sdh, self.audio_language.rule);
+                    self.save_current_settings();
+                }
+                // The audio rule, age filter and symlink setting decide which videos are missing subtitles.
+                // Folders read under the other symlink setting aren't trusted from the index.
+                if !self.get_folder_path().is_empty() {
+                    if symlinks_changed {
+                        self.full_rescan();
+                    } else if rule_changed || age_changed {
+                        self.scan_folder();
+                    }
+                }
+            });
+        if response.header_response.clicked() {
+            self.set_keep_dropdown_open(false); // Close dropdown when section is toggled
+        }
+    }
+
+    /// Render the download backend choice, returning true if changed
+    fn render_backend_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        ui.label(egui::RichText::new("Download backend").strong());
+        ui.horizontal(|ui| {
+            for backend in DownloadBackend::ALL {
+                changed |= ui.radio_value(&mut self.backend.backend, backend, backend.label()).changed();
+            }
+        });
+        if self.backend.backend == DownloadBackend::OpenSubtitles {
+            ui.horizontal(|ui| {
+                ui.label("API key:");
+                changed |= ui.add(egui::TextEdit::singleline(&mut self.backend.opensubtitles_api_key).password(true).desired_width(240.0))
+                    .on_hover_text("Create a consumer API key on opensubtitles.com. Log in with the opensubtitlescom account below for a higher download quota")
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("API URL:");
+                changed |= ui.add(egui::TextEdit::singleline(&mut self.backend.opensubtitles_base_url).desired_width(240.0)).changed();
+            });
+        }
+        changed
+    }
+
+    /// Render username and password fields with a login test for each provider that has accounts
+    fn render_provider_accounts(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(5.0);
+        ui.label(egui::RichText::new("Provider accounts").strong());
+        let storage_note = match ProviderAccounts::storage() {
+            CredentialStorage::SecretService => "Stored in the system keyring, not in the settings file".to_string(),
+            CredentialStorage::Keychain => "Stored in your login Keychain, not in the settings file".to_string(),
+            CredentialStorage::EncryptedFile => match ProviderAccounts::file_path() {
+                Ok(path) => format!("Stored in {}, encrypted for your Windows user", path.display()),
+                Err(_) => "Stored in a credentials file encrypted for your Windows user".to_string(),
+            },
+            CredentialStorage::Session => "No system keyring found (install secret-tool), so accounts are kept until Rustitles closes".to_string(),
+        };
+        ui.label(egui::RichText::new(storage_note).small().color(egui::Color32::GRAY));
+
+        let tests = self.account_login_tests.lock().unwrap().clone();
+        let mut test_provider = None;
+        egui::Grid::new("provider_accounts").num_columns(5).spacing([6.0, 4.0]).show(ui, |ui| {
+            for provider in ACCOUNT_PROVIDERS {
+                let account = self.provider_accounts.accounts.entry(provider.to_string()).or_default();
+                ui.label(*provider);
+                ui.add(egui::TextEdit::singleline(&mut account.username).hint_text("Username").desired_width(120.0));
+                ui.add(egui::TextEdit::singleline(&mut account.password).hint_text("Password").password(true).desired_width(120.0));
+                let testing = matches!(tests.get(*provider), Some(None));
+                if ui.add_enabled(!testing && !account.is_empty(), egui::Button::new("Test login")).clicked() {
+                    test_provider = Some(provider.to_string());
+                }
+                match tests.get(*provider) {
+                    Some(None) => {
+                        ui.spinner();
+                        ui.ctx().request_repaint_after(std::time::Duration::from_millis(200));
+                    }
+                    Some(Some(Ok(()))) => { ui.colored_label(egui::Color32::from_rgb(0, 200, 0), "Login OK"); }
+                    Some(Some(Err(e))) => { ui.colored_label(egui::Color32::from_rgb(255, 100, 100), e); }
+                    None => { ui.label(""); }
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(provider) = test_provider {
+            self.test_provider_login(&provider);
+        }
+        if ui.button("Save accounts").clicked() {
+            self.save_provider_accounts();
+        }
+    }
+
+    /// Render a combo box choosing one of the selected languages, returning true if changed
+    fn render_language_combo(ui: &mut egui::Ui, id: &str, value: &mut String, languages: &[String]) -> bool {
+        let mut changed = false;
+        let selected_text = if value.is_empty() { "Select".to_string() } else { value.clone() };
+        egui::ComboBox::from_id_source(id)
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for lang in languages {
+                    let label = format!("{} [{}]", SubtitleUtils::language_code_to_name(lang), lang);
+                    changed |= ui.selectable_value(value, lang.clone(), label).changed();
+                }
+            });
+        changed
+    }
+
     /// Render folder selection interface
     pub fn render_folder_selection(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
@@ -356,6 +741,14 @@ impl SubtitleDownloader {
                     }
                 }
             }
+            let can_rescan = !self.get_folder_path().is_empty() && !self.scanning;
+            if ui.add_enabled(can_rescan, egui::Button::new("Full rescan"))
+                .on_hover_text("Read every folder again instead of only the ones that changed since the last scan")
+                .clicked()
+            {
+                info!("Full rescan requested: {}", self.get_folder_path());
+                self.full_rescan();
+            }
             ui.label(self.get_folder_path());
         });
     }
@@ -396,13 +789,160 @@ impl SubtitleDownloader {
                     ui.add_space(5.0);
                     ui.label(format!("Ignoring {} extra folders", self.get_ignored_extra_folders()));
                 }
+
+                // Show videos skipped by the audio language rule
+                if self.get_skipped_for_audio_language() > 0 {
+                    ui.add_space(5.0);
+                    ui.label("-");
+                    ui.add_space(5.0);
+                    ui.label(format!("Skipped {} for audio language", self.get_skipped_for_audio_language()));
+                }
+
+                // Show videos left out by the age filter
+                if self.get_skipped_for_age() > 0 {
+                    ui.add_space(5.0);
+                    ui.label("-");
+                    ui.add_space(5.0);
+                    ui.label(format!("Skipped {} older than {} days", self.get_skipped_for_age(), self.providers.max_age_days));
+                }
+
+                // Show videos left out because their subtitles weren't found recently
+                if self.get_skipped_for_history() > 0 {
+                    ui.add_space(5.0);
+                    ui.label("-");
+                    ui.add_space(5.0);
+                    ui.label(format!("Skipped {} not found recently", self.get_skipped_for_history()))
+                        .on_hover_text("Searched again 1 day, 1 week and then 1 month after the last failure");
+                }
+
+                // Show copies of the same video in different places
+                let duplicates = self.get_duplicate_videos();
+                if !duplicates.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label("-");
+                    ui.add_space(5.0);
+                    let details = duplicates.iter()
+                        .map(|group| group.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join("\n"))
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    ui.label(format!("{} duplicate videos", duplicates.iter().map(|g| g.len()).sum::<usize>()))
+                        .on_hover_text(details);
+                }
+
+                // Show folders the scan couldn't read, e.g. for lack of permissions
+                let unreadable = self.get_unreadable_paths();
+                if !unreadable.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label("-");
+                    ui.add_space(5.0);
+                    let details = unreadable.iter()
+                        .map(|(path, error)| format!("{}: {}", path.display(), error))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ui.colored_label(egui::Color32::from_rgb(255, 85, 85), format!("{} unreadable folders", unreadable.len()))
+                        .on_hover_text(details);
+                }
             });
         }
     }
 
+    /// Render per-provider downloads, remaining quota and pauses
+    fn render_provider_limits(ui: &mut egui::Ui) {
+        let statuses = ProviderLimits::statuses();
+        if statuses.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            for status in statuses {
+                let mut text = format!("{}: {} downloaded", status.provider, status.downloads);
+                if let Some(remaining) = status.remaining {
+                    text.push_str(&format!(", {} left", remaining));
+                }
+                match status.paused_until {
+                    Some((until, kind)) => {
+                        text.push_str(&format!(", paused until {} ({})", until.format("%H:%M UTC"), kind.label()));
+                        ui.colored_label(egui::Color32::from_rgb(139, 233, 253), text);
+                    }
+                    None => {
+                        ui.label(egui::RichText::new(text).color(egui::Color32::GRAY));
+                    }
+                }
+                ui.add_space(10.0);
+            }
+        });
+    }
+
+    /// Render the videos found by the scan with checkboxes to choose which ones to download
+    pub fn render_scan_preview(&mut self, ui: &mut egui::Ui) {
+        let mut start = false;
+        let mut cancel = false;
+        let Some(preview) = self.scan_preview.as_mut() else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Review: {} of {} videos selected", preview.selected_count(), preview.videos.len()));
+            if ui.small_button("Select all").clicked() {
+                preview.set_all_selected(true);
+            }
+            if ui.small_button("Select none").clicked() {
+                preview.set_all_selected(false);
+            }
+        });
+        ui.separator();
+
+        let reserved_height = 110.0; // Buttons, status and progress below the list
+        let scroll_height = (ui.available_height() - reserved_height).max(200.0);
+        let row_height = ui.spacing().interact_size.y;
+        // Taken out while rendering so the rows can change the selection
+        let rows = std::mem::take(&mut preview.rows);
+        egui::ScrollArea::vertical()
+            .max_height(scroll_height)
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, rows.len(), |ui, row_range| {
+                for row in &rows[row_range] {
+                    ui.horizontal(|ui| {
+                        ui.set_height(row_height);
+                        match *row {
+                            ScanPreviewRow::Folder(folder) => {
+                                let count = preview.folders[folder].1.len();
+                                let selected = preview.folder_selected_count(folder);
+                                let mut all_selected = selected == count;
+                                let label = format!("📁 {} ({}/{})", preview.folders[folder].0.display(), selected, count);
+                                if ui.checkbox(&mut all_selected, egui::RichText::new(label).strong()).changed() {
+                                    preview.set_folder_selected(folder, all_selected);
+                                }
+                            }
+                            ScanPreviewRow::Video(index) => {
+                                ui.add_space(20.0);
+                                let file_name = Utils::get_file_name(&preview.videos[index].path);
+                                ui.checkbox(&mut preview.selected[index], Utils::truncate_string(&file_name, 50));
+                                ui.label(egui::RichText::new(&preview.videos[index].reason).color(egui::Color32::GRAY));
+                            }
+                        }
+                    });
+                }
+            });
+        preview.rows = rows;
+
+        ui.horizontal(|ui| {
+            let selected = preview.selected_count();
+            start = ui.add_enabled(selected > 0, egui::Button::new(format!("Download subtitles for {} videos", selected))).clicked();
+            cancel = ui.button("Cancel").clicked();
+        });
+
+        if start {
+            self.start_selected_downloads();
+        } else if cancel {
+            info!("Scan preview dismissed");
+            self.scan_preview = None;
+            self.status = "Downloads not started.".to_string();
+        }
+    }
+
     /// Render download jobs status
     pub fn render_download_jobs(&mut self, ui: &mut egui::Ui) {
-        // Update cached jobs if needed
+        // Pull the jobs that changed since the last frame
         self.update_cached_jobs();
         
         let cached_jobs = self.get_cached_jobs();
@@ -410,7 +950,17 @@ impl SubtitleDownloader {
             return;
         }
         
-        ui.label("Subliminal Jobs:");
+        let totals = self.job_list.totals();
+        let mut retry_all = false;
+        ui.horizontal(|ui| {
+            ui.label("Subliminal Jobs:");
+            if !self.is_downloading() && totals.has_retryable() {
+                retry_all = ui.small_button(format!("Retry failed ({})", totals.failed + totals.deferred))
+                    .on_hover_text("Run every failed and deferred job of this run again")
+                    .clicked();
+            }
+        });
+        Self::render_provider_limits(ui);
         ui.separator();
         
         // Calculate available height for the scroll area
@@ -419,79 +969,239 @@ impl SubtitleDownloader {
         let available_height = ui.available_height() - reserved_height;
         let scroll_height = available_height.max(200.0); // Minimum height of 200px
         
+        // Every row has the same height so only the visible ones need to be laid out
+        let row_height = ui.spacing().interact_size.y.max(ui.text_style_height(&egui::TextStyle::Body) + 4.0);
+        let job_list = &self.job_list;
+        let rows = job_list.rows();
+        let can_retry = !self.is_downloading();
+        let mut toggle_request = None;
+        let mut retry_request = None;
+        let mut history_request = None;
         egui::ScrollArea::vertical()
             .max_height(scroll_height)
             .auto_shrink([false, false])
-            .show(ui, |ui| {
-                
-                for job in cached_jobs {
-                    let (status_text, status_color) = match &job.status {
-                        JobStatus::Pending => ("Pending".to_string(), Some(egui::Color32::from_rgb(241, 250, 140))), // yellow
-                        JobStatus::Running => ("Running".to_string(), Some(egui::Color32::from_rgb(189, 147, 249))), // lighter purple
-                        JobStatus::Success => ("Success".to_string(), Some(egui::Color32::from_rgb(80, 250, 123))), // green
-                        JobStatus::EmbeddedExists(msg) => (msg.clone(), Some(egui::Color32::from_rgb(255, 184, 108))), // orange
-                        JobStatus::Failed(err) => (format!("Failed: {}", err), Some(egui::Color32::from_rgb(255, 85, 85))), // red
-                    };
-                    // Video name and status on first line
+            .show_rows(ui, row_height, rows.len(), |ui, row_range| {
+                for row in &rows[row_range] {
                     ui.horizontal(|ui| {
-                        let file_name = Utils::get_file_name(&job.video_path);
-                        ui.label(Utils::truncate_string(&file_name, 50));
-                        match status_color {
-                            Some(color) => ui.label(egui::RichText::new(format!(" - {}", status_text)).color(color)),
-                            None => ui.label(format!(" - {}", status_text)),
-                        };
-                    });
-                    
-                    // Subtitle path on second line
-                    for sub_path in &job.subtitle_paths {
-                        ui.horizontal(|ui| {
-                            ui.add_space(20.0); // Indent the subtitle path
-                            let path_str = sub_path.display().to_string();
-                            let is_srt = sub_path.extension().map(|e| e.eq_ignore_ascii_case("srt")).unwrap_or(false);
-                            if is_srt {
-                                let text = format!("📄 {}", path_str);
-                                let font_id = egui::TextStyle::Body.resolve(ui.style());
-                                let galley_normal = ui.fonts(|f| f.layout_no_wrap(text.clone(), font_id.clone(), egui::Color32::WHITE));
-                                let _galley_underlined = ui.fonts(|f| f.layout_no_wrap(text.clone(), font_id.clone(), egui::Color32::WHITE));
-                                let padding = egui::vec2(8.0, 4.0);
-                                let size = galley_normal.size() + padding;
-                                let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
-                                let hovered = response.hovered();
-                                let painter = ui.painter();
-                                let text_pos = egui::pos2(
-                                    rect.left() + padding.x / 2.0,
-                                    rect.top() + padding.y / 2.0
-                                );
-                                if hovered {
-                                    // Underline using RichText and paint
-                                    let galley = ui.fonts(|f| f.layout_no_wrap(
-                                        text.clone(),
-                                        font_id.clone(),
-                                        egui::Color32::WHITE
-                                    ));
-                                    painter.galley(text_pos, galley.clone(), egui::Color32::WHITE);
-                                    // Draw underline manually
-                                    let underline_y = text_pos.y + galley.size().y - 1.0;
-                                    painter.line_segment([
-                                        egui::pos2(text_pos.x, underline_y),
-                                        egui::pos2(text_pos.x + galley.size().x, underline_y)
-                                    ], egui::Stroke::new(1.5, egui::Color32::WHITE));
-                                    ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
-                                } else {
-                                    painter.galley(text_pos, galley_normal.clone(), egui::Color32::WHITE);
+                        ui.set_height(row_height);
+                        match *row {
+                            JobListRow::Group { group, season } => {
+                                let Some((node, counts)) = job_list.group(group, season) else {
+                                    return;
+                                };
+                                ui.add_space(if season.is_some() { 20.0 } else { 0.0 });
+                                let arrow = if job_list.is_expanded(&node.id) { "⏷" } else { "⏵" };
+                                if ui.add(egui::Button::new(format!("{} {}", arrow, node.label)).frame(false)).clicked() {
+                                    toggle_request = Some(node.id.clone());
                                 }
-                                if response.clicked() {
-                                    if let Err(e) = Utils::open_containing_folder(sub_path) {
-                                        warn!("Failed to open folder for {}: {}", path_str, e);
+                                Self::render_group_counts(ui, counts);
+                                if can_retry && counts.has_retryable() && ui.small_button("Retry").on_hover_text("Retry failed and deferred jobs in this group").clicked() {
+                                    retry_request = Some(node.job_indexes.clone());
+                                }
+                            }
+                            JobListRow::Job { index, depth } => {
+                                if let Some(job) = cached_jobs.get(index) {
+                                    ui.add_space(depth as f32 * 20.0);
+                                    if Self::render_job_line(ui, job) {
+                                        history_request = Some(job.video_path.clone());
                                     }
                                 }
-                            } else {
-                                ui.label(format!("📄 {}", path_str));
                             }
-                        });
-                    }
+                            JobListRow::Detail { index, detail, depth } => {
+                                if let Some(job) = cached_jobs.get(index) {
+                                    ui.add_space(depth as f32 * 20.0);
+                                    Self::render_job_detail(ui, job, detail);
+                                }
+                            }
+                        }
+                    });
                 }
             });
+
+        if history_request.is_some() {
+            self.history_video = history_request;
+        }
+        if retry_all {
+            retry_request = Some((0..self.cached_jobs.len()).collect());
+        }
+        if let Some(id) = toggle_request {
+            self.job_list.toggle(&id, &self.cached_jobs);
+        }
+        if let Some(indexes) = retry_request {
+            self.retry_jobs(&indexes);
+        }
+    }
+
+    /// Render a group's job counts by outcome
+    fn render_group_counts(ui: &mut egui::Ui, counts: JobGroupCounts) {
+        ui.label(egui::RichText::new(format!("✔ {}", counts.succeeded)).color(egui::Color32::from_rgb(80, 250, 123)));
+        if counts.failed > 0 {
+            ui.label(egui::RichText::new(format!("✖ {}", counts.failed)).color(egui::Color32::from_rgb(255, 85, 85)));
+        }
+        if counts.deferred > 0 {
+            ui.label(egui::RichText::new(format!("⏸ {}", counts.deferred)).color(egui::Color32::from_rgb(139, 233, 253)));
+        }
+        if counts.remaining() > 0 {
+            ui.label(egui::RichText::new(format!("… {}", counts.remaining())).color(egui::Color32::GRAY));
+        }
+    }
+
+    /// Render a job's video name and status, returning whether its history was requested
+    fn render_job_line(ui: &mut egui::Ui, job: &DownloadJob) -> bool {
+        let (status_text, status_color) = match &job.status {
+            JobStatus::Pending => match &job.last_error {
+                Some(error) => (format!("Retrying after attempt {}: {}", job.attempt, error), Some(egui::Color32::from_rgb(241, 250, 140))), // yellow
+                None => ("Pending".to_string(), Some(egui::Color32::from_rgb(241, 250, 140))), // yellow
+            },
+            JobStatus::Running if job.attempt > 1 => (format!("Running (attempt {})", job.attempt), Some(egui::Color32::from_rgb(189, 147, 249))), // lighter purple
+            JobStatus::Running => ("Running".to_string(), Some(egui::Color32::from_rgb(189, 147, 249))), // lighter purple
+            JobStatus::Success => ("Success".to_string(), Some(egui::Color32::from_rgb(80, 250, 123))), // green
+            JobStatus::Muxed => ("Muxed into MKV".to_string(), Some(egui::Color32::from_rgb(80, 250, 123))), // green
+            JobStatus::EmbeddedExists(msg) => (msg.clone(), Some(egui::Color32::from_rgb(255, 184, 108))), // orange
+            JobStatus::Deferred(reason) => (format!("Deferred: {}", reason), Some(egui::Color32::from_rgb(139, 233, 253))), // cyan
+            JobStatus::Failed(err) if job.attempt > 1 => (format!("Failed after {} attempts: {}", job.attempt, err), Some(egui::Color32::from_rgb(255, 85, 85))), // red
+            JobStatus::Failed(err) => (format!("Failed: {}", err), Some(egui::Color32::from_rgb(255, 85, 85))), // red
+        };
+        let history_clicked = ui.add(egui::Button::new("🕘").frame(false))
+            .on_hover_text("Download history")
+            .clicked();
+        let file_name = Utils::get_file_name(&job.video_path);
+        ui.label(Utils::truncate_string(&file_name, 50));
+        match status_color {
+            Some(color) => ui.label(egui::RichText::new(format!(" - {}", status_text)).color(color)),
+            None => ui.label(format!(" - {}", status_text)),
+        };
+        history_clicked
+    }
+
+    /// Render the window listing every recorded attempt for a video
+    pub fn render_job_history(&mut self, ctx: &egui::Context) {
+        let Some(video) = self.history_video.clone() else {
+            return;
+        };
+        let mut open = true;
+        let mut forget = false;
+        egui::Window::new("Download history")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(Utils::get_file_name(&video)).strong());
+                ui.label(egui::RichText::new(video.display().to_string()).color(egui::Color32::GRAY));
+                ui.separator();
+                let entries = JobHistory::entries(&video);
+                if entries.is_empty() {
+                    ui.label("No attempts recorded yet.");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("job_history_grid").striped(true).num_columns(5).show(ui, |ui| {
+                        for header in ["Time", "Language", "Outcome", "Provider", "Score"] {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        ui.end_row();
+                        for entry in &entries {
+                            let time = entry.local_time().map(|t| t.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+                            ui.label(time);
+                            ui.label(SubtitleUtils::language_code_to_name(&entry.language));
+                            let color = match entry.outcome {
+                                HistoryOutcome::Found => egui::Color32::from_rgb(80, 250, 123),
+                                HistoryOutcome::NotFound => egui::Color32::from_rgb(255, 85, 85),
+                                HistoryOutcome::Deferred => egui::Color32::from_rgb(139, 233, 253),
+                                HistoryOutcome::Error => egui::Color32::from_rgb(255, 184, 108),
+                            };
+                            let outcome = ui.label(egui::RichText::new(entry.outcome.label()).color(color));
+                            if let Some(message) = &entry.message {
+                                outcome.on_hover_text(message);
+                            }
+                            ui.label(entry.provider.as_deref().unwrap_or("-"));
+                            ui.label(entry.score.map(|s| format!("{:.0}%", s)).unwrap_or_else(|| "-".to_string()));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                forget = ui.button("Forget history")
+                    .on_hover_text("The next scan includes this video again even if it failed recently")
+                    .clicked();
+            });
+        if forget {
+            info!("Forgetting job history for {}", video.display());
+            JobHistory::forget(&video);
+            JobHistory::save();
+        }
+        if !open {
+            self.history_video = None;
+        }
+    }
+
+    /// Render a line below a job: a validation warning, a machine-generated note or a subtitle path
+    fn render_job_detail(ui: &mut egui::Ui, job: &DownloadJob, detail: JobDetail) {
+        match detail {
+            JobDetail::Warning(i) => {
+                if let Some(warning) = job.warnings.get(i) {
+                    ui.label(egui::RichText::new(format!("⚠ {}", warning)).color(egui::Color32::from_rgb(255, 184, 108)));
+                }
+            }
+            JobDetail::MachineGenerated(i) => {
+                let outcome = job.language_outcomes.get(i);
+                if let Some((outcome, note)) = outcome.and_then(|o| Some((o, o.origin.machine_generated_note()?))) {
+                    ui.label(egui::RichText::new(format!("🤖 {}: {}", outcome.language, note)).color(egui::Color32::from_rgb(189, 147, 249)));
+                }
+            }
+            JobDetail::Subtitle(i) => {
+                if let Some(sub_path) = job.subtitle_paths.get(i) {
+                    Self::render_subtitle_link(ui, sub_path);
+                }
+            }
+        }
+    }
+
+    /// Render a subtitle path that opens its folder when clicked
+    fn render_subtitle_link(ui: &mut egui::Ui, sub_path: &std::path::Path) {
+        let path_str = sub_path.display().to_string();
+        let is_srt = sub_path.extension().map(|e| e.eq_ignore_ascii_case("srt")).unwrap_or(false);
+        if is_srt {
+            let text = format!("📄 {}", path_str);
+            let font_id = egui::TextStyle::Body.resolve(ui.style());
+            let galley_normal = ui.fonts(|f| f.layout_no_wrap(text.clone(), font_id.clone(), egui::Color32::WHITE));
+            let _galley_underlined = ui.fonts(|f| f.layout_no_wrap(text.clone(), font_id.clone(), egui::Color32::WHITE));
+            let padding = egui::vec2(8.0, 4.0);
+            let size = galley_normal.size() + padding;
+            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+            let hovered = response.hovered();
+            let painter = ui.painter();
+            let text_pos = egui::pos2(
+                rect.left() + padding.x / 2.0,
+                rect.top() + padding.y / 2.0
+            );
+            if hovered {
+                // Underline using RichText and paint
+                let galley = ui.fonts(|f| f.layout_no_wrap(
+                    text.clone(),
+                    font_id.clone(),
+                    egui::Color32::WHITE
+                ));
+                painter.galley(text_pos, galley.clone(), egui::Color32::WHITE);
+                // Draw underline manually
+                let underline_y = text_pos.y + galley.size().y - 1.0;
+                painter.line_segment([
+                    egui::pos2(text_pos.x, underline_y),
+                    egui::pos2(text_pos.x + galley.size().x, underline_y)
+                ], egui::Stroke::new(1.5, egui::Color32::WHITE));
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+            } else {
+                painter.galley(text_pos, galley_normal.clone(), egui::Color32::WHITE);
+            }
+            if response.clicked() {
+                if let Err(e) = Utils::open_containing_folder(sub_path) {
+                    warn!("Failed to open folder for {}: {}", path_str, e);
+                }
+            }
+        } else {
+            ui.label(format!("📄 {}", path_str));
+        }
     }
 
     /// Render status with optional spinning indicator or check mark
@@ -539,13 +1249,9 @@ impl SubtitleDownloader {
                 ui.add_space(20.0); // Space between spinner and text
             } else if self.get_total_downloads() > 0 && self.get_downloads_completed() == self.get_total_downloads() {
                 // Check if all downloads failed or all succeeded
-                let cached_jobs = self.get_cached_jobs();
-                let all_failed = cached_jobs.iter().all(|j| {
-                    matches!(j.status, JobStatus::Failed(_))
-                });
-                let all_succeeded = cached_jobs.iter().all(|j| {
-                    matches!(j.status, JobStatus::Success | JobStatus::EmbeddedExists(_))
-                });
+                let totals = self.job_list.totals();
+                let all_failed = totals.failed == totals.total();
+                let all_succeeded = totals.succeeded == totals.total();
                 
                 let center = ui.cursor().min + egui::vec2(8.0, 8.0);
                 let painter = ui.painter();
@@ -590,25 +1296,21 @@ impl SubtitleDownloader {
     /// Render progress bar
     pub fn render_progress_bar(&self, ui: &mut egui::Ui) {
         // Count all jobs that are not Pending or Running as completed
-        let cached_jobs = self.get_cached_jobs();
-        let completed_count = cached_jobs.iter().filter(|j| {
-            !matches!(j.status, JobStatus::Pending | JobStatus::Running)
-        }).count();
+        let totals = self.job_list.totals();
+        let completed_count = totals.total() - totals.remaining();
         let total = self.get_total_downloads();
         // Show progress bar only when downloads are active or complete
-        if self.is_downloading() || (!self.is_downloading() && total > 0) {
-            if total > 0 {
-                ui.add_space(10.0);
-                let progress_text = format!("Progress: {} / {} ({})", 
-                    completed_count, 
-                    total,
-                    Utils::format_progress(completed_count, total)
-                );
-                ui.label(progress_text);
-            }
+        if total > 0 {
+            ui.add_space(10.0);
+            let progress_text = format!("Progress: {} / {} ({})", 
+                completed_count, 
+                total,
+                Utils::format_progress(completed_count, total)
+            );
+            ui.label(progress_text);
         }
         // Place the progress bar here, outside the ScrollArea. always fit the window
-        if (self.is_downloading() || (!self.is_downloading() && total > 0)) && total > 0 {
+        if total > 0 {
             let progress = completed_count as f32 / total as f32;
             let window_width = ui.ctx().screen_rect().width();
             let progress_bar = egui::ProgressBar::new(progress)
@@ -646,19 +1348,30 @@ impl eframe::App for SubtitleDownloader {
             self.render_subliminal_status(ui);
             ui.separator();
 
-            // Only show language selection and folder selection after subliminal is installed
-            if self.subliminal_installed {
+            // Only show language selection and folder selection after subliminal is installed,
+            // unless the built-in OpenSubtitles.com client is used
+            if self.subliminal_installed || self.backend.backend == DownloadBackend::OpenSubtitles {
                 self.render_language_selection(ui);
                 ui.separator();
                 self.render_concurrent_downloads(ui);
                 ui.separator();
+                self.render_advanced_settings(ui);
+                ui.separator();
                 self.render_folder_selection(ui);
                 ui.separator();
                 self.render_scan_results(ui);
-                self.render_download_jobs(ui);
+                if self.scan_preview.is_some() {
+                    self.render_scan_preview(ui);
+                } else {
+                    self.render_download_jobs(ui);
+                }
             } else {
                 // Show message when subliminal is not installed
                 ui.label("Please install all dependencies before downloading subtitles.");
+                if ui.button("Use the built-in OpenSubtitles.com client instead").clicked() {
+                    self.backend.backend = DownloadBackend::OpenSubtitles;
+                    self.save_current_settings();
+                }
             }
 
             if !self.folder_path.is_empty() {
@@ -668,24 +1381,40 @@ impl eframe::App for SubtitleDownloader {
             self.render_status(ui);
             self.render_progress_bar(ui);
         });
+        self.render_job_history(ctx);
 
         // When scan finishes, start downloads automatically
         if self.scanning {
             if let Some(rx) = &self.scan_done_receiver {
-                if let Ok(ignored_count) = rx.try_recv() {
+                if let Ok(summary) = rx.try_recv() {
                     self.scanning = false;
                     self.status = "Scan completed.".to_string();
                     self.scan_done_receiver = None;
                     
                     // Update the ignored extra folders count
-                    self.ignored_extra_folders = ignored_count;
-                    if ignored_count > 0 {
-                        info!("Scan completed with {} extra folders ignored", ignored_count);
+                    self.ignored_extra_folders = summary.ignored_extra_folders;
+                    if summary.ignored_extra_folders > 0 {
+                        info!("Scan completed with {} extra folders ignored", summary.ignored_extra_folders);
+                    }
+                    self.skipped_for_audio_language = summary.skipped_for_audio_language;
+                    self.audio_languages = summary.audio_languages;
+                    self.skipped_for_age = summary.skipped_for_age;
+                    self.skipped_for_history = summary.skipped_for_history;
+                    self.duplicate_videos = summary.duplicate_videos;
+                    if !summary.unreadable_paths.is_empty() {
+                        warn!("Scan could not read {} folders", summary.unreadable_paths.len());
                     }
+                    self.unreadable_paths = summary.unreadable_paths;
 
-                    // Start downloads automatically after scan
-                    info!("Scan completed, starting downloads automatically");
-                    self.start_downloads();
+                    if self.auto_start_downloads {
+                        // Start downloads automatically after scan
+                        info!("Scan completed, starting downloads automatically");
+                        self.start_downloads();
+                    } else if !summary.missing_videos.is_empty() {
+                        info!("Scan completed, waiting for {} videos to be reviewed", summary.missing_videos.len());
+                        self.status = "Scan completed. Review the videos and start the downloads.".to_string();
+                        self.scan_preview = Some(ScanPreview::new(summary.missing_videos));
+                    }
                 }
             }
         }
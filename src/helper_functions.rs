@@ -3,8 +3,8 @@
 //! This module provides utility functions for file operations, string formatting,
 //! progress tracking, and input validation used throughout the application.
 
-use std::path::Path;
-use crate::config::{VIDEO_EXTENSIONS, MAX_CONCURRENT_DOWNLOADS};
+use std::path::{Path, PathBuf};
+use crate::config::{VIDEO_EXTENSIONS, MAX_AGE_FILTER_DAYS, MAX_CONCURRENT_DOWNLOADS, MAX_REQUESTS_PER_MINUTE, MAX_RETRY_ATTEMPTS, MAX_RETRY_DELAY_SECS, MAX_SCAN_THREADS, SUBLIMINAL_PROVIDERS};
 
 /// Common utility functions used throughout the application
 pub struct Utils;
@@ -35,6 +35,16 @@ impl Utils {
             .unwrap_or(false)
     }
 
+    /// Check if a file was modified within the last number of days
+    pub fn modified_within_days(path: &Path, days: u32) -> bool {
+        let max_age = std::time::Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age <= max_age)
+    }
+
     /// Create a progress percentage string
     pub fn format_progress(current: usize, total: usize) -> String {
         if total == 0 {
@@ -45,6 +55,96 @@ impl Utils {
         }
     }
 
+    /// Path of a file in the application cache directory, creating the directory
+    pub fn app_cache_path(file_name: &str) -> std::io::Result<PathBuf> {
+        #[cfg(windows)]
+        {
+            let exe_path = std::env::current_exe()?;
+            let exe_dir = exe_path.parent().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get executable directory")
+            })?;
+            Ok(exe_dir.join(format!("rustitles_{}", file_name)))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let home_dir = dirs::home_dir().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get home directory")
+            })?;
+            let app_dir = home_dir.join("Library/Caches/rustitles");
+            std::fs::create_dir_all(&app_dir)?;
+            Ok(app_dir.join(file_name))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let app_dir = match xdg::BaseDirectories::new() {
+                Ok(xdg_dirs) => xdg_dirs.get_cache_home().join("rustitles"),
+                Err(_) => dirs::home_dir().ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get home directory")
+                })?.join(".rustitles"),
+            };
+            std::fs::create_dir_all(&app_dir)?;
+            Ok(app_dir.join(file_name))
+        }
+    }
+
+    /// Path of a file in the application data directory (kept when caches are cleared),
+    /// creating the directory
+    pub fn app_data_path(file_name: &str) -> std::io::Result<PathBuf> {
+        #[cfg(windows)]
+        {
+            let exe_path = std::env::current_exe()?;
+            let exe_dir = exe_path.parent().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get executable directory")
+            })?;
+            Ok(exe_dir.join(format!("rustitles_{}", file_name)))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let home_dir = dirs::home_dir().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get home directory")
+            })?;
+            let app_dir = home_dir.join("Library/Application Support/rustitles");
+            std::fs::create_dir_all(&app_dir)?;
+            Ok(app_dir.join(file_name))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let app_dir = match xdg::BaseDirectories::new() {
+                Ok(xdg_dirs) => xdg_dirs.get_data_home().join("rustitles"),
+                Err(_) => dirs::home_dir().ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "Failed to get home directory")
+                })?.join(".rustitles"),
+            };
+            std::fs::create_dir_all(&app_dir)?;
+            Ok(app_dir.join(file_name))
+        }
+    }
+
+    /// Create a command for an external tool with the console window hidden on Windows
+    /// and output captured on Unix systems
+    pub fn hidden_command(program: &str) -> std::process::Command {
+        let mut cmd = std::process::Command::new(program);
+        // Hide the window on Windows
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+
+        // On Unix systems, just redirect output
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            use std::process::Stdio;
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+        }
+        cmd
+    }
+
     /// Open the containing folder of a file in the system's file explorer
     pub fn open_containing_folder(path: &Path) -> Result<(), String> {
         let _folder = path.parent().ok_or("No parent folder")?;
@@ -95,4 +195,39 @@ impl Validation {
     pub fn is_valid_concurrent_downloads(value: usize) -> bool {
         value > 0 && value <= MAX_CONCURRENT_DOWNLOADS
     }
+
+    /// Validate a minimum score percentage
+    pub fn is_valid_min_score(value: u32) -> bool {
+        value <= 100
+    }
+
+    /// Validate a provider name against the providers shipped with Subliminal
+    pub fn is_valid_provider(name: &str) -> bool {
+        SUBLIMINAL_PROVIDERS.contains(&name)
+    }
+
+    /// Validate an age filter in days (0 disables the filter)
+    pub fn is_valid_age_days(value: u32) -> bool {
+        value <= MAX_AGE_FILTER_DAYS
+    }
+
+    /// Validate the requests-per-minute limit (0 disables the limit)
+    pub fn is_valid_requests_per_minute(value: u32) -> bool {
+        value <= MAX_REQUESTS_PER_MINUTE
+    }
+
+    /// Validate the number of attempts per job (1 disables retries)
+    pub fn is_valid_retry_attempts(value: u32) -> bool {
+        (1..=MAX_RETRY_ATTEMPTS).contains(&value)
+    }
+
+    /// Validate the delay in seconds before the first retry
+    pub fn is_valid_retry_delay(value: u32) -> bool {
+        (1..=MAX_RETRY_DELAY_SECS).contains(&value)
+    }
+
+    /// Validate the number of threads reading directories during a scan
+    pub fn is_valid_scan_threads(value: usize) -> bool {
+        (1..=MAX_SCAN_THREADS).contains(&value)
+    }
 } 
\ No newline at end of file
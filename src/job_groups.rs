@@ -0,0 +1,343 @@
+//! Grouping of download jobs for the job list
+//!
+//! Jobs are grouped into a Movies node and one node per series, with a child
+//! node per season, using the metadata parsed from the file and folder names.
+//! Each node knows its jobs so the GUI can show counts and retry whole groups.
+//! The tree is flattened into rows of equal height so that only the rows in
+//! view are laid out, even for libraries with tens of thousands of episodes.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::data_structures::{DownloadJob, JobStatus};
+use crate::helper_functions::Utils;
+
+/// Job indexes of a series by season
+type SeasonJobs = BTreeMap<Option<u32>, Vec<usize>>;
+
+/// Job counts of a group by outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JobGroupCounts {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub deferred: usize,
+    pub running: usize,
+    pub pending: usize,
+}
+
+impl JobGroupCounts {
+    fn add(&mut self, status: &JobStatus) {
+        match status {
+            status if status.is_success() => self.succeeded += 1,
+            JobStatus::Failed(_) => self.failed += 1,
+            JobStatus::Deferred(_) => self.deferred += 1,
+            JobStatus::Running => self.running += 1,
+            _ => self.pending += 1,
+        }
+    }
+
+    /// Whether any job in the group can be retried
+    pub fn has_retryable(&self) -> bool {
+        self.failed + self.deferred > 0
+    }
+
+    /// Jobs that haven't finished yet
+    pub fn remaining(&self) -> usize {
+        self.running + self.pending
+    }
+
+    pub fn total(&self) -> usize {
+        self.succeeded + self.failed + self.deferred + self.remaining()
+    }
+}
+
+/// A node of the job list: Movies, a series, or a season of a series
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JobGroup {
+    /// Stable id used to remember whether the node is collapsed
+    pub id: String,
+    pub label: String,
+    /// Indexes into the job list of every job in this node and its children
+    pub job_indexes: Vec<usize>,
+    /// Seasons of a series; empty for Movies and for seasons
+    pub children: Vec<JobGroup>,
+}
+
+impl JobGroup {
+    /// Count the group's jobs by outcome
+    pub fn counts(&self, jobs: &[DownloadJob]) -> JobGroupCounts {
+        let mut counts = JobGroupCounts::default();
+        for job in self.job_indexes.iter().filter_map(|&i| jobs.get(i)) {
+            counts.add(&job.status);
+        }
+        counts
+    }
+}
+
+/// Construction of the job list tree
+pub struct JobGroups;
+
+impl JobGroups {
+    /// Series title of an episode, falling back to the folder name when the file name has none
+    fn series_title(job: &DownloadJob) -> String {
+        if !job.metadata.title.is_empty() {
+            return job.metadata.title.clone();
+        }
+        job.video_path.parent().map(Utils::get_file_name).unwrap_or_else(|| "Unknown".to_string())
+    }
+
+    fn season_label(season: Option<u32>) -> String {
+        match season {
+            Some(0) => "Specials".to_string(),
+            Some(season) => format!("Season {}", season),
+            None => "Episodes".to_string(),
+        }
+    }
+
+    fn file_name(path: &Path) -> String {
+        Utils::get_file_name(path).to_lowercase()
+    }
+
+    /// Group jobs into Movies and series → season nodes, sorted by title and episode
+    pub fn build(jobs: &[DownloadJob]) -> Vec<JobGroup> {
+        let mut movies = Vec::new();
+        // Series keyed by lowercase title so "Show Name" and "show.name" end up together
+        let mut series: BTreeMap<String, (String, SeasonJobs)> = BTreeMap::new();
+        for (index, job) in jobs.iter().enumerate() {
+            if job.metadata.is_episode() {
+                let title = Self::series_title(job);
+                series.entry(title.to_lowercase())
+                    .or_insert_with(|| (title, BTreeMap::new()))
+                    .1
+                    .entry(job.metadata.season)
+                    .or_default()
+                    .push(index);
+            } else {
+                movies.push(index);
+            }
+        }
+
+        let episode_key = |&index: &usize| {
+            let job = &jobs[index];
+            let episode = job.metadata.episodes.first().copied().or(job.metadata.absolute_episode);
+            (episode, Self::file_name(&job.video_path))
+        };
+
+        let mut groups = Vec::new();
+        if !movies.is_empty() {
+            movies.sort_by_cached_key(|&index| Self::file_name(&jobs[index].video_path));
+            groups.push(JobGroup {
+                id: "movies".to_string(),
+                label: "Movies".to_string(),
+                job_indexes: movies,
+                children: Vec::new(),
+            });
+        }
+        for (key, (title, seasons)) in series {
+            let children: Vec<JobGroup> = seasons.into_iter()
+                .map(|(season, mut indexes)| {
+                    indexes.sort_by_cached_key(episode_key);
+                    JobGroup {
+                        id: format!("series/{}/{:?}", key, season),
+                        label: Self::season_label(season),
+                        job_indexes: indexes,
+                        children: Vec::new(),
+                    }
+                })
+                .collect();
+            groups.push(JobGroup {
+                id: format!("series/{}", key),
+                label: title,
+                job_indexes: children.iter().flat_map(|c| c.job_indexes.iter().copied()).collect(),
+                children,
+            });
+        }
+        groups
+    }
+}
+
+/// Extra line shown below a job
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobDetail {
+    /// Index into the job's warnings
+    Warning(usize),
+    /// Index into the job's language outcomes, for machine-generated subtitles
+    MachineGenerated(usize),
+    /// Index into the job's subtitle paths
+    Subtitle(usize),
+}
+
+/// One fixed-height row of the job list
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobListRow {
+    /// Header of a top-level group, or of a season when `season` is set
+    Group { group: usize, season: Option<usize> },
+    Job { index: usize, depth: usize },
+    Detail { index: usize, detail: JobDetail, depth: usize },
+}
+
+/// Grouped job list flattened into rows, with the group counts kept up to date
+#[derive(Default)]
+pub struct JobListView {
+    groups: Vec<JobGroup>,
+    counts: Vec<(JobGroupCounts, Vec<JobGroupCounts>)>,
+    totals: JobGroupCounts,
+    /// Ids of the expanded groups; everything starts collapsed
+    expanded: HashSet<String>,
+    rows: Vec<JobListRow>,
+}
+
+impl JobListView {
+    /// Regroup after the job list was replaced
+    pub fn rebuild(&mut self, jobs: &[DownloadJob]) {
+        self.groups = JobGroups::build(jobs);
+        self.refresh(jobs);
+    }
+
+    /// Recount and re-flatten after jobs changed status
+    pub fn refresh(&mut self, jobs: &[DownloadJob]) {
+        self.counts = self.groups.iter()
+            .map(|group| (group.counts(jobs), group.children.iter().map(|child| child.counts(jobs)).collect()))
+            .collect();
+        self.totals = JobGroupCounts::default();
+        for job in jobs {
+            self.totals.add(&job.status);
+        }
+        self.flatten(jobs);
+    }
+
+    fn push_job_rows(rows: &mut Vec<JobListRow>, job_indexes: &[usize], jobs: &[DownloadJob], depth: usize) {
+        for &index in job_indexes {
+            let Some(job) = jobs.get(index) else {
+                continue;
+            };
+            rows.push(JobListRow::Job { index, depth });
+            let details = (0..job.warnings.len()).map(JobDetail::Warning)
+                .chain(job.language_outcomes.iter().enumerate()
+                    .filter(|(_, outcome)| outcome.origin.machine_generated_note().is_some())
+                    .map(|(i, _)| JobDetail::MachineGenerated(i)))
+                .chain((0..job.subtitle_paths.len()).map(JobDetail::Subtitle));
+            rows.extend(details.map(|detail| JobListRow::Detail { index, detail, depth: depth + 1 }));
+        }
+    }
+
+    fn flatten(&mut self, jobs: &[DownloadJob]) {
+        let mut rows = Vec::new();
+        for (group_index, group) in self.groups.iter().enumerate() {
+            rows.push(JobListRow::Group { group: group_index, season: None });
+            if !self.expanded.contains(&group.id) {
+                continue;
+            }
+            if group.children.is_empty() {
+                Self::push_job_rows(&mut rows, &group.job_indexes, jobs, 1);
+            }
+            for (season_index, season) in group.children.iter().enumerate() {
+                rows.push(JobListRow::Group { group: group_index, season: Some(season_index) });
+                if self.expanded.contains(&season.id) {
+                    Self::push_job_rows(&mut rows, &season.job_indexes, jobs, 2);
+                }
+            }
+        }
+        self.rows = rows;
+    }
+
+    /// Expand or collapse a group
+    pub fn toggle(&mut self, id: &str, jobs: &[DownloadJob]) {
+        if !self.expanded.remove(id) {
+            self.expanded.insert(id.to_string());
+        }
+        self.flatten(jobs);
+    }
+
+    pub fn is_expanded(&self, id: &str) -> bool {
+        self.expanded.contains(id)
+    }
+
+    pub fn rows(&self) -> &[JobListRow] {
+        &self.rows
+    }
+
+    /// The group or season a header row points to, with its counts
+    pub fn group(&self, group: usize, season: Option<usize>) -> Option<(&JobGroup, JobGroupCounts)> {
+        let (node, counts) = (self.groups.get(group)?, self.counts.get(group)?);
+        match season {
+            Some(season) => Some((node.children.get(season)?, *counts.1.get(season)?)),
+            None => Some((node, counts.0)),
+        }
+    }
+
+    /// Counts over the whole job list
+    pub fn totals(&self) -> JobGroupCounts {
+        self.totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn jobs(names: &[&str]) -> Vec<DownloadJob> {
+        names.iter().map(|name| DownloadJob::new(PathBuf::from(name))).collect()
+    }
+
+    fn job_rows(view: &JobListView) -> Vec<usize> {
+        view.rows().iter()
+            .filter_map(|row| match row {
+                JobListRow::Job { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn list_view_expands_groups_and_keeps_counts_current() {
+        let mut jobs = jobs(&["Show.S01E02.mkv", "Movie (2020).mkv", "Show.S01E01.mkv", "Show.S02E01.mkv"]);
+        let mut view = JobListView::default();
+        view.rebuild(&jobs);
+        // Movies and the series, both collapsed
+        assert_eq!(view.rows().len(), 2);
+        assert_eq!(view.totals().pending, 4);
+
+        let (series, counts) = view.group(1, None).unwrap();
+        let series_id = series.id.clone();
+        assert_eq!(counts.total(), 3);
+        view.toggle(&series_id, &jobs);
+        assert_eq!(view.rows().len(), 4);
+        let season_id = view.group(1, Some(0)).unwrap().0.id.clone();
+        view.toggle(&season_id, &jobs);
+        assert_eq!(job_rows(&view), vec![2, 0]);
+
+        jobs[0].status = JobStatus::Failed("No subtitles found".to_string());
+        jobs[2].warnings.push("Subtitle validation failed".to_string());
+        view.refresh(&jobs);
+        let (_, season_counts) = view.group(1, Some(0)).unwrap();
+        assert_eq!((season_counts.failed, season_counts.pending), (1, 1));
+        assert!(season_counts.has_retryable());
+        assert_eq!(view.totals().failed, 1);
+        // The warning adds a detail row under its job
+        assert!(view.rows().contains(&JobListRow::Detail { index: 2, detail: JobDetail::Warning(0), depth: 3 }));
+
+        view.toggle(&series_id, &jobs);
+        assert_eq!(view.rows().len(), 2);
+        assert!(view.is_expanded(&season_id));
+    }
+
+    #[test]
+    fn list_view_skips_stale_job_indexes() {
+        let all = jobs(&["Movie A.mkv", "Movie B.mkv", "Movie C.mkv"]);
+        let mut view = JobListView::default();
+        view.rebuild(&all);
+        view.toggle("movies", &all);
+        assert_eq!(job_rows(&view), vec![0, 1, 2]);
+
+        // Refreshing against a shorter list, before the view is rebuilt, leaves out the missing jobs
+        let shorter = all[..2].to_vec();
+        view.refresh(&shorter);
+        assert_eq!(job_rows(&view), vec![0, 1]);
+        assert_eq!(view.group(0, None).unwrap().1.total(), 2);
+        assert_eq!(view.totals().total(), 2);
+        assert!(view.group(1, None).is_none());
+        assert!(view.group(0, Some(0)).is_none());
+    }
+}
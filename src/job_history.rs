@@ -0,0 +1,311 @@
+//! Persistent history of download attempts
+//!
+//! Every finished job records one entry per language with the time, outcome,
+//! provider and score in the application data directory. Scans use it to
+//! leave out videos whose subtitles weren't found recently, re-checking them
+//! after a day, a week and then a month of consecutive failures.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Local, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{HISTORY_RECHECK_DAYS, MAX_HISTORY_ENTRIES_PER_VIDEO};
+use crate::data_structures::{JobStatus, LanguageOutcome};
+use crate::helper_functions::Utils;
+use crate::retry_policy::FailureKind;
+
+/// Name of the history file in the application data directory
+const HISTORY_FILE_NAME: &str = "job_history.json";
+
+/// Outcome of one attempt for one language
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryOutcome {
+    Found,
+    NotFound,
+    /// A provider quota or rate limit stopped the search
+    Deferred,
+    /// The search itself failed, e.g. a network error
+    Error,
+}
+
+impl HistoryOutcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HistoryOutcome::Found => "Found",
+            HistoryOutcome::NotFound => "Not found",
+            HistoryOutcome::Deferred => "Deferred",
+            HistoryOutcome::Error => "Error",
+        }
+    }
+
+    /// Whether the attempt says anything about the subtitle's availability
+    fn is_conclusive(&self) -> bool {
+        matches!(self, HistoryOutcome::Found | HistoryOutcome::NotFound)
+    }
+}
+
+/// One attempt for one language of a video
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub language: String,
+    pub outcome: HistoryOutcome,
+    pub provider: Option<String>,
+    pub score: Option<f64>,
+    /// Failure message of the job, if any
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Attempt time in the local time zone
+    pub fn local_time(&self) -> Option<DateTime<Local>> {
+        DateTime::from_timestamp(self.timestamp, 0).map(|t| t.with_timezone(&Local))
+    }
+}
+
+#[derive(Default)]
+struct HistoryStore {
+    /// Entries by video path, oldest first
+    videos: BTreeMap<String, Vec<HistoryEntry>>,
+    loaded: bool,
+    dirty: bool,
+}
+
+static HISTORY: Lazy<Mutex<HistoryStore>> = Lazy::new(|| Mutex::new(HistoryStore::default()));
+
+/// Access to the persistent job history
+pub struct JobHistory;
+
+impl JobHistory {
+    fn file_path() -> std::io::Result<PathBuf> {
+        Utils::app_data_path(HISTORY_FILE_NAME)
+    }
+
+    fn ensure_loaded(store: &mut HistoryStore) {
+        if store.loaded {
+            return;
+        }
+        store.loaded = true;
+        let Ok(path) = Self::file_path() else {
+            return;
+        };
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            match serde_json::from_str(&content) {
+                Ok(videos) => store.videos = videos,
+                Err(e) => {
+                    crate::warn!("Ignoring unreadable job history {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    fn key(video_path: &Path) -> String {
+        video_path.to_string_lossy().to_string()
+    }
+
+    /// Record a finished job: one entry per language outcome, or one per
+    /// requested language when the job failed before reaching any language.
+    /// Cancelled jobs and jobs skipped without a search aren't recorded.
+    pub fn record(video_path: &Path, status: &JobStatus, failure: Option<FailureKind>, outcomes: &[LanguageOutcome], languages: &[String]) {
+        let entries = Self::entries_for(Utc::now().timestamp(), status, failure, outcomes, languages);
+        if entries.is_empty() {
+            return;
+        }
+
+        let mut store = HISTORY.lock().unwrap();
+        Self::ensure_loaded(&mut store);
+        let history = store.videos.entry(Self::key(video_path)).or_default();
+        history.extend(entries);
+        let excess = history.len().saturating_sub(MAX_HISTORY_ENTRIES_PER_VIDEO);
+        history.drain(..excess);
+        store.dirty = true;
+    }
+
+    /// Outcome of a language the job didn't find. Only a search the providers
+    /// answered counts as not found; limits defer it and anything else is an error.
+    fn missing_outcome(failure: Option<FailureKind>) -> HistoryOutcome {
+        match failure {
+            Some(FailureKind::ProviderLimit) => HistoryOutcome::Deferred,
+            None | Some(FailureKind::NotFound) => HistoryOutcome::NotFound,
+            Some(FailureKind::Transient) | Some(FailureKind::Permanent) => HistoryOutcome::Error,
+        }
+    }
+
+    /// History entries for a finished job, empty if it isn't recorded
+    fn entries_for(timestamp: i64, status: &JobStatus, failure: Option<FailureKind>, outcomes: &[LanguageOutcome], languages: &[String]) -> Vec<HistoryEntry> {
+        let message = match status {
+            JobStatus::Failed(message) | JobStatus::Deferred(message) => Some(message.clone()),
+            _ => None,
+        };
+        if outcomes.is_empty() && (status.is_success() || message.as_deref() == Some("Cancelled")) {
+            return Vec::new();
+        }
+        let missing_outcome = Self::missing_outcome(failure);
+        if outcomes.is_empty() {
+            // The job stopped before any language was searched
+            languages.iter()
+                .map(|language| HistoryEntry {
+                    timestamp,
+                    language: language.clone(),
+                    outcome: missing_outcome,
+                    provider: None,
+                    score: None,
+                    message: message.clone(),
+                })
+                .collect()
+        } else {
+            outcomes.iter()
+                .map(|outcome| HistoryEntry {
+                    timestamp,
+                    language: outcome.language.clone(),
+                    outcome: if outcome.succeeded() { HistoryOutcome::Found } else { missing_outcome },
+                    provider: outcome.provider.clone(),
+                    score: outcome.score,
+                    message: if outcome.succeeded() { None } else { message.clone() },
+                })
+                .collect()
+        }
+    }
+
+    /// Attempts for a video, newest first
+    pub fn entries(video_path: &Path) -> Vec<HistoryEntry> {
+        let mut store = HISTORY.lock().unwrap();
+        Self::ensure_loaded(&mut store);
+        store.videos.get(&Self::key(video_path))
+            .map(|entries| entries.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget a video's attempts so the next scan includes it again
+    pub fn forget(video_path: &Path) {
+        let mut store = HISTORY.lock().unwrap();
+        Self::ensure_loaded(&mut store);
+        if store.videos.remove(&Self::key(video_path)).is_some() {
+            store.dirty = true;
+        }
+    }
+
+    /// When a language is due to be searched again: the last failure plus an
+    /// interval growing with the number of consecutive failures. None if the
+    /// language was never tried or its last attempt didn't fail.
+    fn next_check(entries: &[HistoryEntry], language: &str) -> Option<DateTime<Utc>> {
+        let attempts: Vec<&HistoryEntry> = entries.iter()
+            .rev()
+            .filter(|e| e.language == language && e.outcome.is_conclusive())
+            .collect();
+        let failures = attempts.iter().take_while(|e| e.outcome == HistoryOutcome::NotFound).count();
+        let last_failure = attempts.first().filter(|_| failures > 0)?;
+        let days = HISTORY_RECHECK_DAYS[(failures - 1).min(HISTORY_RECHECK_DAYS.len() - 1)];
+        DateTime::from_timestamp(last_failure.timestamp, 0).map(|t| t + Duration::days(days))
+    }
+
+    /// If every given language failed recently, when the video is due to be searched again
+    pub fn skip_until(video_path: &Path, languages: &[String]) -> Option<DateTime<Utc>> {
+        if languages.is_empty() {
+            return None;
+        }
+        let mut store = HISTORY.lock().unwrap();
+        Self::ensure_loaded(&mut store);
+        let entries = store.videos.get(&Self::key(video_path))?;
+        let now = Utc::now();
+        languages.iter()
+            .map(|language| Self::next_check(entries, language).filter(|due| *due > now))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
+    /// Write the history to disk if anything changed
+    pub fn save() {
+        let mut store = HISTORY.lock().unwrap();
+        if !store.dirty {
+            return;
+        }
+        let result = Self::file_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| {
+                let json = serde_json::to_string(&store.videos).map_err(|e| e.to_string())?;
+                std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+            });
+        match result {
+            Ok(()) => store.dirty = false,
+            Err(e) => {
+                crate::warn!("Failed to save job history: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::SubtitleOrigin;
+
+    fn outcome(language: &str, found: bool) -> LanguageOutcome {
+        LanguageOutcome {
+            language: language.to_string(),
+            subtitle_path: found.then(|| PathBuf::from(format!("video.{}.srt", language))),
+            origin: SubtitleOrigin::Downloaded,
+            provider: found.then(|| "podnapisi".to_string()),
+            score: None,
+        }
+    }
+
+    fn outcomes_of(entries: &[HistoryEntry]) -> Vec<(&str, HistoryOutcome)> {
+        entries.iter().map(|e| (e.language.as_str(), e.outcome)).collect()
+    }
+
+    #[test]
+    fn only_answered_searches_count_as_not_found() {
+        let languages = vec!["en".to_string()];
+        let cases = [
+            (JobStatus::Success, None, HistoryOutcome::NotFound),
+            (JobStatus::Failed("No subtitles found online".to_string()), Some(FailureKind::NotFound), HistoryOutcome::NotFound),
+            (JobStatus::Failed("HTTP error: connection refused".to_string()), Some(FailureKind::Transient), HistoryOutcome::Error),
+            (JobStatus::Failed("Subliminal error: see log".to_string()), Some(FailureKind::Permanent), HistoryOutcome::Error),
+            (JobStatus::Deferred("quota".to_string()), Some(FailureKind::ProviderLimit), HistoryOutcome::Deferred),
+        ];
+        for (status, failure, expected) in cases {
+            let entries = JobHistory::entries_for(0, &status, failure, &[outcome("de", true), outcome("en", false)], &languages);
+            assert_eq!(outcomes_of(&entries), vec![("de", HistoryOutcome::Found), ("en", expected)], "{:?} {:?}", status, failure);
+        }
+    }
+
+    #[test]
+    fn jobs_stopped_before_any_language_record_requested_languages() {
+        let languages = vec!["en".to_string(), "fr".to_string()];
+        let failed = JobStatus::Failed("Subliminal error: see log".to_string());
+        let entries = JobHistory::entries_for(7, &failed, Some(FailureKind::Permanent), &[], &languages);
+        assert_eq!(outcomes_of(&entries), vec![("en", HistoryOutcome::Error), ("fr", HistoryOutcome::Error)]);
+        assert!(entries.iter().all(|e| e.timestamp == 7 && e.message.as_deref() == Some("Subliminal error: see log")));
+
+        let not_found = JobStatus::Failed("No subtitles found online".to_string());
+        let entries = JobHistory::entries_for(7, &not_found, Some(FailureKind::NotFound), &[], &languages);
+        assert_eq!(outcomes_of(&entries), vec![("en", HistoryOutcome::NotFound), ("fr", HistoryOutcome::NotFound)]);
+
+        let cancelled = JobStatus::Failed("Cancelled".to_string());
+        assert!(JobHistory::entries_for(7, &cancelled, Some(FailureKind::Permanent), &[], &languages).is_empty());
+        assert!(JobHistory::entries_for(7, &JobStatus::Success, None, &[], &languages).is_empty());
+    }
+
+    #[test]
+    fn errors_do_not_delay_the_next_check() {
+        let entry = |outcome| HistoryEntry {
+            timestamp: 0,
+            language: "en".to_string(),
+            outcome,
+            provider: None,
+            score: None,
+            message: None,
+        };
+        assert!(JobHistory::next_check(&[entry(HistoryOutcome::Error)], "en").is_none());
+        let after_not_found = JobHistory::next_check(&[entry(HistoryOutcome::NotFound), entry(HistoryOutcome::Error)], "en");
+        assert_eq!(after_not_found, DateTime::from_timestamp(0, 0).map(|t| t + Duration::days(HISTORY_RECHECK_DAYS[0])));
+    }
+}
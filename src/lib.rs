@@ -9,6 +9,33 @@ pub mod logging;
 pub mod settings;
 pub mod python_manager;
 pub mod subtitle_utils;
+pub mod subtitle_model;
+pub mod subtitle_validation;
+pub mod media_probe;
+pub mod subliminal;
+pub mod sdh_stripper;
+pub mod dual_subtitles;
+pub mod ffmpeg;
+pub mod embedded_subtitles;
+pub mod muxer;
+pub mod bitmap_subtitles;
+pub mod ocr;
+pub mod speech_to_text;
+pub mod translation;
+pub mod audio_language;
+pub mod forced_subtitles;
+pub mod provider_accounts;
+pub mod provider_limits;
+pub mod opensubtitles;
+pub mod subtitle_backend;
+pub mod video_hash;
+pub mod library_index;
+pub mod sidecar_map;
+pub mod filename_parser;
+pub mod job_groups;
+pub mod retry_policy;
+pub mod job_history;
+pub mod download_pipeline;
 pub mod app;
 pub mod gui;
 pub mod helper_functions;
@@ -20,4 +47,31 @@ pub use logging::*;
 pub use settings::*;
 pub use python_manager::*;
 pub use subtitle_utils::*;
+pub use subtitle_model::*;
+pub use subtitle_validation::*;
+pub use media_probe::*;
+pub use subliminal::*;
+pub use sdh_stripper::*;
+pub use dual_subtitles::*;
+pub use ffmpeg::*;
+pub use embedded_subtitles::*;
+pub use muxer::*;
+pub use bitmap_subtitles::*;
+pub use ocr::*;
+pub use speech_to_text::*;
+pub use translation::*;
+pub use audio_language::*;
+pub use forced_subtitles::*;
+pub use provider_accounts::*;
+pub use provider_limits::*;
+pub use opensubtitles::*;
+pub use subtitle_backend::*;
+pub use video_hash::*;
+pub use library_index::*;
+pub use sidecar_map::*;
+pub use filename_parser::*;
+pub use job_groups::*;
+pub use retry_policy::*;
+pub use job_history::*;
+pub use download_pipeline::*;
 pub use helper_functions::*; 
\ No newline at end of file
@@ -0,0 +1,709 @@
+//! Persistent index of the scanned library
+//!
+//! Every directory read during a scan is remembered with its modification
+//! time, its subdirectories and its videos (size, modification time and the
+//! subtitle sidecars next to them). Adding, removing or renaming a file updates
+//! the modification time of its directory, so a re-scan only has to stat each
+//! directory and re-read the ones that changed. Videos of unchanged directories
+//! are still stat'ed, as rewriting a file in place leaves its directory alone.
+//! Sidecars are matched against the remembered names instead of probing the
+//! disk for every language and extension, and a video's original audio
+//! language is kept once probed, for as long as the file is unchanged. A full
+//! rescan ignores the index and reads everything again.
+//!
+//! Directories are walked by several threads, which hides the latency of
+//! network shares. Each directory is identified by device and inode so that
+//! symlink loops are visited once, and directories that can't be read are
+//! reported instead of silently dropped. Symlinked folders are only followed
+//! once every real folder has been walked, so a folder reachable both ways is
+//! always listed under its real path whatever the thread timing.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio_language::AudioLanguageFilter;
+use crate::config::LOCAL_EXTRAS_FOLDERS;
+use crate::helper_functions::Utils;
+use crate::sidecar_map::SidecarMap;
+
+/// Name of the index file in the application cache directory
+const INDEX_FILE_NAME: &str = "library_index.json";
+
+/// Version of the index file format. An index written by another version is
+/// ignored, so that entries missing newer fields are read again.
+const INDEX_VERSION: u32 = 3;
+
+/// Directories modified this recently aren't trusted on the next scan, as a
+/// file added within the same timestamp tick wouldn't change the modification time
+const MODIFIED_SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// A video of an indexed directory
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IndexedVideo {
+    pub name: String,
+    pub size: u64,
+    /// Modification time in seconds since the epoch
+    pub modified: u64,
+    /// File names of the subtitles next to the video (e.g. "video.en.srt")
+    pub sidecars: Vec<String>,
+    /// Original audio language, once a scan has probed it
+    pub audio: Option<ProbedAudio>,
+}
+
+/// Original audio language of a video, valid while its size and modification time match
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProbedAudio {
+    pub size: u64,
+    pub modified: u64,
+    /// None when the video has no tagged audio track
+    pub language: Option<String>,
+}
+
+/// Contents of a directory when it was last read
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IndexedDirectory {
+    /// Modification time in nanoseconds since the epoch, 0 when it must be re-read
+    pub modified: u64,
+    pub subdirectories: Vec<String>,
+    /// Subdirectories that are symbolic links, also listed in `subdirectories`
+    pub symlinked_subdirectories: Vec<String>,
+    pub videos: Vec<IndexedVideo>,
+}
+
+/// A video found by a library walk
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryVideo {
+    pub path: PathBuf,
+    /// File size in bytes
+    pub size: u64,
+    /// Modification time in seconds since the epoch
+    pub modified: u64,
+    pub sidecars: Vec<String>,
+}
+
+impl LibraryVideo {
+    /// Check if the video was modified within the last number of days
+    pub fn modified_within_days(&self, days: u32) -> bool {
+        let modified = UNIX_EPOCH + Duration::from_secs(self.modified);
+        let max_age = Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        modified.elapsed().ok().is_none_or(|age| age <= max_age)
+    }
+
+    /// File stem used to match sidecars (e.g. "video" for video.mkv)
+    pub fn stem(&self) -> String {
+        self.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+    }
+}
+
+/// How a library folder is walked
+#[derive(Clone, Debug)]
+pub struct WalkOptions {
+    /// Skip Plex local extras folders
+    pub ignore_extras: bool,
+    /// Read every directory instead of reusing unchanged ones from the index
+    pub full_rescan: bool,
+    /// Directories read at the same time
+    pub threads: usize,
+    /// Walk into folders behind symbolic links
+    pub follow_symlinks: bool,
+}
+
+/// Result of walking a library folder
+#[derive(Debug, Default)]
+pub struct LibraryScan {
+    pub videos: Vec<LibraryVideo>,
+    pub ignored_extra_folders: usize,
+    /// Directories that couldn't be read, with the error
+    pub unreadable_paths: Vec<(PathBuf, String)>,
+    /// Directories listed from disk because they were new or changed
+    pub directories_read: usize,
+    /// Directories taken from the index unchanged
+    pub directories_reused: usize,
+}
+
+/// Contents of the index file
+#[derive(Serialize, Deserialize, Default)]
+struct IndexFile<'a> {
+    /// Missing in indexes written before the format was versioned
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    directories: Cow<'a, HashMap<String, IndexedDirectory>>,
+}
+
+/// Identity of a directory, the same for every path that leads to it
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum DirectoryId {
+    /// Device and inode number
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Inode(u64, u64),
+    /// Canonical path, where inode numbers aren't available
+    #[cfg_attr(unix, allow(dead_code))]
+    Path(PathBuf),
+}
+
+impl DirectoryId {
+    #[cfg(unix)]
+    fn of(_dir: &Path, metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Self::Inode(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn of(dir: &Path, _metadata: &std::fs::Metadata) -> Option<Self> {
+        std::fs::canonicalize(dir).ok().map(Self::Path)
+    }
+}
+
+/// What a walker thread found in one directory
+struct VisitedDirectory {
+    key: String,
+    /// Whether the directory was listed from disk instead of taken from the index
+    listed: bool,
+    /// The entry to save in the index, None when the indexed one is still current
+    updated: Option<IndexedDirectory>,
+    subdirectories: Vec<PathBuf>,
+    /// Symlinked subdirectories to follow once the real ones are walked
+    symlinked_subdirectories: Vec<PathBuf>,
+    ignored_extra_folders: usize,
+    videos: Vec<LibraryVideo>,
+}
+
+/// Work shared by the walker threads
+#[derive(Default)]
+struct WalkQueue {
+    /// Directories to read, with whether they were already claimed in `seen`
+    pending: VecDeque<(PathBuf, bool)>,
+    /// Symlinked folders found so far, followed when `pending` runs dry
+    links: Vec<PathBuf>,
+    /// Directories being read by a thread
+    active: usize,
+    seen: HashSet<DirectoryId>,
+    visited: Vec<VisitedDirectory>,
+    unreadable: Vec<(PathBuf, String)>,
+}
+
+/// Directory index kept between scans
+#[derive(Default)]
+pub struct LibraryIndex {
+    directories: HashMap<String, IndexedDirectory>,
+    dirty: bool,
+}
+
+impl LibraryIndex {
+    fn index_path() -> std::io::Result<PathBuf> {
+        Utils::app_cache_path(INDEX_FILE_NAME)
+    }
+
+    /// Load the index from disk, starting empty when there is none
+    pub fn load() -> Self {
+        let mut index = Self::default();
+        let Ok(path) = Self::index_path() else {
+            return index;
+        };
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            match serde_json::from_str::<IndexFile>(&content) {
+                Ok(file) if file.version == INDEX_VERSION => index.directories = file.directories.into_owned(),
+                Ok(file) => {
+                    crate::info!("Ignoring library index of version {}, every folder will be read again", file.version);
+                }
+                Err(e) => {
+                    crate::warn!("Ignoring unreadable library index {}: {}", path.display(), e);
+                }
+            }
+        }
+        index
+    }
+
+    /// Write the index to disk if a directory was read or removed
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let result = Self::index_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| {
+                let file = IndexFile { version: INDEX_VERSION, directories: Cow::Borrowed(&self.directories) };
+                let json = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+                std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+            });
+        match result {
+            Ok(()) => self.dirty = false,
+            Err(e) => {
+                crate::warn!("Failed to save library index: {}", e);
+            }
+        }
+    }
+
+    fn modified_nanos(time: SystemTime) -> Option<u64> {
+        time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_nanos() as u64)
+    }
+
+    fn modified_secs(metadata: &std::fs::Metadata) -> u64 {
+        metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// Refresh the size and modification time of the videos of an indexed
+    /// directory. Returns whether any of them changed.
+    fn refresh_videos(dir: &Path, entry: &mut IndexedDirectory) -> bool {
+        let mut changed = false;
+        for video in &mut entry.videos {
+            let Ok(metadata) = std::fs::metadata(dir.join(&video.name)) else {
+                continue;
+            };
+            let (size, modified) = (metadata.len(), Self::modified_secs(&metadata));
+            if video.size != size || video.modified != modified {
+                video.size = size;
+                video.modified = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// List a directory from disk
+    pub fn read_directory(dir: &Path, modified: Option<SystemTime>) -> std::io::Result<IndexedDirectory> {
+        let mut subdirectories = Vec::new();
+        let mut symlinked_subdirectories = Vec::new();
+        let mut videos = Vec::new();
+        let mut subtitles = Vec::new();
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            let metadata = match entry.file_type() {
+                // Symlinked files are used like regular ones, symlinked folders are
+                // remembered so the walk can follow them or not
+                Ok(file_type) if file_type.is_symlink() => std::fs::metadata(&path).inspect(|metadata| {
+                    if metadata.is_dir() {
+                        symlinked_subdirectories.push(name.clone());
+                    }
+                }),
+                Ok(file_type) if file_type.is_dir() => {
+                    subdirectories.push(name);
+                    continue;
+                }
+                Ok(_) if Utils::is_video_file(&path) => entry.metadata(),
+                Ok(_) => {
+                    if SidecarMap::is_subtitle_name(&name) {
+                        subtitles.push(name);
+                    }
+                    continue;
+                }
+                Err(e) => Err(e),
+            };
+            let Ok(metadata) = metadata else {
+                continue;
+            };
+            if metadata.is_dir() {
+                subdirectories.push(name);
+            } else if Utils::is_video_file(&path) {
+                videos.push(IndexedVideo { name, size: metadata.len(), modified: Self::modified_secs(&metadata), sidecars: Vec::new(), audio: None });
+            } else if SidecarMap::is_subtitle_name(&name) {
+                subtitles.push(name);
+            }
+        }
+
+        let sidecars = SidecarMap::from_names(dir, subtitles);
+        for video in &mut videos {
+            let stem = Path::new(&video.name).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            video.sidecars = sidecars.sidecars(&stem).to_vec();
+        }
+
+        // A directory changed moments ago may change again within the same tick
+        let settled = modified.filter(|time| time.elapsed().is_ok_and(|age| age >= MODIFIED_SETTLE_TIME));
+        Ok(IndexedDirectory {
+            modified: settled.and_then(Self::modified_nanos).unwrap_or(0),
+            subdirectories,
+            symlinked_subdirectories,
+            videos,
+        })
+    }
+
+    /// Take a directory from the index if its modification time is unchanged, or read it
+    fn visit_directory(
+        directories: &Mutex<HashMap<String, IndexedDirectory>>,
+        dir: &Path,
+        modified: Option<SystemTime>,
+        options: &WalkOptions,
+    ) -> std::io::Result<VisitedDirectory> {
+        let key = dir.to_string_lossy().to_string();
+        let nanos = modified.and_then(Self::modified_nanos);
+        let previous = (!options.full_rescan).then(|| directories.lock().unwrap().get(&key).cloned()).flatten();
+        let (entry, listed, updated) = match previous {
+            Some(mut entry) if entry.modified != 0 && Some(entry.modified) == nanos => {
+                let updated = Self::refresh_videos(dir, &mut entry).then(|| entry.clone());
+                (entry, false, updated)
+            }
+            previous => {
+                let mut entry = Self::read_directory(dir, modified)?;
+                // Probed audio languages stay valid for videos that didn't change
+                for video in &mut entry.videos {
+                    video.audio = previous.as_ref()
+                        .and_then(|old| old.videos.iter().find(|v| v.name == video.name))
+                        .and_then(|old| old.audio.clone())
+                        .filter(|audio| audio.size == video.size && audio.modified == video.modified);
+                }
+                (entry.clone(), true, Some(entry))
+            }
+        };
+
+        let mut subdirectories = Vec::new();
+        let mut symlinked_subdirectories = Vec::new();
+        let mut ignored_extra_folders = 0;
+        for name in &entry.subdirectories {
+            let path = dir.join(name);
+            if options.ignore_extras && LOCAL_EXTRAS_FOLDERS.contains(&name.as_str()) {
+                crate::info!("Ignoring local extras folder: {}", path.display());
+                ignored_extra_folders += 1;
+            } else if !entry.symlinked_subdirectories.contains(name) {
+                subdirectories.push(path);
+            } else if options.follow_symlinks {
+                symlinked_subdirectories.push(path);
+            } else {
+                crate::debug!("Not following symlinked folder: {}", path.display());
+            }
+        }
+        let videos = entry.videos.into_iter()
+            .map(|video| LibraryVideo { path: dir.join(&video.name), size: video.size, modified: video.modified, sidecars: video.sidecars })
+            .collect();
+        Ok(VisitedDirectory { key, listed, updated, subdirectories, symlinked_subdirectories, ignored_extra_folders, videos })
+    }
+
+    /// Queue the symlinked folders found so far, in path order, skipping those
+    /// whose target was already walked or is claimed by an earlier link
+    fn claim_links(state: &mut WalkQueue) {
+        let mut links = std::mem::take(&mut state.links);
+        links.sort();
+        for link in links {
+            let id = match std::fs::metadata(&link) {
+                Ok(metadata) => DirectoryId::of(&link, &metadata),
+                Err(e) => {
+                    crate::warn!("Failed to read directory {}: {}", link.display(), e);
+                    state.unreadable.push((link, e.to_string()));
+                    continue;
+                }
+            };
+            if id.is_some_and(|id| !state.seen.insert(id)) {
+                crate::debug!("Skipping {}: folder already visited through another path", link.display());
+            } else {
+                state.pending.push_back((link, true));
+            }
+        }
+    }
+
+    /// Take directories from the queue until every directory has been visited
+    fn walk_worker(queue: &Mutex<WalkQueue>, ready: &Condvar, directories: &Mutex<HashMap<String, IndexedDirectory>>, options: &WalkOptions) {
+        loop {
+            let (dir, claimed) = {
+                let mut state = queue.lock().unwrap();
+                loop {
+                    if let Some(next) = state.pending.pop_front() {
+                        state.active += 1;
+                        break next;
+                    }
+                    if state.active == 0 {
+                        if state.links.is_empty() {
+                            ready.notify_all();
+                            return;
+                        }
+                        // Every folder reachable without links is walked, follow the links
+                        Self::claim_links(&mut state);
+                        ready.notify_all();
+                        continue;
+                    }
+                    state = ready.wait(state).unwrap();
+                }
+            };
+
+            let identity = std::fs::metadata(&dir)
+                .map(|metadata| (DirectoryId::of(&dir, &metadata), metadata.modified().ok()));
+            let (id, modified) = match identity {
+                Ok(found) => found,
+                Err(e) => {
+                    crate::warn!("Failed to read directory {}: {}", dir.display(), e);
+                    let mut state = queue.lock().unwrap();
+                    state.unreadable.push((dir, e.to_string()));
+                    state.active -= 1;
+                    ready.notify_all();
+                    continue;
+                }
+            };
+            // A folder reached again through a symlink is only walked once
+            if let (Some(id), false) = (id, claimed) {
+                let mut state = queue.lock().unwrap();
+                if !state.seen.insert(id) {
+                    crate::debug!("Skipping {}: folder already visited through another path", dir.display());
+                    state.active -= 1;
+                    ready.notify_all();
+                    continue;
+                }
+            }
+
+            let result = Self::visit_directory(directories, &dir, modified, options);
+            let mut state = queue.lock().unwrap();
+            match result {
+                Ok(visited) => {
+                    state.pending.extend(visited.subdirectories.iter().map(|path| (path.clone(), false)));
+                    state.links.extend(visited.symlinked_subdirectories.iter().cloned());
+                    state.visited.push(visited);
+                }
+                Err(e) => {
+                    crate::warn!("Failed to read directory {}: {}", dir.display(), e);
+                    state.unreadable.push((dir, e.to_string()));
+                }
+            }
+            state.active -= 1;
+            ready.notify_all();
+        }
+    }
+
+    /// Original audio language of a walked video, probed with ffprobe only when
+    /// the index has none for the video's current size and modification time
+    pub fn audio_language(&mut self, video: &LibraryVideo) -> Option<String> {
+        let key = video.path.parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default();
+        let name = video.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let indexed = self.directories.get_mut(&key)
+            .and_then(|entry| entry.videos.iter_mut().find(|v| v.name == name));
+        if let Some(audio) = indexed.as_ref().and_then(|v| v.audio.as_ref()) {
+            if audio.size == video.size && audio.modified == video.modified {
+                return audio.language.clone();
+            }
+        }
+        let language = AudioLanguageFilter::original_audio_language(&video.path);
+        crate::debug!("Original audio language of {}: {:?}", video.path.display(), language);
+        if let Some(indexed) = indexed {
+            indexed.audio = Some(ProbedAudio { size: video.size, modified: video.modified, language: language.clone() });
+            self.dirty = true;
+        }
+        language
+    }
+
+    /// Walk a library folder, re-reading only directories that changed since the last
+    /// walk (or all of them for a full rescan). Entries of directories under the root
+    /// that no longer exist are dropped from the index.
+    pub fn walk(&mut self, root: &Path, options: &WalkOptions) -> LibraryScan {
+        let queue = Mutex::new(WalkQueue { pending: VecDeque::from([(root.to_path_buf(), false)]), ..WalkQueue::default() });
+        let ready = Condvar::new();
+        let directories = Mutex::new(std::mem::take(&mut self.directories));
+        std::thread::scope(|scope| {
+            for _ in 0..options.threads.max(1) {
+                scope.spawn(|| Self::walk_worker(&queue, &ready, &directories, options));
+            }
+        });
+        self.directories = directories.into_inner().unwrap();
+        let walked = queue.into_inner().unwrap();
+
+        let mut scan = LibraryScan { unreadable_paths: walked.unreadable, ..LibraryScan::default() };
+        let mut visited = HashSet::new();
+        for directory in walked.visited {
+            scan.ignored_extra_folders += directory.ignored_extra_folders;
+            scan.videos.extend(directory.videos);
+            if directory.listed {
+                scan.directories_read += 1;
+            } else {
+                scan.directories_reused += 1;
+            }
+            if let Some(entry) = directory.updated {
+                self.directories.insert(directory.key.clone(), entry);
+                self.dirty = true;
+            }
+            visited.insert(directory.key);
+        }
+        // Threads finish in any order, so sort for stable results
+        scan.videos.sort_by(|a, b| a.path.cmp(&b.path));
+        scan.unreadable_paths.sort();
+
+        let before = self.directories.len();
+        self.directories.retain(|key, _| visited.contains(key) || !Path::new(key).starts_with(root));
+        if self.directories.len() != before {
+            self.dirty = true;
+        }
+
+        crate::info!("Library walk of {} with {} threads: {} directories read, {} unchanged, {} unreadable, {} videos",
+            root.display(), options.threads.max(1), scan.directories_read, scan.directories_reused,
+            scan.unreadable_paths.len(), scan.videos.len());
+        scan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("rustitles-index-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    /// Backdate a directory to a fixed time so the next walk trusts its modification time
+    fn settle(dir: &Path) {
+        let backdated = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        std::fs::File::open(dir).unwrap().set_modified(backdated).unwrap();
+    }
+
+    fn options() -> WalkOptions {
+        WalkOptions { ignore_extras: true, full_rescan: false, threads: 4, follow_symlinks: false }
+    }
+
+    #[test]
+    fn rescans_refresh_videos_rewritten_in_unchanged_directories() {
+        let root = temp_folder("rewritten");
+        let video = root.join("Movie.mkv");
+        std::fs::write(&video, [0u8; 100]).unwrap();
+        std::fs::write(root.join("Movie.en.srt"), "1\n").unwrap();
+        settle(&root);
+
+        let mut index = LibraryIndex::default();
+        let first = index.walk(&root, &options());
+        assert_eq!(first.directories_read, 1);
+        assert_eq!(first.videos.len(), 1);
+        assert_eq!(first.videos[0].size, 100);
+        assert_eq!(first.videos[0].sidecars, vec!["Movie.en.srt".to_string()]);
+
+        // Rewriting the file in place leaves the directory's modification time alone
+        std::fs::write(&video, [0u8; 250]).unwrap();
+        let last_week = SystemTime::now() - Duration::from_secs(7 * 24 * 3600);
+        std::fs::File::options().write(true).open(&video).unwrap().set_modified(last_week).unwrap();
+        settle(&root);
+
+        let second = index.walk(&root, &options());
+        assert_eq!((second.directories_read, second.directories_reused), (0, 1));
+        assert_eq!(second.videos[0].size, 250);
+        assert_eq!(second.videos[0].modified, last_week.duration_since(UNIX_EPOCH).unwrap().as_secs());
+        assert_eq!(second.videos[0].sidecars, vec!["Movie.en.srt".to_string()]);
+        // The refreshed entry is kept for the next walk
+        assert_eq!(index.directories[&root.to_string_lossy().to_string()].videos[0].size, 250);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keeps_probed_audio_until_the_video_changes() {
+        let root = temp_folder("audio");
+        let video = root.join("Movie.mkv");
+        std::fs::write(&video, [0u8; 100]).unwrap();
+        let key = root.to_string_lossy().to_string();
+
+        let mut index = LibraryIndex::default();
+        let scan = index.walk(&root, &options());
+        let probed = ProbedAudio { size: scan.videos[0].size, modified: scan.videos[0].modified, language: Some("jpn".to_string()) };
+        index.directories.get_mut(&key).unwrap().videos[0].audio = Some(probed.clone());
+        assert_eq!(index.audio_language(&scan.videos[0]), Some("jpn".to_string()));
+
+        // A new sidecar makes the walk re-read the directory, but the video is unchanged
+        std::fs::write(root.join("Movie.en.srt"), "1\n").unwrap();
+        let scan = index.walk(&root, &options());
+        assert_eq!(scan.directories_read, 1);
+        assert_eq!(index.directories[&key].videos[0].audio, Some(probed));
+        assert_eq!(index.audio_language(&scan.videos[0]), Some("jpn".to_string()));
+
+        // A rewritten video is probed again, and an empty file has no audio
+        std::fs::write(&video, [0u8; 250]).unwrap();
+        std::fs::write(root.join("Movie.de.srt"), "1\n").unwrap();
+        let scan = index.walk(&root, &options());
+        assert_eq!(index.directories[&key].videos[0].audio, None);
+        assert_eq!(index.audio_language(&scan.videos[0]), None);
+        assert_eq!(index.directories[&key].videos[0].audio.as_ref().map(|audio| audio.size), Some(250));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn video_paths(scan: &LibraryScan) -> Vec<PathBuf> {
+        scan.videos.iter().map(|video| video.path.clone()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_links_once_and_lists_folders_under_their_real_path() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_folder("links");
+        let external = temp_folder("links-external");
+        let season = root.join("Show").join("Season 1");
+        std::fs::create_dir_all(&season).unwrap();
+        std::fs::write(season.join("Show.S01E01.mkv"), "").unwrap();
+        std::fs::write(external.join("Extra.mkv"), "").unwrap();
+        // A loop back to the root, a second path to a real folder and a folder outside the root
+        symlink(&root, season.join("back")).unwrap();
+        symlink(root.join("Show"), root.join("Alias")).unwrap();
+        symlink(&external, root.join("External")).unwrap();
+
+        let followed = vec![root.join("External").join("Extra.mkv"), season.join("Show.S01E01.mkv")];
+        for _ in 0..20 {
+            let options = WalkOptions { full_rescan: true, threads: 8, follow_symlinks: true, ..options() };
+            let scan = LibraryIndex::default().walk(&root, &options);
+            assert_eq!(video_paths(&scan), followed);
+            assert!(scan.unreadable_paths.is_empty());
+        }
+
+        let mut index = LibraryIndex::default();
+        let scan = index.walk(&root, &options());
+        assert_eq!(video_paths(&scan), vec![season.join("Show.S01E01.mkv")]);
+        let entry = &index.directories[&root.to_string_lossy().to_string()];
+        assert_eq!(entry.symlinked_subdirectories.len(), 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&external);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_folders_that_cannot_be_read() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = temp_folder("unreadable");
+        for name in ["Open", "Gone"] {
+            std::fs::create_dir(root.join(name)).unwrap();
+            std::fs::write(root.join(name).join(format!("{}.mkv", name)), "").unwrap();
+        }
+        settle(&root);
+        let mut index = LibraryIndex::default();
+        assert_eq!(index.walk(&root, &options()).videos.len(), 2);
+
+        // The indexed root still lists a folder removed behind its back
+        std::fs::remove_dir_all(root.join("Gone")).unwrap();
+        settle(&root);
+        let scan = index.walk(&root, &options());
+        assert_eq!(video_paths(&scan), vec![root.join("Open").join("Open.mkv")]);
+        assert_eq!(scan.unreadable_paths.len(), 1);
+        assert_eq!(scan.unreadable_paths[0].0, root.join("Gone"));
+
+        let locked = root.join("Locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::write(locked.join("Locked.mkv"), "").unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't stop root, which can list the folder anyway
+        if std::fs::read_dir(&locked).is_err() {
+            let scan = LibraryIndex::default().walk(&root, &options());
+            assert_eq!(video_paths(&scan), vec![root.join("Open").join("Open.mkv")]);
+            assert_eq!(scan.unreadable_paths.len(), 1);
+            assert_eq!(scan.unreadable_paths[0].0, locked);
+        }
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn ignores_indexes_of_another_version() {
+        let directories = HashMap::from([("/videos".to_string(), IndexedDirectory::default())]);
+        let current = IndexFile { version: INDEX_VERSION, directories: Cow::Borrowed(&directories) };
+        let parsed: IndexFile = serde_json::from_str(&serde_json::to_string(&current).unwrap()).unwrap();
+        assert_eq!((parsed.version, parsed.directories.len()), (INDEX_VERSION, 1));
+
+        // Before versioning the file was the bare directory map
+        let unversioned = serde_json::to_string(&directories).unwrap();
+        let parsed: IndexFile = serde_json::from_str(&unversioned).unwrap();
+        assert_ne!(parsed.version, INDEX_VERSION);
+    }
+}
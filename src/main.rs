@@ -10,6 +10,33 @@ mod logging;
 mod settings;
 mod python_manager;
 mod subtitle_utils;
+mod subtitle_model;
+mod subtitle_validation;
+mod media_probe;
+mod subliminal;
+mod sdh_stripper;
+mod dual_subtitles;
+mod ffmpeg;
+mod embedded_subtitles;
+mod muxer;
+mod bitmap_subtitles;
+mod ocr;
+mod speech_to_text;
+mod translation;
+mod audio_language;
+mod forced_subtitles;
+mod provider_accounts;
+mod provider_limits;
+mod opensubtitles;
+mod subtitle_backend;
+mod video_hash;
+mod library_index;
+mod sidecar_map;
+mod filename_parser;
+mod job_groups;
+mod retry_policy;
+mod job_history;
+mod download_pipeline;
 mod app;
 mod gui;
 mod helper_functions;
@@ -21,11 +48,35 @@ pub use logging::*;
 pub use settings::*;
 pub use python_manager::*;
 pub use subtitle_utils::*;
+pub use subtitle_model::*;
+pub use subtitle_validation::*;
+pub use media_probe::*;
+pub use subliminal::*;
+pub use sdh_stripper::*;
+pub use dual_subtitles::*;
+pub use ffmpeg::*;
+pub use embedded_subtitles::*;
+pub use muxer::*;
+pub use bitmap_subtitles::*;
+pub use ocr::*;
+pub use speech_to_text::*;
+pub use translation::*;
+pub use audio_language::*;
+pub use forced_subtitles::*;
+pub use provider_accounts::*;
+pub use provider_limits::*;
+pub use opensubtitles::*;
+pub use subtitle_backend::*;
+pub use video_hash::*;
+pub use library_index::*;
+pub use sidecar_map::*;
+pub use filename_parser::*;
+pub use job_groups::*;
+pub use retry_policy::*;
+pub use job_history::*;
+pub use download_pipeline::*;
 pub use helper_functions::*;
 
-// Only keep actually used imports
-use image;
-use serde_json;
 use crate::logging::LOGGER;
 
 // Third-party crate imports
@@ -56,7 +107,7 @@ fn load_app_icon() -> Option<egui::IconData> {
     {
         if let Ok(image) = image::load_from_memory(include_bytes!("../resources/rustitles_icon.ico")) {
             let rgba = image.to_rgba8();
-            let size = [rgba.width() as u32, rgba.height() as u32];
+            let size = [rgba.width(), rgba.height()];
             Some(egui::IconData {
                 rgba: rgba.into_raw(),
                 width: size[0],
@@ -73,7 +124,7 @@ fn load_app_icon() -> Option<egui::IconData> {
         // Try PNG first on Linux/macOS, then fallback to ICO
         if let Ok(image) = image::load_from_memory(include_bytes!("../resources/rustitles_icon.png")) {
             let rgba = image.to_rgba8();
-            let size = [rgba.width() as u32, rgba.height() as u32];
+            let size = [rgba.width(), rgba.height()];
             Some(egui::IconData {
                 rgba: rgba.into_raw(),
                 width: size[0],
@@ -81,7 +132,7 @@ fn load_app_icon() -> Option<egui::IconData> {
             })
         } else if let Ok(image) = image::load_from_memory(include_bytes!("../resources/rustitles_icon.ico")) {
             let rgba = image.to_rgba8();
-            let size = [rgba.width() as u32, rgba.height() as u32];
+            let size = [rgba.width(), rgba.height()];
             Some(egui::IconData {
                 rgba: rgba.into_raw(),
                 width: size[0],
@@ -95,6 +146,7 @@ fn load_app_icon() -> Option<egui::IconData> {
 }
 
 /// Calculate window position to center on the currently used monitor
+#[cfg_attr(not(windows), allow(unused_variables))]
 fn calculate_window_position(window_size: [f32; 2]) -> egui::Pos2 {
     #[cfg(windows)]
     {
@@ -183,25 +235,6 @@ fn cleanup_on_exit() {
     }
 }
 
-// =============================================================================
-// ERROR HANDLING
-// =============================================================================
-
-/// Custom error type for application-specific errors
-#[derive(Debug, thiserror::Error)]
-enum AppError {
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-    
-    #[error("JSON serialization error: {0}")]
-    Json(#[from] serde_json::Error),
-    
-    #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
-    
-
-}
-
 // =============================================================================
 // VERSION CONSTANT & VERSION CHECK STATE
 // =============================================================================
@@ -0,0 +1,182 @@
+//! Media container inspection using ffprobe
+//!
+//! This module wraps ffprobe calls used to inspect video files, such as
+//! reading the container duration and listing subtitle and audio streams.
+
+use std::path::Path;
+use std::process::Output;
+
+use crate::helper_functions::Utils;
+
+/// Codecs of text-based subtitle streams that can be converted to SRT
+const TEXT_SUBTITLE_CODECS: &[&str] = &["subrip", "srt", "ass", "ssa", "webvtt", "mov_text", "text"];
+
+/// Codecs of bitmap subtitle streams that need OCR
+const BITMAP_SUBTITLE_CODECS: &[&str] = &["hdmv_pgs_subtitle", "dvd_subtitle"];
+
+/// A single stream in a media container
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaStream {
+    /// Absolute stream index within the container
+    pub index: u32,
+    pub codec_type: String,
+    pub codec_name: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub forced: bool,
+    pub default: bool,
+}
+
+impl MediaStream {
+    /// Whether this is a text subtitle stream that can be extracted to SRT
+    pub fn is_text_subtitle(&self) -> bool {
+        self.codec_type == "subtitle" && TEXT_SUBTITLE_CODECS.contains(&self.codec_name.as_str())
+    }
+
+    /// Whether this is a bitmap subtitle stream (PGS or VobSub)
+    pub fn is_bitmap_subtitle(&self) -> bool {
+        self.codec_type == "subtitle" && BITMAP_SUBTITLE_CODECS.contains(&self.codec_name.as_str())
+    }
+
+    /// Whether this stream is flagged as forced (foreign parts only)
+    pub fn is_forced(&self) -> bool {
+        self.forced || self.title.as_deref().is_some_and(|t| t.to_lowercase().contains("forced"))
+    }
+}
+
+/// A demuxed packet of a single stream
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaPacket {
+    pub pts_secs: f64,
+    pub duration_secs: Option<f64>,
+    pub data: Vec<u8>,
+}
+
+/// Utilities for inspecting video containers
+pub struct MediaProbe;
+
+impl MediaProbe {
+    /// Run ffprobe with the given arguments, hiding the console window on Windows
+    pub fn run_ffprobe(args: &[&str], video_path: &Path) -> Option<Output> {
+        let mut cmd = Utils::hidden_command("ffprobe");
+        cmd.arg("-v").arg("error").args(args).arg(video_path);
+        match cmd.output() {
+            Ok(output) if output.status.success() => Some(output),
+            Ok(output) => {
+                crate::debug!("ffprobe failed for {}: {}", video_path.display(), String::from_utf8_lossy(&output.stderr).trim());
+                None
+            }
+            Err(e) => {
+                crate::debug!("Failed to run ffprobe for {}: {}", video_path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Get the container duration in seconds
+    pub fn duration_secs(video_path: &Path) -> Option<f64> {
+        let output = Self::run_ffprobe(
+            &["-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"],
+            video_path,
+        )?;
+        String::from_utf8_lossy(&output.stdout).trim().parse::<f64>().ok()
+    }
+
+    /// List the streams matching an ffprobe stream specifier ("s" for subtitles, "a" for audio)
+    pub fn streams(video_path: &Path, selector: &str) -> Vec<MediaStream> {
+        let Some(json) = Self::probe_json(
+            &[
+                "-select_streams", selector,
+                "-show_entries", "stream=index,codec_type,codec_name:stream_tags=language,title:stream_disposition=default,forced",
+                "-of", "json",
+            ],
+            video_path,
+        ) else {
+            return Vec::new();
+        };
+        let Some(streams) = json.get("streams").and_then(|s| s.as_array()) else {
+            return Vec::new();
+        };
+        streams.iter()
+            .map(|stream| {
+                let text = |v: Option<&serde_json::Value>| v.and_then(|v| v.as_str()).map(|s| s.to_string());
+                let flag = |name: &str| stream.get("disposition")
+                    .and_then(|d| d.get(name))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0) == 1;
+                let tags = stream.get("tags");
+                MediaStream {
+                    index: stream.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+                    codec_type: text(stream.get("codec_type")).unwrap_or_default(),
+                    codec_name: text(stream.get("codec_name")).unwrap_or_default(),
+                    language: text(tags.and_then(|t| t.get("language"))).map(|l| l.to_lowercase()),
+                    title: text(tags.and_then(|t| t.get("title"))),
+                    forced: flag("forced"),
+                    default: flag("default"),
+                }
+            })
+            .collect()
+    }
+
+    /// Run ffprobe with JSON output and parse it
+    fn probe_json(args: &[&str], video_path: &Path) -> Option<serde_json::Value> {
+        let output = Self::run_ffprobe(args, video_path)?;
+        match serde_json::from_slice(&output.stdout) {
+            Ok(json) => Some(json),
+            Err(e) => {
+                crate::warn!("Failed to parse ffprobe output for {}: {}", video_path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Decode the hex dump ffprobe prints for -show_data ("00000000: 1600 0013 ...  ascii")
+    fn parse_hex_dump(dump: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for line in dump.lines() {
+            let Some((_, rest)) = line.split_once(": ") else { continue };
+            // The hex columns are padded to a fixed width before the ASCII column
+            let hex: Vec<u8> = rest.bytes().take(40).filter(|b| b.is_ascii_hexdigit()).collect();
+            for pair in hex.chunks_exact(2) {
+                if let Ok(byte) = u8::from_str_radix(std::str::from_utf8(pair).unwrap_or(""), 16) {
+                    bytes.push(byte);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Read every packet of a stream together with its payload
+    pub fn packets(video_path: &Path, stream_index: u32) -> Vec<MediaPacket> {
+        let selector = stream_index.to_string();
+        let Some(json) = Self::probe_json(
+            &["-select_streams", &selector, "-show_entries", "packet=pts_time,duration_time,data", "-show_data", "-of", "json"],
+            video_path,
+        ) else {
+            return Vec::new();
+        };
+        let Some(packets) = json.get("packets").and_then(|p| p.as_array()) else {
+            return Vec::new();
+        };
+        let number = |v: Option<&serde_json::Value>| v.and_then(|v| v.as_str()).and_then(|s| s.parse::<f64>().ok());
+        packets.iter()
+            .filter_map(|packet| Some(MediaPacket {
+                pts_secs: number(packet.get("pts_time"))?,
+                duration_secs: number(packet.get("duration_time")),
+                data: Self::parse_hex_dump(packet.get("data")?.as_str()?),
+            }))
+            .collect()
+    }
+
+    /// Read a stream's codec extradata (for VobSub this is the .idx header with the palette)
+    pub fn extradata(video_path: &Path, stream_index: u32) -> Option<Vec<u8>> {
+        let selector = stream_index.to_string();
+        let json = Self::probe_json(
+            &["-select_streams", &selector, "-show_entries", "stream=extradata", "-show_data", "-of", "json"],
+            video_path,
+        )?;
+        let dump = json.get("streams")?.as_array()?.first()?.get("extradata")?.as_str()?;
+        let data = Self::parse_hex_dump(dump);
+        if data.is_empty() { None } else { Some(data) }
+    }
+}
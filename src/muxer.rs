@@ -0,0 +1,241 @@
+//! Muxing of sidecar subtitles into MKV containers
+//!
+//! This module remuxes a video together with its sidecar subtitles using
+//! ffmpeg. The result is written to a temporary file next to the video and
+//! verified, then moved over the original with a single rename so the video
+//! path always points at a complete file. The original is hard-linked (or
+//! copied) to a backup until the rename has succeeded. The temporary file has
+//! no video extension, so scans never pick up a leftover one.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::Ffmpeg;
+use crate::media_probe::{MediaProbe, MediaStream};
+use crate::subtitle_utils::SubtitleUtils;
+
+/// A sidecar subtitle prepared for muxing
+#[derive(Clone, Debug)]
+struct MuxTrack {
+    path: PathBuf,
+    language: String,
+    title: Option<String>,
+    forced: bool,
+    hearing_impaired: bool,
+}
+
+/// Remuxing of videos with their sidecar subtitles
+pub struct SubtitleMuxer;
+
+impl SubtitleMuxer {
+    /// Whether a video can be muxed (only Matroska containers are rewritten in place)
+    pub fn supports(video_path: &Path) -> bool {
+        video_path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("mkv"))
+    }
+
+    /// Describe a sidecar from its name (video.en.srt, video.en.forced.srt, video.en+ja.ass)
+    fn track_for(video_path: &Path, subtitle_path: &Path) -> Option<MuxTrack> {
+        let ext = subtitle_path.extension()?.to_str()?.to_lowercase();
+        // VobSub .sub files need their .idx companion and aren't supported here
+        if !["srt", "ass", "ssa", "vtt"].contains(&ext.as_str()) {
+            return None;
+        }
+        let tag = SubtitleUtils::subtitle_language(video_path, subtitle_path)?;
+        let mut parts = tag.split('.');
+        let language = parts.next()?.to_string();
+        let flags: Vec<&str> = parts.collect();
+        let title = language.split_once('+').map(|(primary, secondary)| {
+            format!("{} + {}", SubtitleUtils::language_code_to_name(primary), SubtitleUtils::language_code_to_name(secondary))
+        });
+        Some(MuxTrack {
+            path: subtitle_path.to_path_buf(),
+            language,
+            title,
+            forced: flags.contains(&"forced"),
+            hearing_impaired: flags.contains(&"sdh"),
+        })
+    }
+
+    /// Container language tag for a selected language code
+    fn container_language(lang: &str) -> String {
+        let primary = lang.split('+').next().unwrap_or(lang);
+        SubtitleUtils::iso639_2_codes(primary).first()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| primary.to_string())
+    }
+
+    /// Whether the video already has a subtitle track like this one
+    fn already_embedded(existing: &[MediaStream], track: &MuxTrack) -> bool {
+        let primary = track.language.split('+').next().unwrap_or(&track.language);
+        existing.iter().any(|stream| {
+            stream.is_forced() == track.forced
+                && stream.language.as_deref().is_some_and(|l| SubtitleUtils::language_matches(l, primary))
+                && (track.title.is_none() || stream.title == track.title)
+        })
+    }
+
+    /// Check that a muxed file has the expected subtitle streams and duration
+    fn verify(path: &Path, expected_subtitles: usize, expected_duration: Option<f64>) -> Result<(), String> {
+        let subtitles = MediaProbe::streams(path, "s").len();
+        if subtitles != expected_subtitles {
+            return Err(format!("expected {} subtitle streams, found {}", expected_subtitles, subtitles));
+        }
+        if let Some(expected) = expected_duration {
+            match MediaProbe::duration_secs(path) {
+                Some(actual) if (actual - expected).abs() <= 1.0 => {}
+                Some(actual) => return Err(format!("duration changed from {:.1}s to {:.1}s", expected, actual)),
+                None => return Err("could not read duration".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Temporary output next to the video (.movie.mkv.rustitles-mux.tmp)
+    fn temp_path(video_path: &Path) -> Option<PathBuf> {
+        let file_name = video_path.file_name()?.to_str()?;
+        Some(video_path.with_file_name(format!(".{}.rustitles-mux.tmp", file_name)))
+    }
+
+    /// Replace the video with the verified temporary file in one rename, keeping a
+    /// link or copy of the original as a backup until the rename has succeeded
+    fn replace_original(video_path: &Path, temp_path: &Path, backup_path: &Path) -> Result<(), String> {
+        let _ = std::fs::remove_file(backup_path);
+        if std::fs::hard_link(video_path, backup_path).is_err() {
+            std::fs::copy(video_path, backup_path).map_err(|e| format!("failed to back up original: {}", e))?;
+        }
+        if let Err(e) = std::fs::rename(temp_path, video_path) {
+            let _ = std::fs::remove_file(backup_path);
+            return Err(format!("failed to replace original: {}", e));
+        }
+        if let Err(e) = std::fs::remove_file(backup_path) {
+            crate::warn!("Failed to remove mux backup {}: {}", backup_path.display(), e);
+        }
+        Ok(())
+    }
+
+    /// Mux the given sidecars into the video, replacing it atomically.
+    /// Returns the sidecars that were muxed.
+    pub fn mux(video_path: &Path, subtitle_paths: &[PathBuf], delete_sidecars: bool) -> Result<Vec<PathBuf>, String> {
+        if !Self::supports(video_path) {
+            return Err("muxing is only supported for MKV files".to_string());
+        }
+        let existing = MediaProbe::streams(video_path, "s");
+        let tracks: Vec<MuxTrack> = subtitle_paths.iter()
+            .filter_map(|p| Self::track_for(video_path, p))
+            .filter(|track| {
+                let duplicate = Self::already_embedded(&existing, track);
+                if duplicate {
+                    crate::debug!("Not muxing {}: the video already has this track", track.path.display());
+                }
+                !duplicate
+            })
+            .collect();
+        if tracks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let has_default = existing.iter().any(|s| s.default);
+        let duration = MediaProbe::duration_secs(video_path);
+        let file_name = video_path.file_name().and_then(|n| n.to_str()).ok_or("invalid video file name")?;
+        let temp_path = Self::temp_path(video_path).ok_or("invalid video file name")?;
+        let backup_path = video_path.with_file_name(format!("{}.rustitles-backup", file_name));
+
+        let mut args: Vec<OsString> = vec!["-i".into(), video_path.into()];
+        for track in &tracks {
+            args.push("-i".into());
+            args.push(track.path.clone().into());
+        }
+        args.push("-map".into());
+        args.push("0".into());
+        for i in 1..=tracks.len() {
+            args.push("-map".into());
+            args.push(i.to_string().into());
+        }
+        args.push("-c".into());
+        args.push("copy".into());
+        let mut default_set = has_default;
+        for (i, track) in tracks.iter().enumerate() {
+            let stream = existing.len() + i;
+            args.push(format!("-metadata:s:s:{}", stream).into());
+            args.push(format!("language={}", Self::container_language(&track.language)).into());
+            if let Some(title) = &track.title {
+                args.push(format!("-metadata:s:s:{}", stream).into());
+                args.push(format!("title={}", title).into());
+            }
+            let mut disposition = Vec::new();
+            if track.forced {
+                disposition.push("forced");
+            } else if !default_set {
+                // The first full subtitle becomes the default when the file has none
+                disposition.push("default");
+                default_set = true;
+            }
+            if track.hearing_impaired {
+                disposition.push("hearing_impaired");
+            }
+            args.push(format!("-disposition:s:{}", stream).into());
+            args.push(if disposition.is_empty() { "0".to_string() } else { disposition.join("+") }.into());
+        }
+        // The temporary file has no .mkv extension, so the format is given explicitly
+        args.push("-f".into());
+        args.push("matroska".into());
+        args.push(temp_path.clone().into());
+
+        let expected_subtitles = existing.len() + tracks.len();
+        let result = Ffmpeg::run(&args)
+            .and_then(|_| Self::verify(&temp_path, expected_subtitles, duration))
+            .and_then(|_| Self::replace_original(video_path, &temp_path, &backup_path));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        let muxed: Vec<PathBuf> = tracks.into_iter().map(|t| t.path).collect();
+        if delete_sidecars {
+            for path in &muxed {
+                if let Err(e) = std::fs::remove_file(path) {
+                    crate::warn!("Failed to delete muxed sidecar {}: {}", path.display(), e);
+                }
+            }
+        }
+        crate::info!("Muxed {} subtitles into {}", muxed.len(), video_path.display());
+        Ok(muxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper_functions::Utils;
+
+    #[test]
+    fn temporary_output_is_not_a_video() {
+        let temp = SubtitleMuxer::temp_path(Path::new("/videos/Movie.mkv")).unwrap();
+        assert_eq!(temp, PathBuf::from("/videos/.Movie.mkv.rustitles-mux.tmp"));
+        assert!(!Utils::is_video_file(&temp));
+    }
+
+    #[test]
+    fn replaces_the_original_in_one_rename() {
+        let folder = std::env::temp_dir().join(format!("rustitles-mux-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        let video = folder.join("Movie.mkv");
+        let temp = SubtitleMuxer::temp_path(&video).unwrap();
+        let backup = folder.join("Movie.mkv.rustitles-backup");
+
+        std::fs::write(&video, "original").unwrap();
+        std::fs::write(&temp, "muxed").unwrap();
+        SubtitleMuxer::replace_original(&video, &temp, &backup).unwrap();
+        assert_eq!(std::fs::read_to_string(&video).unwrap(), "muxed");
+        assert!(!temp.exists() && !backup.exists());
+
+        // Without a temporary file the original stays in place
+        assert!(SubtitleMuxer::replace_original(&video, &temp, &backup).is_err());
+        assert_eq!(std::fs::read_to_string(&video).unwrap(), "muxed");
+        assert!(!backup.exists());
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
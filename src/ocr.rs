@@ -0,0 +1,239 @@
+//! OCR conversion of bitmap subtitle tracks to SRT
+//!
+//! This module decodes PGS and VobSub tracks in a selected language and runs
+//! every subtitle image through the tesseract CLI with the matching language
+//! model, writing the recognised text as a timed SRT sidecar. All images of a
+//! track are recognised in a single tesseract run.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::bitmap_subtitles::{BitmapCue, BitmapSubtitleDecoder};
+use crate::embedded_subtitles::EmbeddedSubtitles;
+use crate::helper_functions::Utils;
+use crate::media_probe::{MediaProbe, MediaStream};
+use crate::subtitle_model::{Cue, Subtitle};
+use crate::subtitle_utils::SubtitleUtils;
+
+/// Counter keeping temporary image names unique across concurrent jobs
+static IMAGE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// tesseract command line runner
+pub struct Tesseract;
+
+impl Tesseract {
+    /// Tesseract traineddata name for a selected language code
+    pub fn language_code(lang: &str) -> Option<String> {
+        match lang {
+            "zh" | "zh-cn" => Some("chi_sim".to_string()),
+            "zh-tw" => Some("chi_tra".to_string()),
+            "no" => Some("nor".to_string()),
+            "fil" => Some("tgl".to_string()),
+            // Tesseract uses the terminologic ISO 639-2 codes, listed last
+            _ => SubtitleUtils::iso639_2_codes(lang).last().map(|c| c.to_string()),
+        }
+    }
+
+    /// List the installed language models
+    pub fn installed_languages() -> Result<Vec<String>, String> {
+        let output = Utils::hidden_command("tesseract")
+            .arg("--list-langs")
+            .output()
+            .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+        // Older versions print the list on stderr
+        let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        Ok(text.lines()
+            .filter(|l| !l.starts_with("List of available languages"))
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect())
+    }
+
+    /// Run tesseract on an image, or on a text file listing images
+    fn run(input: &Path, language: &str) -> Result<String, String> {
+        let output = Utils::hidden_command("tesseract")
+            .arg(input)
+            .arg("stdout")
+            .arg("-l").arg(language)
+            .arg("--psm").arg("6") // A single uniform block of text
+            .output()
+            .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(format!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+        }
+    }
+
+    /// Recognise the text in an image
+    pub fn recognize(image_path: &Path, language: &str) -> Result<String, String> {
+        Self::run(image_path, language)
+    }
+
+    /// Split the output of a batch run into pages. Tesseract separates pages with
+    /// form feeds; some versions also end the last page with one.
+    fn split_pages(text: &str, count: usize) -> Option<Vec<String>> {
+        let mut pages: Vec<String> = text.split('\x0c').map(str::to_string).collect();
+        if pages.len() == count + 1 && pages.last().is_some_and(|p| p.trim().is_empty()) {
+            pages.pop();
+        }
+        (pages.len() == count).then_some(pages)
+    }
+
+    /// Recognise the text of several images in a single tesseract run, which
+    /// loads the language model once instead of once per image. Returns one
+    /// text per image, in order.
+    pub fn recognize_batch(image_paths: &[PathBuf], language: &str) -> Result<Vec<String>, String> {
+        let list_path = std::env::temp_dir().join(format!(
+            "rustitles-ocr-{}-{}.txt",
+            std::process::id(),
+            IMAGE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let list: Vec<String> = image_paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        std::fs::write(&list_path, list.join("\n"))
+            .map_err(|e| format!("Failed to write OCR image list: {}", e))?;
+        let result = Self::run(&list_path, language);
+        let _ = std::fs::remove_file(&list_path);
+        let text = result?;
+        match Self::split_pages(&text, image_paths.len()) {
+            Some(pages) => Ok(pages),
+            None => {
+                // Page boundaries are lost, so fall back to one run per image
+                crate::warn!("tesseract batch output didn't split into {} pages, recognising images one by one", image_paths.len());
+                image_paths.iter().map(|p| Self::recognize(p, language)).collect()
+            }
+        }
+    }
+}
+
+/// Conversion of bitmap subtitle tracks to SRT
+pub struct SubtitleOcr;
+
+impl SubtitleOcr {
+    /// Pick the bitmap track to convert for a language, preferring full default tracks
+    fn pick_track<'a>(streams: &'a [MediaStream], lang: &str) -> Option<&'a MediaStream> {
+        let mut candidates: Vec<&MediaStream> = streams.iter()
+            .filter(|s| s.is_bitmap_subtitle())
+            .filter(|s| s.language.as_deref().is_some_and(|l| SubtitleUtils::language_matches(l, lang)))
+            .collect();
+        candidates.sort_by_key(|s| (s.is_forced(), !s.default));
+        candidates.into_iter().next()
+    }
+
+    /// Decode every image of a bitmap track
+    fn decode_track(video_path: &Path, stream: &MediaStream) -> Vec<BitmapCue> {
+        let packets = MediaProbe::packets(video_path, stream.index);
+        if stream.codec_name == "dvd_subtitle" {
+            let palette = MediaProbe::extradata(video_path, stream.index)
+                .and_then(|idx| BitmapSubtitleDecoder::parse_idx_palette(&idx));
+            BitmapSubtitleDecoder::decode_vobsub(&packets, palette.as_ref())
+        } else {
+            BitmapSubtitleDecoder::decode_pgs(&packets)
+        }
+    }
+
+    /// Tidy up recognised text: drop empty lines and fix the most common misreads
+    fn clean_text(text: &str) -> String {
+        text.lines()
+            .map(|l| l.trim().replace('|', "I"))
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Write a cue image to a temporary PGM file for OCR
+    fn write_image(cue: &BitmapCue, scale: usize) -> Result<PathBuf, String> {
+        let image_path = std::env::temp_dir().join(format!(
+            "rustitles-ocr-{}-{}.pgm",
+            std::process::id(),
+            IMAGE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&image_path, cue.bitmap.padded_scaled(10, scale).to_pgm())
+            .map_err(|e| format!("Failed to write OCR image: {}", e))?;
+        Ok(image_path)
+    }
+
+    /// Run OCR on every cue image of a track with one tesseract run
+    fn recognize_cues(cues: &[BitmapCue], language: &str, scale: usize) -> Result<Vec<String>, String> {
+        let mut image_paths = Vec::with_capacity(cues.len());
+        let written = cues.iter().try_for_each(|cue| {
+            image_paths.push(Self::write_image(cue, scale)?);
+            Ok::<(), String>(())
+        });
+        let result = written.and_then(|_| Tesseract::recognize_batch(&image_paths, language));
+        for path in &image_paths {
+            let _ = std::fs::remove_file(path);
+        }
+        result.map(|texts| texts.iter().map(|text| Self::clean_text(text)).collect())
+    }
+
+    /// Convert the bitmap track in a language to {stem}.{lang}.srt.
+    /// Returns Ok(None) when the video has no bitmap track in that language.
+    pub fn convert(video_path: &Path, lang: &str) -> Result<Option<PathBuf>, String> {
+        let streams = MediaProbe::streams(video_path, "s");
+        let Some(stream) = Self::pick_track(&streams, lang) else {
+            return Ok(None);
+        };
+        let language = Tesseract::language_code(lang)
+            .ok_or_else(|| format!("no OCR language model known for '{}'", lang))?;
+        if !Tesseract::installed_languages()?.contains(&language) {
+            return Err(format!("tesseract language data '{}' is not installed", language));
+        }
+        let output = EmbeddedSubtitles::sidecar_path(video_path, lang, false)
+            .ok_or("invalid video file name")?;
+
+        crate::info!("Running OCR on {} track {} of {}", stream.codec_name, stream.index, video_path.display());
+        let images = Self::decode_track(video_path, stream);
+        if images.is_empty() {
+            return Err(format!("no images decoded from {} track {}", stream.codec_name, stream.index));
+        }
+        // DVD subtitles are low resolution, so upscale them before OCR
+        let scale = if stream.codec_name == "dvd_subtitle" { 2 } else { 1 };
+        let texts = Self::recognize_cues(&images, &language, scale)?;
+        let cues: Vec<Cue> = images.iter().zip(texts)
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(image, text)| Cue { start_ms: image.start_ms, end_ms: image.end_ms, text })
+            .collect();
+        if cues.is_empty() {
+            return Err("OCR recognised no text".to_string());
+        }
+
+        std::fs::write(&output, Subtitle { cues }.to_srt())
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        crate::info!("OCR converted {} images to {}", images.len(), output.display());
+        Ok(Some(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_batch_output_into_pages() {
+        let cases: [(&str, usize, Option<Vec<&str>>); 5] = [
+            ("Hello\n\x0cWorld\n", 2, Some(vec!["Hello\n", "World\n"])),
+            ("Hello\n\x0cWorld\n\x0c", 2, Some(vec!["Hello\n", "World\n"])),
+            ("\x0c\x0cLast", 3, Some(vec!["", "", "Last"])),
+            ("Only one page", 1, Some(vec!["Only one page"])),
+            ("Hello\x0cWorld", 3, None),
+        ];
+        for (text, count, expected) in cases {
+            let expected = expected.map(|pages| pages.into_iter().map(str::to_string).collect::<Vec<_>>());
+            assert_eq!(Tesseract::split_pages(text, count), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn cleans_recognised_text() {
+        assert_eq!(SubtitleOcr::clean_text("  |t was\n\n  here  \n"), "It was\nhere");
+    }
+
+    #[test]
+    fn maps_tesseract_language_codes() {
+        let cases = [("en", Some("eng")), ("fr", Some("fra")), ("zh-tw", Some("chi_tra")), ("xx", None)];
+        for (lang, expected) in cases {
+            assert_eq!(Tesseract::language_code(lang).as_deref(), expected, "{}", lang);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Native OpenSubtitles.com REST client
+//!
+//! This backend talks to the OpenSubtitles.com REST API directly so subtitles
+//! can be downloaded without Python. It searches by the OpenSubtitles movie
+//! hash first and falls back to the title, season and episode read from the
+//! file name. It logs in with the "opensubtitlescom" provider account when one
+//! is configured, which raises the daily download quota, and reports quota and
+//! rate-limit responses to the shared provider limits. An expired login token
+//! is dropped and the login retried once. The base URL is configurable so the
+//! client can be pointed at a local mock server.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::config::APP_VERSION;
+use crate::filename_parser::FilenameParser;
+use crate::provider_accounts::{ProviderAccount, ProviderAccounts};
+use crate::provider_limits::{LimitKind, ProviderLimitHit, ProviderLimits};
+use crate::settings::{BackendSettings, HearingImpairedPreference};
+use crate::subliminal::{DownloadedSubtitle, SubliminalOptions, SubliminalReport};
+use crate::subtitle_backend::SubtitleBackend;
+use crate::video_hash::{HashKind, VideoHasher};
+
+/// Provider name used for accounts, limits and reports
+pub const OPENSUBTITLES_PROVIDER: &str = "opensubtitlescom";
+
+/// A subtitle file found by a search
+#[derive(Clone, Debug)]
+struct SearchResult {
+    file_id: u64,
+    language: String,
+    hearing_impaired: bool,
+    hash_match: bool,
+    download_count: u64,
+}
+
+/// Why a request failed
+enum ApiError {
+    Limited(LimitKind, String),
+    /// The login token was rejected, e.g. because it expired
+    Unauthorized(String),
+    Other(String),
+}
+
+impl ApiError {
+    fn message(&self) -> &str {
+        match self {
+            ApiError::Limited(_, message) | ApiError::Unauthorized(message) | ApiError::Other(message) => message,
+        }
+    }
+}
+
+/// Client for the OpenSubtitles.com REST API
+pub struct OpenSubtitlesClient {
+    http: Client,
+    base_url: String,
+    api_key: String,
+    account: Option<ProviderAccount>,
+    /// Login token, fetched once and shared by all jobs
+    token: Mutex<Option<String>>,
+}
+
+impl OpenSubtitlesClient {
+    pub fn new(settings: &BackendSettings, accounts: &ProviderAccounts) -> Self {
+        let http = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self {
+            http,
+            base_url: settings.opensubtitles_base_url.trim().trim_end_matches('/').to_string(),
+            api_key: settings.opensubtitles_api_key.trim().to_string(),
+            account: accounts.accounts.get(OPENSUBTITLES_PROVIDER).filter(|a| !a.is_empty()).cloned(),
+            token: Mutex::new(None),
+        }
+    }
+
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder
+            .header("Api-Key", &self.api_key)
+            .header("User-Agent", format!("Rustitles v{}", APP_VERSION))
+            .header("Accept", "application/json")
+    }
+
+    /// Read a JSON response, turning quota, rate-limit and auth responses into errors
+    fn read_response(response: reqwest::blocking::Response) -> Result<Value, ApiError> {
+        let status = response.status();
+        let body: Value = response.json().unwrap_or(Value::Null);
+        let message = body.get("message").or_else(|| body.get("errors"))
+            .map(|m| m.to_string().trim_matches('"').to_string())
+            .unwrap_or_else(|| status.to_string());
+        match status {
+            s if s.is_success() => Ok(body),
+            StatusCode::TOO_MANY_REQUESTS => Err(ApiError::Limited(LimitKind::RateLimit, message)),
+            // The API answers 406 once the daily download quota is used up
+            StatusCode::NOT_ACCEPTABLE => Err(ApiError::Limited(LimitKind::Quota, message)),
+            StatusCode::UNAUTHORIZED => Err(ApiError::Unauthorized(format!("authentication failed: {}", message))),
+            StatusCode::FORBIDDEN => Err(ApiError::Other(format!("authentication failed: {}", message))),
+            _ => Err(ApiError::Other(format!("OpenSubtitles returned {}: {}", status, message))),
+        }
+    }
+
+    /// Login token, logging in on first use or after the token was cleared.
+    /// Anonymous use needs no token.
+    fn token(&self) -> Result<Option<String>, ApiError> {
+        let Some(account) = &self.account else {
+            return Ok(None);
+        };
+        let mut token = self.token.lock().unwrap();
+        if token.is_none() {
+            crate::info!("Logging in to OpenSubtitles.com as {}", account.username);
+            let response = self.request(self.http.post(format!("{}/login", self.base_url)))
+                .json(&serde_json::json!({ "username": account.username, "password": account.password }))
+                .send()
+                .map_err(|e| ApiError::Other(format!("HTTP error: {}", e)))?;
+            // A rejected login is final, unlike a rejected token
+            let body = Self::read_response(response).map_err(|e| match e {
+                ApiError::Unauthorized(message) => ApiError::Other(message),
+                e => e,
+            })?;
+            let new_token = body.get("token").and_then(|t| t.as_str())
+                .ok_or_else(|| ApiError::Other("login response has no token".to_string()))?;
+            *token = Some(new_token.to_string());
+        }
+        Ok(token.clone())
+    }
+
+    /// Search for subtitles with the given query parameters
+    fn search(&self, params: &[(&str, String)]) -> Result<Vec<SearchResult>, ApiError> {
+        let response = self.request(self.http.get(format!("{}/subtitles", self.base_url)))
+            .query(params)
+            .send()
+            .map_err(|e| ApiError::Other(format!("HTTP error: {}", e)))?;
+        let body = Self::read_response(response)?;
+        let results = body.get("data").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+        Ok(results.iter()
+            .filter_map(|item| {
+                let attributes = item.get("attributes")?;
+                let file_id = attributes.get("files")?.as_array()?.first()?.get("file_id")?.as_u64()?;
+                Some(SearchResult {
+                    file_id,
+                    language: attributes.get("language")?.as_str()?.to_lowercase(),
+                    hearing_impaired: attributes.get("hearing_impaired").and_then(|v| v.as_bool()).unwrap_or(false),
+                    hash_match: attributes.get("moviehash_match").and_then(|v| v.as_bool()).unwrap_or(false),
+                    download_count: attributes.get("download_count").and_then(|v| v.as_u64()).unwrap_or(0),
+                })
+            })
+            .collect())
+    }
+
+    /// Request a download link for a file
+    fn request_download(&self, file_id: u64) -> Result<Value, ApiError> {
+        let mut builder = self.request(self.http.post(format!("{}/download", self.base_url)))
+            .json(&serde_json::json!({ "file_id": file_id }));
+        if let Some(token) = self.token()? {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().map_err(|e| ApiError::Other(format!("HTTP error: {}", e)))?;
+        Self::read_response(response)
+    }
+
+    /// Request a download link for a file and fetch the subtitle
+    fn download_file(&self, file_id: u64) -> Result<Vec<u8>, ApiError> {
+        let body = match self.request_download(file_id) {
+            Err(ApiError::Unauthorized(message)) if self.account.is_some() => {
+                // The token expired or was revoked, so log in again once
+                crate::info!("OpenSubtitles.com rejected the login token ({}), logging in again", message);
+                *self.token.lock().unwrap() = None;
+                self.request_download(file_id)?
+            }
+            result => result?,
+        };
+        if let Some(remaining) = body.get("remaining").and_then(|r| r.as_i64()) {
+            let remaining = u32::try_from(remaining).unwrap_or(0);
+            ProviderLimits::record(&[OPENSUBTITLES_PROVIDER.to_string()], &[(OPENSUBTITLES_PROVIDER.to_string(), remaining)], &[]);
+        }
+        let link = body.get("link").and_then(|l| l.as_str())
+            .ok_or_else(|| ApiError::Other("download response has no link".to_string()))?;
+        let bytes = self.http.get(link)
+            .header("User-Agent", format!("Rustitles v{}", APP_VERSION))
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.bytes())
+            .map_err(|e| ApiError::Other(format!("Failed to download subtitle: {}", e)))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Search by movie hash first, then by the title parsed from the file name
+    fn find(&self, video_path: &Path, languages: &[String], options: &SubliminalOptions) -> Result<Vec<SearchResult>, ApiError> {
+        let mut sorted: Vec<String> = languages.iter().map(|l| l.to_lowercase()).collect();
+        sorted.sort();
+        // The API redirects unless languages are sorted and lowercase
+        let mut common = vec![("languages", sorted.join(","))];
+        common.push(("hearing_impaired", match options.hearing_impaired {
+            HearingImpairedPreference::Avoid => "exclude".to_string(),
+            _ => "include".to_string(),
+        }));
+        common.push(("foreign_parts_only", if options.foreign_only { "only" } else { "exclude" }.to_string()));
+
+        let mut results = Vec::new();
+        match VideoHasher::hash(video_path, HashKind::OpenSubtitles) {
+            Some(hash) => {
+                let mut params = common.clone();
+                params.push(("moviehash", hash));
+                results = self.search(&params)?;
+            }
+            None => {
+                crate::debug!("Not searching {} by hash: file can't be hashed", video_path.display());
+            }
+        }
+        let missing = sorted.iter().any(|lang| !results.iter().any(|r| &r.language == lang));
+        if missing {
+            let metadata = FilenameParser::parse(video_path);
+            if !metadata.title.is_empty() {
+                let mut params = common;
+                params.push(("query", metadata.title.clone()));
+                let episode = metadata.episodes.first().copied().or(metadata.absolute_episode);
+                if let Some(episode) = episode {
+                    // Anime absolute numbering has no season
+                    if let Some(season) = metadata.season {
+                        params.push(("season_number", season.to_string()));
+                    }
+                    params.push(("episode_number", episode.to_string()));
+                } else if let Some(year) = metadata.year {
+                    params.push(("year", year.to_string()));
+                }
+                results.extend(self.search(&params)?);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Best result for a language: hash matches first, then the preferred
+    /// hearing impaired variant, then the most downloaded
+    fn best_for<'a>(results: &'a [SearchResult], lang: &str, preference: HearingImpairedPreference) -> Option<&'a SearchResult> {
+        results.iter()
+            .filter(|r| r.language == lang)
+            .max_by_key(|r| {
+                let hi_rank = match preference {
+                    HearingImpairedPreference::Prefer => r.hearing_impaired,
+                    HearingImpairedPreference::Avoid => !r.hearing_impaired,
+                    HearingImpairedPreference::NoPreference => true,
+                };
+                (r.hash_match, hi_rank, r.download_count)
+            })
+    }
+
+    fn download_languages(&self, options: &SubliminalOptions, video_path: &Path, report: &mut SubliminalReport) -> Result<(), ApiError> {
+        let folder = match &options.directory {
+            Some(directory) => directory.as_path(),
+            None => video_path.parent().ok_or_else(|| ApiError::Other("invalid video path".to_string()))?,
+        };
+        let stem = video_path.file_stem().and_then(|s| s.to_str())
+            .ok_or_else(|| ApiError::Other("invalid video file name".to_string()))?;
+        let wanted: Vec<String> = options.languages.iter()
+            .filter(|lang| options.force || !folder.join(format!("{}.{}.srt", stem, lang)).exists())
+            .cloned()
+            .collect();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+
+        let results = self.find(video_path, &wanted, options)?;
+        for lang in &wanted {
+            let Some(result) = Self::best_for(&results, &lang.to_lowercase(), options.hearing_impaired) else {
+                report.combined_output.push_str(&format!("no {} subtitle found\n", lang));
+                continue;
+            };
+            let content = self.download_file(result.file_id)?;
+            let path = folder.join(format!("{}.{}.srt", stem, lang));
+            std::fs::write(&path, content)
+                .map_err(|e| ApiError::Other(format!("Failed to write {}: {}", path.display(), e)))?;
+            crate::info!("Downloaded {} subtitle from OpenSubtitles.com to {}", lang, path.display());
+            report.downloaded.push(DownloadedSubtitle {
+                language: lang.clone(),
+                provider: OPENSUBTITLES_PROVIDER.to_string(),
+                score: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SubtitleBackend for OpenSubtitlesClient {
+    fn name(&self) -> &'static str {
+        OPENSUBTITLES_PROVIDER
+    }
+
+    fn download(&self, options: &SubliminalOptions, video_path: &Path) -> Result<SubliminalReport, String> {
+        // A retry that excludes this provider has nothing else to try
+        if !options.providers.is_empty() && !options.providers.iter().any(|p| p == OPENSUBTITLES_PROVIDER) {
+            return Ok(SubliminalReport { combined_output: "downloaded 0 subtitle".to_string(), ..SubliminalReport::default() });
+        }
+        if self.api_key.is_empty() {
+            return Err("no OpenSubtitles.com API key configured".to_string());
+        }
+        let paused = ProviderLimits::paused(&[OPENSUBTITLES_PROVIDER.to_string()]);
+        if !paused.is_empty() {
+            crate::debug!("OpenSubtitles.com is paused, skipping {}", video_path.display());
+            return Ok(SubliminalReport { limit_hits: paused, ..SubliminalReport::default() });
+        }
+        ProviderLimits::wait_for_request_slot(options.requests_per_minute);
+
+        let mut report = SubliminalReport::default();
+        match self.download_languages(options, video_path, &mut report) {
+            Ok(()) => {}
+            Err(ApiError::Limited(kind, message)) => {
+                crate::warn!("OpenSubtitles.com {} for {}: {}", kind.label(), video_path.display(), message);
+                ProviderLimits::pause(OPENSUBTITLES_PROVIDER, kind);
+                report.limit_hits.push(ProviderLimitHit { provider: OPENSUBTITLES_PROVIDER.to_string(), kind });
+            }
+            Err(e) => {
+                if report.downloaded.is_empty() {
+                    return Err(e.message().to_string());
+                }
+                crate::error!("OpenSubtitles.com failed for {}: {}", video_path.display(), e.message());
+            }
+        }
+        report.combined_output.push_str(&format!("downloaded {} subtitle(s)", report.downloaded.len()));
+        crate::info!("OpenSubtitles.com result for {}:\n{}", video_path.display(), report.combined_output);
+        Ok(report)
+    }
+}
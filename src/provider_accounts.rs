@@ -0,0 +1,421 @@
+//! Provider account credentials
+//!
+//! Usernames and passwords for providers that support logging in are kept out
+//! of the settings file, in the platform's credential store: the Secret Service
+//! (through `secret-tool`) on Linux, the login Keychain (through `security`) on
+//! macOS, and a file encrypted for the current user with DPAPI on Windows.
+//! Without a store the accounts last only until Rustitles closes, and the plain
+//! text file older versions wrote is moved into the store on the next save.
+//! Logins are tested with Subliminal's own provider code so the result matches
+//! what downloads will do.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ACCOUNT_PROVIDERS;
+use crate::helper_functions::Utils;
+use crate::python_manager::PythonManager;
+use crate::settings::Settings;
+use crate::subliminal::Subliminal;
+
+/// Service attribute used for Secret Service and Keychain entries
+const SECRET_SERVICE_NAME: &str = "rustitles";
+
+/// Logs in with a provider and reports authentication errors separately
+const LOGIN_TEST_SCRIPT: &str = r#"
+import os, sys
+from subliminal.extensions import provider_manager
+from subliminal.exceptions import AuthenticationError
+try:
+    provider = provider_manager[os.environ["RUSTITLES_PROVIDER"]].plugin(
+        username=os.environ["RUSTITLES_USERNAME"], password=os.environ["RUSTITLES_PASSWORD"])
+    provider.initialize()
+    provider.terminate()
+except AuthenticationError as e:
+    print("authentication failed: %s" % e)
+    sys.exit(2)
+except Exception as e:
+    print("%s: %s" % (type(e).__name__, e))
+    sys.exit(1)
+"#;
+
+/// Login for a single provider
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProviderAccount {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for ProviderAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderAccount")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+impl ProviderAccount {
+    pub fn is_empty(&self) -> bool {
+        self.username.trim().is_empty()
+    }
+}
+
+/// Where provider accounts are stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CredentialStorage {
+    /// Linux Secret Service, through secret-tool
+    SecretService,
+    /// macOS login Keychain, through the security tool
+    Keychain,
+    /// File encrypted for the current Windows user with DPAPI
+    EncryptedFile,
+    /// Nowhere: accounts are kept until Rustitles closes
+    Session,
+}
+
+/// Accounts for every provider with a login, keyed by provider name
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct ProviderAccounts {
+    pub accounts: BTreeMap<String, ProviderAccount>,
+}
+
+impl ProviderAccounts {
+    /// Path of the encrypted credentials file used on Windows, next to the settings file
+    pub fn file_path() -> std::io::Result<PathBuf> {
+        Ok(Settings::get_path()?.with_file_name("rustitles_credentials.dat"))
+    }
+
+    /// Path of the plain text credentials file written by older versions
+    fn legacy_file_path() -> std::io::Result<PathBuf> {
+        let settings_path = Settings::get_path()?;
+        #[cfg(windows)]
+        let file_name = "rustitles_credentials.json";
+        #[cfg(not(windows))]
+        let file_name = "credentials.json";
+        Ok(settings_path.with_file_name(file_name))
+    }
+
+    /// Store used for accounts on this system
+    pub fn storage() -> CredentialStorage {
+        if cfg!(windows) {
+            CredentialStorage::EncryptedFile
+        } else if cfg!(target_os = "macos") && which::which("security").is_ok() {
+            CredentialStorage::Keychain
+        } else if cfg!(target_os = "linux") && which::which("secret-tool").is_ok() {
+            CredentialStorage::SecretService
+        } else {
+            CredentialStorage::Session
+        }
+    }
+
+    /// Load accounts from the credential store, plus any left in an old plain text file
+    pub fn load() -> Self {
+        let storage = Self::storage();
+        let mut loaded = Self::default();
+        match storage {
+            CredentialStorage::EncryptedFile => loaded = Self::load_file(),
+            CredentialStorage::SecretService | CredentialStorage::Keychain => {
+                for provider in ACCOUNT_PROVIDERS {
+                    if let Some(account) = Self::store_lookup(storage, provider) {
+                        loaded.accounts.insert(provider.to_string(), account);
+                    }
+                }
+            }
+            CredentialStorage::Session => {}
+        }
+        for (provider, account) in Self::load_legacy_file().accounts {
+            loaded.accounts.entry(provider).or_insert(account);
+        }
+        loaded.accounts.retain(|_, account| !account.is_empty());
+        crate::debug!("Loaded accounts for providers: {:?}", loaded.accounts.keys().collect::<Vec<_>>());
+        loaded
+    }
+
+    fn load_file() -> Self {
+        let Ok(path) = Self::file_path() else {
+            return Self::default();
+        };
+        let Ok(encrypted) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        Self::decrypt(&encrypted)
+            .and_then(|json| serde_json::from_slice(&json).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                crate::warn!("Failed to read credentials file {}: {}", path.display(), e);
+                Self::default()
+            })
+    }
+
+    fn load_legacy_file() -> Self {
+        let Ok(path) = Self::legacy_file_path() else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                crate::warn!("Failed to parse credentials file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Save accounts to the credential store and remove any old plain text file.
+    /// Accounts the store rejects stay in memory until Rustitles closes.
+    pub fn save(&self) -> Result<(), String> {
+        let storage = Self::storage();
+        let accounts: BTreeMap<String, ProviderAccount> = self.accounts.iter()
+            .filter(|(_, account)| !account.is_empty())
+            .map(|(provider, account)| (provider.clone(), account.clone()))
+            .collect();
+        let mut errors = Vec::new();
+        match storage {
+            CredentialStorage::EncryptedFile => {
+                if let Err(e) = Self::write_file(&ProviderAccounts { accounts }) {
+                    errors.push(e);
+                }
+            }
+            CredentialStorage::SecretService | CredentialStorage::Keychain => {
+                for provider in ACCOUNT_PROVIDERS {
+                    let stored = match accounts.get(*provider) {
+                        Some(account) => Self::store_save(storage, provider, account),
+                        None => Self::store_clear(storage, provider),
+                    };
+                    if let Err(e) = stored {
+                        errors.push(format!("{}: {}", provider, e));
+                    }
+                }
+            }
+            CredentialStorage::Session => {
+                crate::info!("No credential store available, keeping provider accounts for this session only");
+            }
+        }
+        Self::remove_legacy_file()?;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} (kept for this session only)", errors.join(", ")))
+        }
+    }
+
+    fn remove_legacy_file() -> Result<(), String> {
+        let path = Self::legacy_file_path().map_err(|e| format!("Failed to get credentials path: {}", e))?;
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            crate::info!("Removed plain text credentials file {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// Write the encrypted credentials file, or remove it when there are no accounts
+    fn write_file(accounts: &ProviderAccounts) -> Result<(), String> {
+        let path = Self::file_path().map_err(|e| format!("Failed to get credentials path: {}", e))?;
+        if accounts.accounts.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            }
+            return Ok(());
+        }
+        let json = serde_json::to_vec(accounts)
+            .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+        let encrypted = Self::encrypt(&json)?;
+        std::fs::write(&path, encrypted).map_err(|e| format!("Failed to write credentials file: {}", e))?;
+        crate::debug!("Credentials saved to {}", path.display());
+        Ok(())
+    }
+
+    /// Encrypt data so only the current Windows user can read it
+    #[cfg(windows)]
+    fn encrypt(data: &[u8]) -> Result<Vec<u8>, String> {
+        use windows::core::PCWSTR;
+        use windows::Win32::Security::Cryptography::{CryptProtectData, CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN};
+
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, PCWSTR::null(), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Failed to encrypt credentials: {}", e))?;
+            Ok(Self::take_blob(output))
+        }
+    }
+
+    #[cfg(windows)]
+    fn decrypt(data: &[u8]) -> Result<Vec<u8>, String> {
+        use windows::Win32::Security::Cryptography::{CryptUnprotectData, CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN};
+
+        let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| format!("Failed to decrypt credentials: {}", e))?;
+            Ok(Self::take_blob(output))
+        }
+    }
+
+    /// Copy out a buffer allocated by DPAPI and free it
+    #[cfg(windows)]
+    unsafe fn take_blob(blob: windows::Win32::Security::Cryptography::CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        use windows::Win32::Foundation::{LocalFree, HLOCAL};
+
+        let bytes = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(blob.pbData.cast()));
+        bytes
+    }
+
+    #[cfg(not(windows))]
+    fn encrypt(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err("encrypted credentials files are only used on Windows".to_string())
+    }
+
+    #[cfg(not(windows))]
+    fn decrypt(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err("encrypted credentials files are only used on Windows".to_string())
+    }
+
+    fn store_lookup(storage: CredentialStorage, provider: &str) -> Option<ProviderAccount> {
+        let output = match storage {
+            CredentialStorage::SecretService => Utils::hidden_command("secret-tool")
+                .args(["lookup", "service", SECRET_SERVICE_NAME, "provider", provider])
+                .output(),
+            _ => Utils::hidden_command("security")
+                .args(["find-generic-password", "-s", SECRET_SERVICE_NAME, "-a", provider, "-w"])
+                .output(),
+        }.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let secret = match storage {
+            CredentialStorage::SecretService => output.stdout,
+            _ => Self::hex_decode(String::from_utf8_lossy(&output.stdout).trim())?,
+        };
+        serde_json::from_slice(&secret).ok()
+    }
+
+    fn store_save(storage: CredentialStorage, provider: &str, account: &ProviderAccount) -> Result<(), String> {
+        let secret = serde_json::to_string(account).map_err(|e| e.to_string())?;
+        // The secret is passed on stdin so it never shows up in the process list
+        let (program, input) = match storage {
+            CredentialStorage::SecretService => ("secret-tool", secret),
+            // security reads commands from stdin with -i; hex keeps the secret free of quoting
+            _ => ("security", format!(
+                "add-generic-password -U -s {} -a {} -w {}\n",
+                SECRET_SERVICE_NAME, provider, Self::hex_encode(secret.as_bytes()),
+            )),
+        };
+        let mut command = Utils::hidden_command(program);
+        match storage {
+            CredentialStorage::SecretService => command
+                .arg("store")
+                .arg(format!("--label=Rustitles {} account", provider))
+                .args(["service", SECRET_SERVICE_NAME, "provider", provider]),
+            _ => command.arg("-i"),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        child.stdin.take().ok_or("failed to open stdin")?
+            .write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to write to {}: {}", program, e))?;
+        let output = child.wait_with_output().map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        // security -i exits successfully even when a command fails, but reports it on stderr
+        if output.status.success() && (storage == CredentialStorage::SecretService || stderr.is_empty()) {
+            Ok(())
+        } else {
+            Err(stderr)
+        }
+    }
+
+    fn store_clear(storage: CredentialStorage, provider: &str) -> Result<(), String> {
+        let output = match storage {
+            CredentialStorage::SecretService => Utils::hidden_command("secret-tool")
+                .args(["clear", "service", SECRET_SERVICE_NAME, "provider", provider])
+                .output(),
+            _ => Utils::hidden_command("security")
+                .args(["delete-generic-password", "-s", SECRET_SERVICE_NAME, "-a", provider])
+                .output(),
+        }.map_err(|e| format!("Failed to run the credential store: {}", e))?;
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        // Deleting a Keychain item that was never stored isn't an error here
+        if output.status.success() || stderr.contains("could not be found") {
+            Ok(())
+        } else {
+            Err(stderr)
+        }
+    }
+
+    fn hex_encode(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+        (0..hex.len()).step_by(2)
+            .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect()
+    }
+
+    /// Accounts with a username, in provider order
+    pub fn configured(&self) -> Vec<(String, ProviderAccount)> {
+        self.accounts.iter()
+            .filter(|(_, account)| !account.is_empty())
+            .map(|(provider, account)| (provider.clone(), account.clone()))
+            .collect()
+    }
+
+    /// Log in with Subliminal's provider code and report why it failed
+    pub fn test_login(provider: &str, account: &ProviderAccount) -> Result<(), String> {
+        if account.is_empty() {
+            return Err("no username entered".to_string());
+        }
+        let mut env_vars: HashMap<String, String> = Subliminal::build_env();
+        env_vars.insert("RUSTITLES_PROVIDER".to_string(), provider.to_string());
+        env_vars.insert("RUSTITLES_USERNAME".to_string(), account.username.clone());
+        env_vars.insert("RUSTITLES_PASSWORD".to_string(), account.password.clone());
+
+        crate::info!("Testing {} login for {}", provider, account.username);
+        let output = ["python3", "python", "py"].iter()
+            .find_map(|python| PythonManager::run_command_hidden(python, &["-c", LOGIN_TEST_SCRIPT], &env_vars).ok())
+            .ok_or("Python is not available")?;
+        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        match output.status.code() {
+            Some(0) => {
+                crate::info!("{} login succeeded", provider);
+                Ok(())
+            }
+            Some(2) => {
+                crate::warn!("{} login rejected: {}", provider, message);
+                Err(format!("Login rejected: {}", message.trim_start_matches("authentication failed: ")))
+            }
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                let detail = if message.is_empty() { stderr } else { message };
+                crate::warn!("{} login test failed: {}", provider, detail);
+                Err(format!("Could not log in: {}", detail))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keychain_secrets_round_trip_through_hex() {
+        let account = ProviderAccount { username: "user name".to_string(), password: "p\"a ss'w\\ord é".to_string() };
+        let secret = serde_json::to_string(&account).unwrap();
+        let hex = ProviderAccounts::hex_encode(secret.as_bytes());
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+        let decoded = ProviderAccounts::hex_decode(&hex).unwrap();
+        assert_eq!(serde_json::from_slice::<ProviderAccount>(&decoded).unwrap(), account);
+        assert_eq!(ProviderAccounts::hex_decode("abc"), None);
+        assert_eq!(ProviderAccounts::hex_decode("zz"), None);
+    }
+}
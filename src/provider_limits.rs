@@ -0,0 +1,253 @@
+//! Provider quota and rate-limit tracking
+//!
+//! Providers such as OpenSubtitles enforce daily download quotas and reject
+//! bursts of requests. This module recognizes those responses in Subliminal's
+//! output, pauses the affected provider (until the quota resets at midnight UTC,
+//! or for a cooldown after a rate limit) and spaces out Subliminal runs with a
+//! global requests-per-minute limit shared by all download threads.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use once_cell::sync::Lazy;
+
+use crate::config::{RATE_LIMIT_COOLDOWN_SECS, SUBLIMINAL_PROVIDERS};
+
+/// Exceptions Subliminal and its providers raise when a download quota is used up
+const QUOTA_PHRASES: &[&str] = &["downloadlimitexceeded", "downloadlimitreached"];
+
+/// Exceptions and HTTP errors raised when a provider rejects requests for coming too fast
+const RATE_LIMIT_PHRASES: &[&str] = &["toomanyrequests", "429 client error"];
+
+/// Why a provider stopped serving downloads
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LimitKind {
+    Quota,
+    RateLimit,
+}
+
+impl LimitKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LimitKind::Quota => "quota",
+            LimitKind::RateLimit => "rate limit",
+        }
+    }
+}
+
+/// A provider that reported a quota or rate limit during a run
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProviderLimitHit {
+    pub provider: String,
+    pub kind: LimitKind,
+}
+
+/// Current limits and usage of a provider, for display
+#[derive(Clone, Debug)]
+pub struct ProviderLimitStatus {
+    pub provider: String,
+    pub paused_until: Option<(DateTime<Utc>, LimitKind)>,
+    pub downloads: u32,
+    pub remaining: Option<u32>,
+}
+
+#[derive(Default)]
+struct LimitState {
+    request_times: VecDeque<Instant>,
+    paused: BTreeMap<String, (DateTime<Utc>, LimitKind)>,
+    downloads: BTreeMap<String, u32>,
+    remaining: BTreeMap<String, u32>,
+}
+
+static STATE: Lazy<Mutex<LimitState>> = Lazy::new(|| Mutex::new(LimitState::default()));
+
+/// Shared provider limit state for all download threads
+pub struct ProviderLimits;
+
+impl ProviderLimits {
+    /// Provider named in a line of output. The longest name wins so that
+    /// "opensubtitlescom" isn't read as "opensubtitles".
+    fn provider_in_line(line: &str) -> Option<&'static str> {
+        SUBLIMINAL_PROVIDERS.iter()
+            .filter(|p| line.contains(*p))
+            .max_by_key(|p| p.len())
+            .copied()
+    }
+
+    /// Find quota and rate-limit exceptions in lowercased Subliminal output.
+    /// A traceback line often doesn't name the provider, so the provider of the
+    /// closest line above it is used instead.
+    pub fn detect(output: &str) -> Vec<ProviderLimitHit> {
+        let mut hits: Vec<ProviderLimitHit> = Vec::new();
+        let mut last_provider = None;
+        for line in output.lines() {
+            let provider = Self::provider_in_line(line).or(last_provider);
+            last_provider = provider;
+            let kind = if QUOTA_PHRASES.iter().any(|p| line.contains(p)) {
+                LimitKind::Quota
+            } else if RATE_LIMIT_PHRASES.iter().any(|p| line.contains(p)) {
+                LimitKind::RateLimit
+            } else {
+                continue;
+            };
+            let Some(provider) = provider else {
+                continue;
+            };
+            if !hits.iter().any(|h| h.provider == provider && h.kind == kind) {
+                hits.push(ProviderLimitHit { provider: provider.to_string(), kind });
+            }
+        }
+        hits
+    }
+
+    /// Find "remaining downloads" counts reported by providers in lowercased output
+    pub fn detect_remaining(output: &str) -> Vec<(String, u32)> {
+        output.lines()
+            .filter_map(|line| {
+                let after = &line[line.find("remaining")? + "remaining".len()..];
+                let digits: String = after.chars()
+                    .skip_while(|c| !c.is_ascii_digit())
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                Some((Self::provider_in_line(line)?.to_string(), digits.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Block until another Subliminal run fits in the requests-per-minute limit (0 = unlimited)
+    pub fn wait_for_request_slot(requests_per_minute: u32) {
+        if requests_per_minute == 0 {
+            return;
+        }
+        let window = Duration::from_secs(60);
+        let mut logged = false;
+        loop {
+            let wait = {
+                let mut state = STATE.lock().unwrap();
+                let now = Instant::now();
+                while state.request_times.front().is_some_and(|t| now.duration_since(*t) >= window) {
+                    state.request_times.pop_front();
+                }
+                if state.request_times.len() < requests_per_minute as usize {
+                    state.request_times.push_back(now);
+                    return;
+                }
+                window.saturating_sub(now.duration_since(state.request_times[0]))
+            };
+            if !logged {
+                crate::debug!("Request limit of {} per minute reached, waiting {:.0}s", requests_per_minute, wait.as_secs_f64());
+                logged = true;
+            }
+            std::thread::sleep(wait.min(Duration::from_secs(1)));
+        }
+    }
+
+    /// Pause a provider until its quota resets or its rate-limit cooldown ends
+    pub fn pause(provider: &str, kind: LimitKind) {
+        let until = match kind {
+            // OpenSubtitles and most others reset daily quotas at midnight UTC
+            LimitKind::Quota => Utc::now().date_naive().succ_opt()
+                .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+                .unwrap_or_else(Utc::now),
+            LimitKind::RateLimit => Utc::now() + chrono::Duration::seconds(RATE_LIMIT_COOLDOWN_SECS as i64),
+        };
+        let mut state = STATE.lock().unwrap();
+        if state.paused.get(provider).is_none_or(|(current, _)| *current < until) {
+            crate::warn!("Pausing provider {} until {} ({})", provider, until.format("%H:%M UTC"), kind.label());
+            state.paused.insert(provider.to_string(), (until, kind));
+        }
+    }
+
+    /// Record the downloads, remaining quota and limit hits of a finished run
+    pub fn record(downloaded_providers: &[String], remaining: &[(String, u32)], hits: &[ProviderLimitHit]) {
+        {
+            let mut state = STATE.lock().unwrap();
+            for provider in downloaded_providers {
+                *state.downloads.entry(provider.clone()).or_default() += 1;
+            }
+            for (provider, count) in remaining {
+                state.remaining.insert(provider.clone(), *count);
+            }
+        }
+        for hit in hits {
+            Self::pause(&hit.provider, hit.kind);
+        }
+    }
+
+    /// Paused providers among the requested ones (empty means all providers), dropping expired pauses
+    pub fn paused(requested: &[String]) -> Vec<ProviderLimitHit> {
+        let mut state = STATE.lock().unwrap();
+        let now = Utc::now();
+        state.paused.retain(|_, (until, _)| *until > now);
+        state.paused.iter()
+            .filter(|(provider, _)| requested.is_empty() || requested.contains(provider))
+            .map(|(provider, (_, kind))| ProviderLimitHit { provider: provider.clone(), kind: *kind })
+            .collect()
+    }
+
+    /// Limits and usage of every provider that was used or paused this session
+    pub fn statuses() -> Vec<ProviderLimitStatus> {
+        let state = STATE.lock().unwrap();
+        let now = Utc::now();
+        let mut providers: Vec<&String> = state.downloads.keys()
+            .chain(state.remaining.keys())
+            .chain(state.paused.keys())
+            .collect();
+        providers.sort();
+        providers.dedup();
+        providers.into_iter()
+            .map(|provider| ProviderLimitStatus {
+                provider: provider.clone(),
+                paused_until: state.paused.get(provider).copied().filter(|(until, _)| *until > now),
+                downloads: state.downloads.get(provider).copied().unwrap_or(0),
+                remaining: state.remaining.get(provider).copied(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(provider: &str, kind: LimitKind) -> ProviderLimitHit {
+        ProviderLimitHit { provider: provider.to_string(), kind }
+    }
+
+    #[test]
+    fn detects_limit_exceptions() {
+        let cases: [(&str, Vec<ProviderLimitHit>); 7] = [
+            (
+                "subliminal.providers.opensubtitles.downloadlimitreached: 407 download limit reached",
+                vec![hit("opensubtitles", LimitKind::Quota)],
+            ),
+            (
+                "error in provider 'opensubtitlescom'\ntraceback (most recent call last):\nsubliminal.exceptions.downloadlimitexceeded: daily quota",
+                vec![hit("opensubtitlescom", LimitKind::Quota)],
+            ),
+            (
+                "addic7ed raised toomanyrequests\npodnapisi: 429 client error: too many requests for url",
+                vec![hit("addic7ed", LimitKind::RateLimit), hit("podnapisi", LimitKind::RateLimit)],
+            ),
+            (
+                "opensubtitles: toomanyrequests\nopensubtitles: toomanyrequests",
+                vec![hit("opensubtitles", LimitKind::RateLimit)],
+            ),
+            // Ordinary log lines that happen to mention quotas or limits
+            ("listing subtitles with provider podnapisi (quota of 40 per day)", vec![]),
+            ("gestdown: no download limit, rate limit or daily limit applies", vec![]),
+            ("downloadlimitexceeded raised before any provider was used", vec![]),
+        ];
+        for (output, expected) in cases {
+            assert_eq!(ProviderLimits::detect(output), expected, "{}", output);
+        }
+    }
+
+    #[test]
+    fn detects_remaining_downloads() {
+        let output = "opensubtitlescom: 17 downloads remaining\nopensubtitles: remaining downloads: 3\nno count here";
+        assert_eq!(ProviderLimits::detect_remaining(output), vec![("opensubtitles".to_string(), 3)]);
+    }
+}
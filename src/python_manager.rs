@@ -5,7 +5,6 @@
 
 use std::env;
 use std::io;
-use std::io::Write;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use log::{info, warn, error};
@@ -21,6 +20,8 @@ use crate::PYTHON_INSTALLER_URL;
 #[cfg(windows)]
 use std::fs::File;
 #[cfg(windows)]
+use std::io::Write;
+#[cfg(windows)]
 use std::ptr::null_mut;
 #[cfg(windows)]
 use winreg::enums::*;
@@ -31,10 +32,6 @@ use windows::Win32::Foundation::{WPARAM, LPARAM};
 #[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{SendMessageTimeoutW, HWND_BROADCAST, WM_SETTINGCHANGE, SMTO_ABORTIFHUNG};
 
-// Unix-specific imports (Linux and macOS)
-#[cfg(any(target_os = "linux", target_os = "macos"))]
-use dirs;
-
 /// Python and Subliminal installation and management utilities
 pub struct PythonManager;
 
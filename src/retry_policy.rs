@@ -0,0 +1,173 @@
+//! Retry policy for failed download jobs
+//!
+//! Failures are classified as transient (network trouble, Subliminal's DBM
+//! cache error), provider limits (handled by deferring the job) or permanent
+//! (nothing found, cancelled). Subliminal reports most network trouble only in
+//! its log and then finds nothing, so its output is checked as well as the job
+//! status. Transient failures are retried with exponential backoff and jitter
+//! so that parallel jobs don't all retry at the same moment.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::MAX_RETRY_BACKOFF_SECS;
+use crate::data_structures::JobStatus;
+use crate::settings::RetrySettings;
+
+/// Lowercase fragments of failure messages that are worth retrying
+const TRANSIENT_MARKERS: &[&str] = &[
+    "dbm cache error", "try again later", "timed out", "timeout", "connection", "network",
+    "temporarily", "http error", "502", "503", "504",
+];
+
+/// Lowercase fragments of Subliminal's output (exception names, and its
+/// "Provider x: timeout" style messages) that show a provider had network or
+/// server trouble
+const TRANSIENT_OUTPUT_MARKERS: &[&str] = &[
+    "connectionerror", "connecttimeout", "readtimeout", "max retries exceeded",
+    "temporary failure in name resolution", "server error:", "http error 50", ": timeout",
+    ": service unavailable", "serviceunavailable",
+];
+
+/// Why a job failed, deciding whether it is retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureKind {
+    /// May succeed if tried again shortly
+    Transient,
+    /// A provider quota or rate limit; the job is deferred instead
+    ProviderLimit,
+    /// The providers answered but had no subtitles; not retried
+    NotFound,
+    /// Trying again won't help
+    Permanent,
+}
+
+/// When and how often failed jobs are retried
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per job including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_settings(settings: &RetrySettings) -> Self {
+        Self {
+            max_attempts: settings.max_attempts.max(1),
+            base_delay: Duration::from_secs(u64::from(settings.base_delay_secs)),
+        }
+    }
+
+    /// Classify a finished job from its status and the downloader's lowercased
+    /// output, or None if the job didn't fail
+    pub fn classify(status: &JobStatus, output: &str) -> Option<FailureKind> {
+        match status {
+            JobStatus::Deferred(_) => Some(FailureKind::ProviderLimit),
+            JobStatus::Failed(message) if message == "Cancelled" => Some(FailureKind::Permanent),
+            JobStatus::Failed(message) => {
+                let lower = message.to_lowercase();
+                let transient = TRANSIENT_MARKERS.iter().any(|marker| lower.contains(marker))
+                    || TRANSIENT_OUTPUT_MARKERS.iter().any(|marker| output.contains(marker));
+                Some(if transient {
+                    FailureKind::Transient
+                } else if message.starts_with("No subtitles found") {
+                    FailureKind::NotFound
+                } else {
+                    FailureKind::Permanent
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a job that just finished its `attempt`-th attempt (1-based) should run again
+    pub fn should_retry(&self, kind: FailureKind, attempt: u32) -> bool {
+        kind == FailureKind::Transient && attempt < self.max_attempts
+    }
+
+    /// Delay before the attempt after `attempt`: the base delay doubled per
+    /// earlier retry, capped, with between half and all of it used at random
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let backoff = self.base_delay
+            .saturating_mul(1 << exponent)
+            .min(Duration::from_secs(MAX_RETRY_BACKOFF_SECS));
+        backoff.mul_f64(0.5 + Self::jitter() * 0.5)
+    }
+
+    /// Random fraction in [0, 1) without pulling in a random number crate
+    fn jitter() -> f64 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+        (hasher.finish() % 1_000_000) as f64 / 1_000_000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(message: &str) -> JobStatus {
+        JobStatus::Failed(message.to_string())
+    }
+
+    #[test]
+    fn classifies_from_status_and_output() {
+        let cases = [
+            (JobStatus::Success, "", None),
+            (JobStatus::Muxed, "readtimeout", None),
+            (JobStatus::Deferred("quota".to_string()), "", Some(FailureKind::ProviderLimit)),
+            (failed("DBM cache error - try again later"), "", Some(FailureKind::Transient)),
+            (failed("HTTP error: connection refused"), "", Some(FailureKind::Transient)),
+            (failed("No subtitles found online"), "downloaded 0 subtitle", Some(FailureKind::NotFound)),
+            (
+                failed("No subtitles found online"),
+                "requests.exceptions.connectionerror: max retries exceeded with url",
+                Some(FailureKind::Transient),
+            ),
+            (failed("Subliminal error: see log"), "provider 'podnapisi': timeout", Some(FailureKind::Transient)),
+            (failed("Subliminal error: see log"), "503 server error: service unavailable for url", Some(FailureKind::Transient)),
+            (failed("Subliminal error: see log"), "provider 'gestdown': http error 502", Some(FailureKind::Transient)),
+            (failed("Subliminal error: see log"), "404 client error: not found for url", Some(FailureKind::Permanent)),
+            (failed("Subliminal error: see log"), "valueerror: bad video", Some(FailureKind::Permanent)),
+            (failed("Cancelled"), "readtimeout", Some(FailureKind::Permanent)),
+        ];
+        for (status, output, expected) in cases {
+            assert_eq!(RetryPolicy::classify(&status, output), expected, "{:?} / {}", status, output);
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_failures_within_the_attempt_limit() {
+        let policy = RetryPolicy { max_attempts: 3, base_delay: Duration::from_secs(10) };
+        let cases = [
+            (FailureKind::Transient, 1, true),
+            (FailureKind::Transient, 2, true),
+            (FailureKind::Transient, 3, false),
+            (FailureKind::ProviderLimit, 1, false),
+            (FailureKind::NotFound, 1, false),
+            (FailureKind::Permanent, 1, false),
+        ];
+        for (kind, attempt, expected) in cases {
+            assert_eq!(policy.should_retry(kind, attempt), expected, "{:?} attempt {}", kind, attempt);
+        }
+        let single = RetryPolicy::from_settings(&RetrySettings { max_attempts: 0, ..RetrySettings::default() });
+        assert!(!single.should_retry(FailureKind::Transient, 1));
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter_and_a_cap() {
+        let policy = RetryPolicy { max_attempts: 10, base_delay: Duration::from_secs(10) };
+        let cap = Duration::from_secs(MAX_RETRY_BACKOFF_SECS);
+        for attempt in 1..=12 {
+            let full = Duration::from_secs(10).saturating_mul(1 << (attempt - 1)).min(cap);
+            for _ in 0..20 {
+                let delay = policy.delay(attempt);
+                assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?} outside {:?}..={:?}", attempt, delay, full / 2, full);
+            }
+        }
+        let none = RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO };
+        assert_eq!(none.delay(5), Duration::ZERO);
+    }
+}
@@ -0,0 +1,177 @@
+//! Removal of hearing-impaired (SDH) annotations from subtitles
+//!
+//! This module strips sound descriptions such as `[MUSIC]` and
+//! `(door slams)`, music-only lines and `JOHN:` speaker labels, and writes a
+//! clean variant next to the original file. Parentheses are only treated as
+//! descriptions when written in capitals or when they open a line with a short
+//! phrase, so dialogue such as "I said (quietly, mind you) no." is kept.
+
+use std::path::{Path, PathBuf};
+
+use crate::subtitle_model::{Cue, Subtitle, SubtitleFormat};
+
+/// Hearing-impaired annotation removal
+pub struct SdhStripper;
+
+impl SdhStripper {
+    /// Strip SDH annotations from a single cue text, returning the cleaned text
+    pub fn strip_text(text: &str) -> String {
+        let without_descriptions = Self::remove_enclosed(text);
+        let mut lines = Vec::new();
+        for line in without_descriptions.lines() {
+            let line = Self::remove_speaker_label(&line.split_whitespace().collect::<Vec<_>>().join(" "));
+            // Drop lines that only contain music symbols or dialogue dashes
+            if line.chars().all(|c| c.is_whitespace() || matches!(c, '♪' | '♫' | '#' | '-' | '–')) {
+                continue;
+            }
+            lines.push(line);
+        }
+        // A single remaining line doesn't need a dialogue dash
+        if lines.len() == 1 {
+            let single = lines[0].trim_start_matches(['-', '–']).trim_start().to_string();
+            lines[0] = single;
+        }
+        lines.join("\n")
+    }
+
+    /// Whether an enclosed span is a sound description: anything in [brackets],
+    /// a span in capitals, or a parenthesized phrase without sentence punctuation
+    /// that opens its line and either fills it or is at most three words long
+    fn is_description(open: char, content: &str, opens_line: bool, fills_line: bool) -> bool {
+        let letters: Vec<char> = content.chars().filter(|c| c.is_alphabetic()).collect();
+        if open == '[' || (!letters.is_empty() && letters.iter().all(|c| c.is_uppercase())) {
+            return true;
+        }
+        let sentence = content.trim_end().ends_with(['.', '?', '!']);
+        opens_line && !sentence && (fills_line || content.split_whitespace().count() <= 3)
+    }
+
+    /// Remove [bracketed] and (parenthesized) sound descriptions, keeping
+    /// parentheses that are part of the dialogue
+    fn remove_enclosed(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(['[', '(']) {
+            // Find the matching closing bracket, allowing nested ones
+            let mut depth = 0usize;
+            let end = rest[start..].char_indices().find_map(|(i, c)| {
+                match c {
+                    '[' | '(' => depth += 1,
+                    ']' | ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(start + i);
+                        }
+                    }
+                    _ => {}
+                }
+                None
+            });
+            let Some(end) = end else {
+                break;
+            };
+            let (before, content, after) = (&rest[..start], &rest[start + 1..end], &rest[end + 1..]);
+            out.push_str(before);
+            let line_start = &out[out.rfind('\n').map_or(0, |i| i + 1)..];
+            let opens_line = line_start.trim_start().trim_start_matches(['-', '–']).trim().is_empty();
+            let fills_line = opens_line && after.split('\n').next().is_some_and(|tail| tail.trim().is_empty());
+            let open = rest[start..].chars().next().unwrap_or('(');
+            if !Self::is_description(open, content, opens_line, fills_line) {
+                out.push_str(&rest[start..=end]);
+            }
+            rest = after;
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Remove a leading "JOHN:" or "- MAN 2:" style speaker label
+    fn remove_speaker_label(line: &str) -> String {
+        let (dash, rest) = match line.strip_prefix('-').or_else(|| line.strip_prefix('–')) {
+            Some(rest) => ("- ", rest.trim_start()),
+            None => ("", line),
+        };
+        if let Some((label, text)) = rest.split_once(':') {
+            let is_label = label.chars().any(|c| c.is_alphabetic())
+                && label.chars().all(|c| c.is_uppercase() || c.is_ascii_digit() || matches!(c, ' ' | '.' | '\'' | '-'))
+                && label.chars().count() <= 30;
+            if is_label {
+                return format!("{}{}", dash, text.trim_start());
+            }
+        }
+        line.to_string()
+    }
+
+    /// Strip SDH annotations from every cue, dropping cues that become empty
+    pub fn strip(subtitle: &Subtitle) -> Subtitle {
+        let cues = subtitle.cues.iter()
+            .map(|cue| Cue { text: Self::strip_text(&cue.text), ..cue.clone() })
+            .filter(|cue| !cue.text.trim().is_empty())
+            .collect();
+        Subtitle { cues }
+    }
+
+    /// Path of the original SDH subtitle once moved aside (video.en.srt -> video.en.sdh.srt)
+    pub fn sdh_path(subtitle_path: &Path) -> Option<PathBuf> {
+        let stem = subtitle_path.file_stem()?.to_str()?;
+        let ext = subtitle_path.extension()?.to_str()?;
+        Some(subtitle_path.with_file_name(format!("{}.sdh.{}", stem, ext)))
+    }
+
+    /// Write an SDH-free variant of an SRT file, keeping the original as `.sdh.srt`.
+    /// Returns the path of the moved original if anything was stripped.
+    pub fn process_file(subtitle_path: &Path) -> Result<Option<PathBuf>, String> {
+        if SubtitleFormat::from_path(subtitle_path) != Some(SubtitleFormat::Srt) {
+            return Ok(None);
+        }
+        let original = Subtitle::load(subtitle_path)?;
+        let stripped = Self::strip(&original);
+        if stripped.cues == original.cues {
+            return Ok(None);
+        }
+        let sdh_path = Self::sdh_path(subtitle_path)
+            .ok_or_else(|| format!("Invalid subtitle path: {}", subtitle_path.display()))?;
+        std::fs::rename(subtitle_path, &sdh_path)
+            .map_err(|e| format!("Failed to move {} to {}: {}", subtitle_path.display(), sdh_path.display(), e))?;
+        std::fs::write(subtitle_path, stripped.to_srt())
+            .map_err(|e| format!("Failed to write {}: {}", subtitle_path.display(), e))?;
+        crate::info!("Wrote SDH-free subtitle {} (original kept as {})", subtitle_path.display(), sdh_path.display());
+        Ok(Some(sdh_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_descriptions_and_labels_but_keeps_dialogue() {
+        let cases = [
+            // Speaker labels
+            ("JOHN: Get down!", "Get down!"),
+            ("- MAN 2: Over here.\n- WOMAN: Where?", "- Over here.\n- Where?"),
+            ("Note: this isn't a label.", "Note: this isn't a label."),
+            // Music cues
+            ("♪ ♪", ""),
+            ("[MUSIC PLAYING]", ""),
+            ("♪ la la la ♪", "♪ la la la ♪"),
+            ("# #\nHello.", "Hello."),
+            // Sound descriptions, in any case
+            ("(door slams)", ""),
+            ("(Door Slams)\nWho's there?", "Who's there?"),
+            ("(sighs) I know.", "I know."),
+            ("[Laughs] Very funny.", "Very funny."),
+            ("- (GASPS)\n- It's you!", "It's you!"),
+            ("Stop (SHOUTING) now.", "Stop now."),
+            ("(man on radio speaking indistinctly)", ""),
+            // Parentheses that are dialogue
+            ("I said (quietly, mind you) no.", "I said (quietly, mind you) no."),
+            ("(I hope she didn't notice.)", "(I hope she didn't notice.)"),
+            ("It costs ten (10) dollars.", "It costs ten (10) dollars."),
+            ("Unclosed (bracket here", "Unclosed (bracket here"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(SdhStripper::strip_text(text), expected, "{:?}", text);
+        }
+    }
+}
@@ -3,32 +3,451 @@
 //! This module handles loading, saving, and managing user preferences
 //! and application settings that persist between sessions.
 
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
-use crate::config::DEFAULT_CONCURRENT_DOWNLOADS;
+use crate::config::{
+    DEFAULT_CONCURRENT_DOWNLOADS, DEFAULT_MIN_SCORE_EPISODE, DEFAULT_MIN_SCORE_MOVIE, DEFAULT_REQUESTS_PER_MINUTE,
+    DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_DELAY_SECS, DEFAULT_SCAN_THREADS, MAX_AGE_FILTER_DAYS, MAX_REQUESTS_PER_MINUTE,
+    MAX_RETRY_ATTEMPTS, MAX_SCAN_THREADS, OPENSUBTITLES_API_URL, SUBLIMINAL_PROVIDERS,
+};
+use crate::helper_functions::Validation;
 
 /// Application settings that persist between sessions
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub selected_languages: Vec<String>,
+    /// Fallback languages tried in order when a selected language isn't found
+    pub language_fallbacks: BTreeMap<String, Vec<String>>,
+    /// Languages to also fetch forced (foreign parts only) subtitles for
+    pub forced_languages: Vec<String>,
     pub force_download: bool,
     pub overwrite_existing: bool,
     pub concurrent_downloads: usize,
     pub ignore_local_extras: bool,
+    /// Start downloads as soon as a scan finishes instead of showing the scan preview
+    pub auto_start_downloads: bool,
+    pub backend: BackendSettings,
+    pub providers: ProviderSettings,
+    pub retry: RetrySettings,
+    pub scan: ScanSettings,
+    pub validation: ValidationSettings,
+    pub hearing_impaired: HearingImpairedSettings,
+    pub dual_subtitles: DualSubtitleSettings,
+    pub embedded: EmbeddedSubtitleSettings,
+    pub mux: MuxSettings,
+    pub ocr: OcrSettings,
+    pub speech_to_text: SpeechToTextSettings,
+    pub translation: TranslationSettings,
+    pub audio_language: AudioLanguageSettings,
+}
+
+/// Where subtitles are downloaded from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DownloadBackend {
+    /// The Subliminal command line with all of its providers
+    #[default]
+    Subliminal,
+    /// The built-in OpenSubtitles.com client, which needs no Python
+    OpenSubtitles,
+}
+
+impl DownloadBackend {
+    pub const ALL: [DownloadBackend; 2] = [DownloadBackend::Subliminal, DownloadBackend::OpenSubtitles];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DownloadBackend::Subliminal => "Subliminal",
+            DownloadBackend::OpenSubtitles => "OpenSubtitles.com (built-in)",
+        }
+    }
+}
+
+/// Download backend choice and settings for the native OpenSubtitles.com client
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackendSettings {
+    pub backend: DownloadBackend,
+    /// Consumer API key from opensubtitles.com
+    pub opensubtitles_api_key: String,
+    /// API base URL, can point at a mock server
+    pub opensubtitles_base_url: String,
+}
+
+impl Default for BackendSettings {
+    fn default() -> Self {
+        Self {
+            backend: DownloadBackend::default(),
+            opensubtitles_api_key: String::new(),
+            opensubtitles_base_url: OPENSUBTITLES_API_URL.to_string(),
+        }
+    }
+}
+
+/// Provider selection and match filtering passed to Subliminal
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ProviderSettings {
+    /// Providers Subliminal may use
+    pub enabled_providers: Vec<String>,
+    /// Minimum score in percent for movies (0 accepts any match)
+    pub min_score_movie: u32,
+    /// Minimum score in percent for episodes (0 accepts any match)
+    pub min_score_episode: u32,
+    /// Only process videos modified within this many days (0 disables the filter)
+    pub max_age_days: u32,
+    /// Subliminal runs allowed per minute across all downloads (0 = unlimited)
+    pub requests_per_minute: u32,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            enabled_providers: SUBLIMINAL_PROVIDERS.iter().map(|p| p.to_string()).collect(),
+            min_score_movie: DEFAULT_MIN_SCORE_MOVIE,
+            min_score_episode: DEFAULT_MIN_SCORE_EPISODE,
+            max_age_days: 0,
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+        }
+    }
+}
+
+impl ProviderSettings {
+    /// Providers to pass with -p, or none when every provider is enabled
+    pub fn provider_args(&self) -> Vec<String> {
+        if SUBLIMINAL_PROVIDERS.iter().all(|p| self.enabled_providers.iter().any(|e| e == p)) {
+            Vec::new()
+        } else {
+            self.enabled_providers.clone()
+        }
+    }
+
+    /// Fix values that are out of range, e.g. from a hand-edited settings file
+    pub fn validate(&mut self) {
+        let before = self.enabled_providers.len();
+        // Keep the first occurrence of each provider, in the user's order
+        let mut seen = HashSet::new();
+        self.enabled_providers.retain(|p| Validation::is_valid_provider(p) && seen.insert(p.clone()));
+        if self.enabled_providers.len() != before {
+            crate::warn!("Removed unknown or duplicate providers from settings");
+        }
+        if self.enabled_providers.is_empty() {
+            crate::warn!("No providers enabled, enabling all providers");
+            self.enabled_providers = SUBLIMINAL_PROVIDERS.iter().map(|p| p.to_string()).collect();
+        }
+        for score in [&mut self.min_score_movie, &mut self.min_score_episode] {
+            if !Validation::is_valid_min_score(*score) {
+                crate::warn!("Invalid minimum score {}, using 100", score);
+                *score = 100;
+            }
+        }
+        if !Validation::is_valid_age_days(self.max_age_days) {
+            crate::warn!("Invalid age filter of {} days, using {}", self.max_age_days, MAX_AGE_FILTER_DAYS);
+            self.max_age_days = MAX_AGE_FILTER_DAYS;
+        }
+        if !Validation::is_valid_requests_per_minute(self.requests_per_minute) {
+            crate::warn!("Invalid request limit of {} per minute, using {}", self.requests_per_minute, MAX_REQUESTS_PER_MINUTE);
+            self.requests_per_minute = MAX_REQUESTS_PER_MINUTE;
+        }
+    }
+}
+
+/// Automatic retries of jobs that failed with a transient error
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetrySettings {
+    /// Attempts per job including the first one (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry in seconds, doubled for each further retry
+    pub base_delay_secs: u32,
+    /// Leave videos whose subtitles weren't found recently out of scans, re-checking
+    /// them after a day, a week and then a month
+    pub skip_recent_failures: bool,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_ATTEMPTS,
+            base_delay_secs: DEFAULT_RETRY_DELAY_SECS,
+            skip_recent_failures: true,
+        }
+    }
+}
+
+impl RetrySettings {
+    /// Fix values that are out of range, e.g. from a hand-edited settings file
+    pub fn validate(&mut self) {
+        if !Validation::is_valid_retry_attempts(self.max_attempts) {
+            let fixed = self.max_attempts.clamp(1, MAX_RETRY_ATTEMPTS);
+            crate::warn!("Invalid retry attempts {}, using {}", self.max_attempts, fixed);
+            self.max_attempts = fixed;
+        }
+        if !Validation::is_valid_retry_delay(self.base_delay_secs) {
+            crate::warn!("Invalid retry delay of {} seconds, using {}", self.base_delay_secs, DEFAULT_RETRY_DELAY_SECS);
+            self.base_delay_secs = DEFAULT_RETRY_DELAY_SECS;
+        }
+    }
+}
+
+/// How library folders are walked during a scan
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Directories read at the same time
+    pub threads: usize,
+    /// Walk into folders behind symbolic links (loops are visited once)
+    pub follow_symlinks: bool,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            threads: DEFAULT_SCAN_THREADS,
+            follow_symlinks: true,
+        }
+    }
+}
+
+impl ScanSettings {
+    /// Fix values that are out of range, e.g. from a hand-edited settings file
+    pub fn validate(&mut self) {
+        if !Validation::is_valid_scan_threads(self.threads) {
+            let fixed = self.threads.clamp(1, MAX_SCAN_THREADS);
+            crate::warn!("Invalid scan thread count {}, using {}", self.threads, fixed);
+            self.threads = fixed;
+        }
+    }
+}
+
+/// Settings for the sanity checks run after each download
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ValidationSettings {
+    /// Check downloaded subtitles and report problems as job warnings
+    pub enabled: bool,
+    /// Delete subtitles that fail validation and retry with another provider
+    pub retry_invalid: bool,
+}
+
+impl Default for ValidationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retry_invalid: false,
+        }
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             selected_languages: Vec::new(),
+            language_fallbacks: BTreeMap::new(),
+            forced_languages: Vec::new(),
             force_download: false,
             overwrite_existing: false,
             concurrent_downloads: DEFAULT_CONCURRENT_DOWNLOADS,
             ignore_local_extras: false,
+            auto_start_downloads: true,
+            backend: BackendSettings::default(),
+            providers: ProviderSettings::default(),
+            retry: RetrySettings::default(),
+            scan: ScanSettings::default(),
+            validation: ValidationSettings::default(),
+            hearing_impaired: HearingImpairedSettings::default(),
+            dual_subtitles: DualSubtitleSettings::default(),
+            embedded: EmbeddedSubtitleSettings::default(),
+            mux: MuxSettings::default(),
+            ocr: OcrSettings::default(),
+            speech_to_text: SpeechToTextSettings::default(),
+            translation: TranslationSettings::default(),
+            audio_language: AudioLanguageSettings::default(),
+        }
+    }
+}
+
+/// Preference for hearing-impaired (SDH) subtitles passed to Subliminal
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum HearingImpairedPreference {
+    #[default]
+    NoPreference,
+    Prefer,
+    Avoid,
+}
+
+impl HearingImpairedPreference {
+    /// All preferences in display order
+    pub const ALL: [HearingImpairedPreference; 3] = [
+        HearingImpairedPreference::NoPreference,
+        HearingImpairedPreference::Prefer,
+        HearingImpairedPreference::Avoid,
+    ];
+
+    /// Human-readable label for the settings UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            HearingImpairedPreference::NoPreference => "Don't care",
+            HearingImpairedPreference::Prefer => "Prefer SDH",
+            HearingImpairedPreference::Avoid => "Avoid SDH",
+        }
+    }
+}
+
+/// Settings for hearing-impaired subtitle handling
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct HearingImpairedSettings {
+    pub preference: HearingImpairedPreference,
+    /// Write an SDH-free copy of downloaded subtitles, keeping the original as .sdh
+    pub strip_sdh: bool,
+}
+
+/// Output format for merged dual-language subtitles
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DualSubtitleFormat {
+    /// Styled ASS with the secondary language at the top of the screen
+    #[default]
+    Ass,
+    /// Plain SRT with the secondary lines stacked under the primary ones
+    Srt,
+}
+
+/// Settings for merging two downloaded languages into one subtitle
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DualSubtitleSettings {
+    pub enabled: bool,
+    pub primary_language: String,
+    pub secondary_language: String,
+    pub format: DualSubtitleFormat,
+}
+
+/// Settings for handling subtitle tracks embedded in video containers
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EmbeddedSubtitleSettings {
+    /// Extract embedded text tracks in a selected language to sidecar files instead of downloading
+    pub extract_text_tracks: bool,
+}
+
+/// Settings for muxing subtitles into MKV containers
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MuxSettings {
+    /// Mux the job's sidecar subtitles into MKV files after downloading
+    pub enabled: bool,
+    /// Delete the sidecar files once they are muxed
+    pub delete_sidecars: bool,
+}
+
+/// Settings for converting bitmap subtitle tracks with OCR
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct OcrSettings {
+    /// OCR embedded PGS/VobSub tracks with tesseract when providers return nothing
+    pub enabled: bool,
+}
+
+/// Settings for generating subtitles from audio with a local whisper.cpp build
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SpeechToTextSettings {
+    /// Transcribe the audio when no subtitles are found anywhere (never runs unless enabled)
+    pub enabled: bool,
+    /// whisper.cpp command line binary
+    pub binary_path: String,
+    /// ggml model file passed to whisper.cpp
+    pub model_path: String,
+    pub threads: usize,
+}
+
+impl Default for SpeechToTextSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            binary_path: "whisper-cli".to_string(),
+            model_path: String::new(),
+            threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+        }
+    }
+}
+
+/// Service used to machine-translate subtitles
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum TranslationBackend {
+    /// LibreTranslate compatible HTTP endpoint
+    #[default]
+    LibreTranslate,
+    /// Local command reading one cue per line on stdin and writing one translation per line
+    Command,
+}
+
+/// Settings for translating a downloaded language into missing ones
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TranslationSettings {
+    pub enabled: bool,
+    pub backend: TranslationBackend,
+    /// Base URL of the LibreTranslate instance
+    pub endpoint: String,
+    pub api_key: String,
+    /// Command line with {source} and {target} placeholders
+    pub command: String,
+    /// Number of cues sent per request
+    pub batch_size: usize,
+}
+
+impl Default for TranslationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: TranslationBackend::LibreTranslate,
+            endpoint: "http://localhost:5000".to_string(),
+            api_key: String::new(),
+            command: String::new(),
+            batch_size: 50,
         }
     }
 }
 
+/// Rule for skipping subtitles based on the original audio language
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum AudioLanguageRule {
+    #[default]
+    Off,
+    /// Skip a language, with its fallbacks, when the original audio is in that language
+    SkipMatchingLanguages,
+    /// Only fetch subtitles when the audio is in none of the selected languages
+    OnlyForeignAudio,
+}
+
+impl AudioLanguageRule {
+    /// All rules in display order
+    pub const ALL: [AudioLanguageRule; 3] = [
+        AudioLanguageRule::Off,
+        AudioLanguageRule::SkipMatchingLanguages,
+        AudioLanguageRule::OnlyForeignAudio,
+    ];
+
+    /// Human-readable label for the settings UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioLanguageRule::Off => "Always fetch",
+            AudioLanguageRule::SkipMatchingLanguages => "Skip languages matching the audio",
+            AudioLanguageRule::OnlyForeignAudio => "Only fetch for foreign audio",
+        }
+    }
+}
+
+/// Settings for skipping subtitles based on the audio language
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AudioLanguageSettings {
+    pub rule: AudioLanguageRule,
+}
+
 impl Settings {
     /// Get the path where settings are stored
     pub fn get_path() -> std::io::Result<PathBuf> {
@@ -78,9 +497,12 @@ impl Settings {
             Ok(path) => {
                 match std::fs::read_to_string(&path) {
                     Ok(content) => {
-                        match serde_json::from_str(&content) {
-                            Ok(settings) => {
+                        match serde_json::from_str::<Settings>(&content) {
+                            Ok(mut settings) => {
                                 crate::info!("Settings loaded from {}", path.display());
+                                settings.providers.validate();
+                                settings.retry.validate();
+                                settings.scan.validate();
                                 settings
                             }
                             Err(e) => {
@@ -112,4 +534,33 @@ impl Settings {
         crate::debug!("Settings saved to {}", path.display());
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_unknown_and_duplicate_providers_in_order() {
+        let cases: [(&[&str], &[&str]); 4] = [
+            (&["podnapisi", "gestdown", "podnapisi"], &["podnapisi", "gestdown"]),
+            (&["gestdown", "podnapisi", "gestdown", "podnapisi"], &["gestdown", "podnapisi"]),
+            (&["nope", "podnapisi", "nope"], &["podnapisi"]),
+            (&["podnapisi"], &["podnapisi"]),
+        ];
+        for (providers, expected) in cases {
+            let mut settings = ProviderSettings {
+                enabled_providers: providers.iter().map(|p| p.to_string()).collect(),
+                ..ProviderSettings::default()
+            };
+            settings.validate();
+            assert_eq!(settings.enabled_providers, expected, "{:?}", providers);
+        }
+    }
+
+    #[test]
+    fn enables_every_provider_when_none_are_valid() {
+        let mut settings = ProviderSettings { enabled_providers: vec!["nope".to_string()], ..ProviderSettings::default() };
+        settings.validate();
+        assert_eq!(settings.enabled_providers.len(), SUBLIMINAL_PROVIDERS.len());
+    }
+}
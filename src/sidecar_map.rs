@@ -0,0 +1,220 @@
+//! Subtitle sidecars of a folder, from a single directory listing
+//!
+//! Checking a video for subtitles used to probe the disk once per language
+//! and extension (`video.en.srt`, `video.en.sub`, ...), which adds up to dozens
+//! of round trips per video on SMB and NFS shares. A `SidecarMap` lists the
+//! folder once and indexes every subtitle file by each dotted prefix of its
+//! name, so `video.en.srt` can be looked up by the stem `video` (or by
+//! `video.en` for a video of that name) without touching the disk again.
+//! Names are matched case-insensitively.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::SUBTITLE_EXTENSIONS;
+use crate::translation::MACHINE_TRANSLATED_TAG;
+
+#[cfg(test)]
+thread_local! {
+    /// Folders listed by the current thread, so tests can count disk round trips
+    pub(crate) static LISTINGS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Subtitle files of a folder by video stem
+#[derive(Clone, Debug, Default)]
+pub struct SidecarMap {
+    folder: PathBuf,
+    /// Lowercase stem → file names of the subtitles starting with "{stem}."
+    by_stem: HashMap<String, Vec<String>>,
+}
+
+impl SidecarMap {
+    /// Check if a file name has a subtitle extension
+    pub fn is_subtitle_name(name: &str) -> bool {
+        Path::new(name).extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| SUBTITLE_EXTENSIONS.iter().any(|s| s.eq_ignore_ascii_case(ext)))
+    }
+
+    /// Build the map from file names already listed from the folder
+    pub fn from_names<I: IntoIterator<Item = String>>(folder: &Path, names: I) -> Self {
+        let mut by_stem: HashMap<String, Vec<String>> = HashMap::new();
+        for name in names.into_iter().filter(|name| Self::is_subtitle_name(name)) {
+            let lower = name.to_lowercase();
+            // Every prefix before a dot may be the stem of a video ("a.b.en.srt" → "a", "a.b", "a.b.en")
+            for (i, _) in lower.match_indices('.') {
+                by_stem.entry(lower[..i].to_string()).or_default().push(name.clone());
+            }
+        }
+        Self { folder: folder.to_path_buf(), by_stem }
+    }
+
+    /// List a folder once and map its subtitle files
+    pub fn read(folder: &Path) -> std::io::Result<Self> {
+        let listed = if folder.as_os_str().is_empty() { Path::new(".") } else { folder };
+        #[cfg(test)]
+        LISTINGS.with(|count| count.set(count.get() + 1));
+        let names = std::fs::read_dir(listed)?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string());
+        Ok(Self::from_names(folder, names))
+    }
+
+    /// Map of the folder of a video, empty when the folder can't be listed
+    pub fn for_video(video_path: &Path) -> Self {
+        let folder = video_path.parent().unwrap_or_else(|| Path::new(""));
+        Self::read(folder).unwrap_or_else(|e| {
+            crate::debug!("Failed to list {}: {}", folder.display(), e);
+            Self { folder: folder.to_path_buf(), by_stem: HashMap::new() }
+        })
+    }
+
+    /// Language part of a sidecar name for a video stem, lowercased ("en" for
+    /// video.en.srt, "en.forced" for video.en.forced.srt, empty for video.srt).
+    /// Machine translations count for their language ("fr" for
+    /// video.fr.machine-translated.srt).
+    pub fn language(stem: &str, sidecar: &str) -> Option<String> {
+        let name = sidecar.to_lowercase();
+        let rest = name.strip_prefix(&stem.to_lowercase())?.strip_prefix('.')?;
+        let (lang, ext) = rest.rsplit_once('.').unwrap_or(("", rest));
+        let lang = lang.strip_suffix(MACHINE_TRANSLATED_TAG)
+            .and_then(|l| l.strip_suffix('.'))
+            .unwrap_or(lang);
+        SUBTITLE_EXTENSIONS.contains(&ext).then(|| lang.to_string())
+    }
+
+    /// Whether a sidecar name is a machine translation
+    pub fn is_machine_translated(name: &str) -> bool {
+        Path::new(name).file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.to_lowercase().ends_with(&format!(".{}", MACHINE_TRANSLATED_TAG)))
+    }
+
+    /// File names of the subtitles of a video stem (e.g. "video.en.srt" for "video")
+    pub fn sidecars(&self, stem: &str) -> &[String] {
+        self.by_stem.get(&stem.to_lowercase()).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Subtitles of a stem in a language ("" for subtitles without one), in the
+    /// order of `SUBTITLE_EXTENSIONS` with machine translations last
+    pub fn find_all(&self, stem: &str, lang: &str) -> Vec<PathBuf> {
+        let lang = lang.to_lowercase();
+        let mut matches: Vec<&String> = self.sidecars(stem).iter()
+            .filter(|name| Self::language(stem, name).is_some_and(|l| l == lang))
+            .collect();
+        matches.sort_by_key(|name| {
+            let ext = Path::new(name.as_str()).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+            (Self::is_machine_translated(name), SUBTITLE_EXTENSIONS.iter().position(|s| *s == ext))
+        });
+        matches.into_iter().map(|name| self.folder.join(name)).collect()
+    }
+
+    /// Preferred subtitle of a stem in a language ("" for subtitles without one)
+    pub fn find(&self, stem: &str, lang: &str) -> Option<PathBuf> {
+        self.find_all(stem, lang).into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_sidecar_languages() {
+        let cases = [
+            ("video", "video.en.srt", Some("en")),
+            ("video", "Video.EN.SRT", Some("en")),
+            ("video", "video.srt", Some("")),
+            ("video", "video.en.forced.srt", Some("en.forced")),
+            ("video", "video.fr.machine-translated.srt", Some("fr")),
+            ("video", "video.pt-br.machine-translated.srt", Some("pt-br")),
+            ("video", "video.en.txt", None),
+            ("video", "other.en.srt", None),
+            ("video", "videos.en.srt", None),
+        ];
+        for (stem, sidecar, expected) in cases {
+            assert_eq!(SidecarMap::language(stem, sidecar).as_deref(), expected, "{}", sidecar);
+        }
+    }
+
+    #[test]
+    fn finds_sidecars_by_stem_and_language() {
+        let names = ["a.en.srt", "a.b.en.ass", "a.b.en.srt", "a.b.fr.machine-translated.srt", "a.b.fr.srt", "a.b.mkv"];
+        let map = SidecarMap::from_names(Path::new("dir"), names.iter().map(|n| n.to_string()));
+        assert_eq!(map.sidecars("a").len(), 5);
+        assert_eq!(map.find("a.b", "en"), Some(PathBuf::from("dir/a.b.en.srt")));
+        assert_eq!(map.find_all("a.b", "fr"), vec![
+            PathBuf::from("dir/a.b.fr.srt"),
+            PathBuf::from("dir/a.b.fr.machine-translated.srt"),
+        ]);
+        assert_eq!(map.find("a.b", "de"), None);
+    }
+
+    #[test]
+    fn one_listing_per_folder_replaces_a_probe_per_language_and_extension() {
+        let root = std::env::temp_dir().join(format!("rustitles-sidecars-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (folders, videos_per_folder) = (10, 50);
+        let mut videos = Vec::new();
+        for season in 1..=folders {
+            let folder = root.join(format!("Season {}", season));
+            std::fs::create_dir_all(&folder).unwrap();
+            for episode in 1..=videos_per_folder {
+                let stem = format!("Show.S{:02}E{:02}", season, episode);
+                std::fs::write(folder.join(format!("{}.mkv", stem)), "").unwrap();
+                // Every other episode has English, every third a Spanish .ass as well
+                if episode % 2 == 0 {
+                    std::fs::write(folder.join(format!("{}.en.srt", stem)), "").unwrap();
+                }
+                if episode % 3 == 0 {
+                    std::fs::write(folder.join(format!("{}.es.ass", stem)), "").unwrap();
+                }
+                videos.push(folder.join(format!("{}.mkv", stem)));
+            }
+        }
+        // Three wanted languages with fallbacks, plus the generic video.srt
+        let langs = ["pt-br", "pt", "es", "en", "nl", "de", ""];
+
+        // How subtitles were found before: one exists() per language and extension
+        let mut probes = 0;
+        let started = std::time::Instant::now();
+        let probed: Vec<Vec<PathBuf>> = videos.iter()
+            .map(|video| {
+                let stem = video.file_stem().unwrap().to_string_lossy().to_string();
+                langs.iter()
+                    .filter_map(|lang| SUBTITLE_EXTENSIONS.iter().find_map(|ext| {
+                        probes += 1;
+                        let name = if lang.is_empty() { format!("{}.{}", stem, ext) } else { format!("{}.{}.{}", stem, lang, ext) };
+                        let path = video.with_file_name(name);
+                        path.exists().then_some(path)
+                    }))
+                    .collect()
+            })
+            .collect();
+        let probing = started.elapsed();
+
+        LISTINGS.with(|count| count.set(0));
+        let started = std::time::Instant::now();
+        let mut maps: HashMap<PathBuf, SidecarMap> = HashMap::new();
+        let mapped: Vec<Vec<PathBuf>> = videos.iter()
+            .map(|video| {
+                let folder = video.parent().unwrap().to_path_buf();
+                let map = maps.entry(folder.clone()).or_insert_with(|| SidecarMap::read(&folder).unwrap());
+                let stem = video.file_stem().unwrap().to_string_lossy().to_string();
+                langs.iter().filter_map(|lang| map.find(&stem, lang)).collect()
+            })
+            .collect();
+        let listing = started.elapsed();
+        let listings = LISTINGS.with(|count| count.get());
+
+        eprintln!("{} videos in {} folders: {} exists() probes took {:?}, {} listings took {:?}",
+            videos.len(), folders, probes, probing, listings, listing);
+        assert_eq!(mapped, probed);
+        assert_eq!(listings, folders);
+        // 35 probes per video, less the extensions after .srt for the 250 English
+        // subtitles and after .ass for the 160 Spanish ones
+        assert_eq!(probes, 500 * 35 - 250 * 4 - 160);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
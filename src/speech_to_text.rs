@@ -0,0 +1,98 @@
+//! Speech-to-text subtitle generation with whisper.cpp
+//!
+//! This module is a last-resort fallback for videos no provider has subtitles
+//! for. It extracts the matching audio track with ffmpeg as 16 kHz mono WAV,
+//! which whisper.cpp requires, and runs the user-configured whisper.cpp
+//! binary to write `{stem}.{lang}.srt`.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::Ffmpeg;
+use crate::helper_functions::Utils;
+use crate::media_probe::{MediaProbe, MediaStream};
+use crate::settings::SpeechToTextSettings;
+use crate::subtitle_utils::SubtitleUtils;
+
+/// Subtitle generation from a video's audio track
+pub struct SpeechToText;
+
+impl SpeechToText {
+    /// Pick the audio track spoken in a language. A single untagged track is
+    /// assumed to be in the requested language.
+    fn pick_audio_track<'a>(streams: &'a [MediaStream], lang: &str) -> Option<&'a MediaStream> {
+        let mut matching: Vec<&MediaStream> = streams.iter()
+            .filter(|s| s.language.as_deref().is_some_and(|l| SubtitleUtils::language_matches(l, lang)))
+            .collect();
+        matching.sort_by_key(|s| !s.default);
+        match (matching.first(), streams) {
+            (Some(stream), _) => Some(stream),
+            (None, [only]) if only.language.as_deref().is_none_or(|l| l == "und") => Some(only),
+            _ => None,
+        }
+    }
+
+    /// Check that the configured binary and model exist before doing any work
+    fn check_settings(settings: &SpeechToTextSettings) -> Result<(), String> {
+        if settings.binary_path.trim().is_empty() {
+            return Err("no whisper.cpp binary configured".to_string());
+        }
+        if settings.model_path.trim().is_empty() {
+            return Err("no whisper model configured".to_string());
+        }
+        if !Path::new(&settings.model_path).is_file() {
+            return Err(format!("whisper model not found: {}", settings.model_path));
+        }
+        Ok(())
+    }
+
+    /// Transcribe the audio track in a language to {stem}.{lang}.srt
+    pub fn transcribe(video_path: &Path, lang: &str, settings: &SpeechToTextSettings) -> Result<PathBuf, String> {
+        Self::check_settings(settings)?;
+        let streams = MediaProbe::streams(video_path, "a");
+        let stream = Self::pick_audio_track(&streams, lang)
+            .ok_or_else(|| format!("no {} audio track", SubtitleUtils::language_code_to_name(lang)))?;
+        let folder = video_path.parent().ok_or("invalid video path")?;
+        let stem = video_path.file_stem().and_then(|s| s.to_str()).ok_or("invalid video file name")?;
+        // whisper.cpp appends .srt to the output base name itself
+        let output_base = folder.join(format!("{}.{}", stem, lang));
+        let output = folder.join(format!("{}.{}.srt", stem, lang));
+        let wav_path = std::env::temp_dir().join(format!("rustitles-stt-{}-{}.wav", std::process::id(), Utils::get_file_name(&output)));
+
+        let args: Vec<OsString> = vec![
+            "-i".into(), video_path.into(),
+            "-map".into(), format!("0:{}", stream.index).into(),
+            "-vn".into(), "-ac".into(), "1".into(), "-ar".into(), "16000".into(),
+            "-c:a".into(), "pcm_s16le".into(),
+            wav_path.clone().into(),
+        ];
+        crate::info!("Extracting audio track {} of {} for speech recognition", stream.index, video_path.display());
+        if let Err(e) = Ffmpeg::run(&args) {
+            let _ = std::fs::remove_file(&wav_path);
+            return Err(e);
+        }
+
+        // whisper only takes the base language code
+        let whisper_lang = lang.split('-').next().unwrap_or(lang);
+        crate::info!("Running whisper.cpp ({} threads) on {}", settings.threads, video_path.display());
+        let result = Utils::hidden_command(&settings.binary_path)
+            .arg("-m").arg(&settings.model_path)
+            .arg("-t").arg(settings.threads.max(1).to_string())
+            .arg("-l").arg(whisper_lang)
+            .arg("-osrt")
+            .arg("-of").arg(&output_base)
+            .arg("-f").arg(&wav_path)
+            .output();
+        let _ = std::fs::remove_file(&wav_path);
+        let output_result = result.map_err(|e| format!("Failed to run {}: {}", settings.binary_path, e))?;
+        if !output_result.status.success() {
+            let _ = std::fs::remove_file(&output);
+            return Err(format!("whisper.cpp failed: {}", String::from_utf8_lossy(&output_result.stderr).trim()));
+        }
+        if !output.exists() {
+            return Err("whisper.cpp did not write a subtitle".to_string());
+        }
+        crate::info!("Generated machine-transcribed subtitle {}", output.display());
+        Ok(output)
+    }
+}
@@ -0,0 +1,375 @@
+//! Subliminal command line invocation and output parsing
+//!
+//! This module builds the arguments and environment for `subliminal download`,
+//! runs it with the available Python launchers as fallbacks and extracts the
+//! per-language provider and score from its verbose output.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::{ACCOUNT_PROVIDERS, SUBLIMINAL_PROVIDERS};
+use crate::helper_functions::Validation;
+use crate::provider_accounts::ProviderAccount;
+use crate::provider_limits::{ProviderLimitHit, ProviderLimits};
+use crate::python_manager::PythonManager;
+use crate::settings::HearingImpairedPreference;
+use crate::debug;
+
+/// Options for a single `subliminal download` invocation
+#[derive(Clone, Debug, Default)]
+pub struct SubliminalOptions {
+    pub languages: Vec<String>,
+    pub force: bool,
+    /// Restrict the search to these providers (empty means Subliminal's defaults)
+    pub providers: Vec<String>,
+    pub hearing_impaired: HearingImpairedPreference,
+    /// Only look for forced (foreign parts only) subtitles
+    pub foreign_only: bool,
+    /// Minimum score in percent
+    pub min_score: Option<u32>,
+    /// Only process videos newer than this many days
+    pub max_age_days: Option<u32>,
+    /// Provider logins as (provider, account)
+    pub accounts: Vec<(String, ProviderAccount)>,
+    /// Global limit on Subliminal runs per minute (0 = unlimited)
+    pub requests_per_minute: u32,
+    /// Write the subtitles to this folder instead of next to the video
+    pub directory: Option<PathBuf>,
+}
+
+/// A subtitle reported as downloaded in Subliminal's verbose output
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadedSubtitle {
+    pub language: String,
+    pub provider: String,
+    pub score: Option<f64>,
+}
+
+/// Parsed summary of a Subliminal run
+#[derive(Clone, Debug, Default)]
+pub struct SubliminalReport {
+    /// Lowercased stdout and stderr, used for phrase matching
+    pub combined_output: String,
+    pub downloaded: Vec<DownloadedSubtitle>,
+    /// Providers that hit a quota or rate limit, or were skipped while paused
+    pub limit_hits: Vec<ProviderLimitHit>,
+}
+
+impl SubliminalReport {
+    /// Parse the output of a Subliminal run
+    pub fn from_output(output: &Output) -> Self {
+        let stdout_str = String::from_utf8_lossy(&output.stdout).to_lowercase();
+        let stderr_str = String::from_utf8_lossy(&output.stderr).to_lowercase();
+        let combined_output = format!("{}\n{}", stdout_str, stderr_str).trim().to_string();
+        let downloaded = combined_output.lines().filter_map(Self::parse_subtitle_line).collect();
+        let limit_hits = ProviderLimits::detect(&combined_output);
+        SubliminalReport { combined_output, downloaded, limit_hits }
+    }
+
+    /// Parse a "- [score] language subtitle from provider (match on ...)" line
+    fn parse_subtitle_line(line: &str) -> Option<DownloadedSubtitle> {
+        let rest = line.trim().strip_prefix("- [")?;
+        let (score, rest) = rest.split_once(']')?;
+        let (language, rest) = rest.trim().split_once(" subtitle from ")?;
+        let provider = rest.split_whitespace().next()?;
+        Some(DownloadedSubtitle {
+            language: language.trim().to_string(),
+            provider: provider.to_string(),
+            score: score.trim().trim_end_matches('%').parse().ok(),
+        })
+    }
+
+    /// Whether Subliminal reported that nothing was downloaded
+    pub fn downloaded_nothing(&self) -> bool {
+        // "Downloaded 0 subtitle" at normal verbosity, "0 subtitle downloaded for" when verbose.
+        // No output at all means Subliminal wasn't run because every provider was paused.
+        self.combined_output.is_empty()
+            || self.combined_output.contains("downloaded 0 subtitle")
+            || self.combined_output.lines().any(|l| l.trim().starts_with("0 subtitle downloaded"))
+    }
+
+    /// Append the output of a later Subliminal run for the same video
+    pub fn merge(&mut self, other: SubliminalReport) {
+        if !self.combined_output.is_empty() {
+            self.combined_output.push('\n');
+        }
+        self.combined_output.push_str(&other.combined_output);
+        self.downloaded.extend(other.downloaded);
+        for hit in other.limit_hits {
+            if !self.limit_hits.contains(&hit) {
+                self.limit_hits.push(hit);
+            }
+        }
+    }
+
+    /// Provider that supplied the subtitle for a language, if reported
+    pub fn provider_for(&self, lang: &str) -> Option<&str> {
+        self.downloaded.iter()
+            .find(|s| s.language.eq_ignore_ascii_case(lang))
+            .map(|s| s.provider.as_str())
+    }
+
+    /// Score of the subtitle downloaded for a language, if Subliminal reported one
+    pub fn score_for(&self, lang: &str) -> Option<f64> {
+        self.downloaded.iter()
+            .find(|s| s.language.eq_ignore_ascii_case(lang))
+            .and_then(|s| s.score)
+    }
+}
+
+/// Counter keeping configuration file names unique across concurrent jobs
+static CONFIG_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary Subliminal configuration file with provider logins, readable
+/// only by the current user on Unix and removed when dropped
+struct LoginConfig {
+    path: PathBuf,
+}
+
+impl LoginConfig {
+    /// TOML with a `[provider.NAME]` table per account. JSON string escapes are
+    /// valid TOML basic strings.
+    fn contents(accounts: &[(String, ProviderAccount)]) -> String {
+        let quote = |value: &str| serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string());
+        accounts.iter()
+            .filter(|(provider, account)| ACCOUNT_PROVIDERS.contains(&provider.as_str()) && !account.is_empty())
+            .map(|(provider, account)| format!(
+                "[provider.{}]\nusername = {}\npassword = {}\n\n",
+                provider, quote(&account.username), quote(&account.password)
+            ))
+            .collect()
+    }
+
+    /// Write the configuration for the accounts, or nothing when there are none
+    fn write(accounts: &[(String, ProviderAccount)]) -> io::Result<Option<Self>> {
+        let contents = Self::contents(accounts);
+        if contents.is_empty() {
+            return Ok(None);
+        }
+        let path = std::env::temp_dir().join(format!(
+            "rustitles-subliminal-{}-{}.toml",
+            std::process::id(),
+            CONFIG_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let config = Self { path };
+        options.open(&config.path)?.write_all(contents.as_bytes())?;
+        Ok(Some(config))
+    }
+}
+
+impl Drop for LoginConfig {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            crate::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Subliminal command line runner
+pub struct Subliminal;
+
+impl Subliminal {
+    /// Build the environment used for every Subliminal process
+    pub fn build_env() -> HashMap<String, String> {
+        // Create cache directory and set environment variables to fix DBM cache issues on Windows
+        let cache_dir = PythonManager::ensure_cache_dir().unwrap_or_else(|_| std::env::temp_dir().join("subliminal_cache"));
+        let mut env_vars = HashMap::<String, String>::new();
+        env_vars.insert("PYTHONIOENCODING".to_string(), "utf-8".to_string());
+        env_vars.insert("SUBLIMINAL_CACHE_DIR".to_string(), cache_dir.to_string_lossy().to_string());
+        env_vars.insert("PYTHONHASHSEED".to_string(), "0".to_string());
+
+        // Additional environment variables to help with Windows DBM cache issues
+        #[cfg(windows)]
+        {
+            env_vars.insert("SUBLIMINAL_CACHE_BACKEND".to_string(), "memory".to_string());
+            env_vars.insert("PYTHONPATH".to_string(), std::env::var("PYTHONPATH").unwrap_or_default());
+        }
+        env_vars
+    }
+
+    /// Build the `subliminal download` arguments for a video. Provider logins
+    /// are read from the configuration file, if any, so passwords never show
+    /// up in the process list.
+    pub fn build_args(options: &SubliminalOptions, video_path: &Path, login_config: Option<&Path>) -> Vec<String> {
+        // The configuration file is a global option and goes before the command
+        let mut args = Vec::new();
+        if let Some(config) = login_config {
+            args.push("--config".to_string());
+            args.push(config.to_string_lossy().to_string());
+        }
+        // Verbose output reports the provider and score of each subtitle
+        args.push("download".to_string());
+        args.push("-vv".to_string());
+        if options.force {
+            args.push("--force".to_string());
+        }
+        match options.hearing_impaired {
+            HearingImpairedPreference::Prefer => args.push("--hearing-impaired".to_string()),
+            HearingImpairedPreference::Avoid => args.push("--no-hearing-impaired".to_string()),
+            HearingImpairedPreference::NoPreference => {}
+        }
+        if options.foreign_only {
+            args.push("--foreign-only".to_string());
+        }
+        if let Some(directory) = &options.directory {
+            args.push("-d".to_string());
+            args.push(directory.to_string_lossy().to_string());
+        }
+        if let Some(score) = options.min_score.filter(|s| *s > 0 && Validation::is_valid_min_score(*s)) {
+            args.push("--min-score".to_string());
+            args.push(score.to_string());
+        }
+        if let Some(days) = options.max_age_days.filter(|d| *d > 0 && Validation::is_valid_age_days(*d)) {
+            args.push("--age".to_string());
+            args.push(format!("{}d", days));
+        }
+        for provider in options.providers.iter().filter(|p| Validation::is_valid_provider(p)) {
+            args.push("-p".to_string());
+            args.push(provider.clone());
+        }
+        // Multiple -l flags, one for each language
+        for lang in &options.languages {
+            args.push("-l".to_string());
+            args.push(lang.clone());
+        }
+        args.push(video_path.to_string_lossy().to_string());
+        args
+    }
+
+    /// Run Subliminal with multiple failsafes for how it may be installed
+    pub fn run(args: &[String], env_vars: &HashMap<String, String>) -> io::Result<Output> {
+        let all_args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        debug!("Running subliminal command: subliminal {}", args.join(" "));
+
+        PythonManager::run_command_hidden("subliminal", &all_args, env_vars)
+            .or_else(|_| {
+                debug!("Subliminal direct command failed, trying python -m subliminal");
+                let mut python_args = vec!["-m", "subliminal"];
+                python_args.extend(&all_args);
+                PythonManager::run_command_hidden("python", &python_args, env_vars)
+            })
+            .or_else(|_| {
+                debug!("Python command failed, trying py -m subliminal");
+                let mut python_args = vec!["-m", "subliminal"];
+                python_args.extend(&all_args);
+                PythonManager::run_command_hidden("py", &python_args, env_vars)
+            })
+            .or_else(|_| {
+                debug!("Py command failed, trying python3 -m subliminal");
+                let mut python_args = vec!["-m", "subliminal"];
+                python_args.extend(&all_args);
+                PythonManager::run_command_hidden("python3", &python_args, env_vars)
+            })
+    }
+
+    /// Run a download for a video and parse the result. Paused providers are
+    /// left out and the run waits for a slot in the requests-per-minute limit.
+    pub fn download(options: &SubliminalOptions, video_path: &Path, env_vars: &HashMap<String, String>) -> io::Result<SubliminalReport> {
+        let paused = ProviderLimits::paused(&options.providers);
+        let mut options = options.clone();
+        if !paused.is_empty() {
+            let requested = if options.providers.is_empty() {
+                SUBLIMINAL_PROVIDERS.iter().map(|p| p.to_string()).collect()
+            } else {
+                options.providers.clone()
+            };
+            options.providers = requested.into_iter()
+                .filter(|p| !paused.iter().any(|hit| &hit.provider == p))
+                .collect();
+            if options.providers.is_empty() {
+                debug!("Every provider is paused, not running Subliminal for {}", video_path.display());
+                return Ok(SubliminalReport { limit_hits: paused, ..SubliminalReport::default() });
+            }
+        }
+
+        ProviderLimits::wait_for_request_slot(options.requests_per_minute);
+        let login_config = LoginConfig::write(&options.accounts)?;
+        let args = Self::build_args(&options, video_path, login_config.as_ref().map(|c| c.path.as_path()));
+        let output = Self::run(&args, env_vars);
+        drop(login_config);
+        let mut report = output.map(|out| SubliminalReport::from_output(&out))?;
+        let providers: Vec<String> = report.downloaded.iter().map(|s| s.provider.clone()).collect();
+        ProviderLimits::record(&providers, &ProviderLimits::detect_remaining(&report.combined_output), &report.limit_hits);
+        // Skipped providers might have had the subtitle, so they count as limited too
+        for hit in paused {
+            if !report.limit_hits.contains(&hit) {
+                report.limit_hits.push(hit);
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(username: &str, password: &str) -> ProviderAccount {
+        ProviderAccount { username: username.to_string(), password: password.to_string() }
+    }
+
+    #[test]
+    fn keeps_passwords_out_of_the_arguments() {
+        let options = SubliminalOptions {
+            languages: vec!["en".to_string()],
+            accounts: vec![("addic7ed".to_string(), account("me", "secret"))],
+            ..SubliminalOptions::default()
+        };
+        let config = LoginConfig::write(&options.accounts).unwrap().unwrap();
+        let args = Subliminal::build_args(&options, Path::new("movie.mkv"), Some(&config.path));
+        assert!(!args.iter().any(|a| a.contains("secret") || a == "--addic7ed"));
+        assert_eq!(&args[..3], ["--config".to_string(), config.path.to_string_lossy().to_string(), "download".to_string()]);
+        assert_eq!(std::fs::read_to_string(&config.path).unwrap(), "[provider.addic7ed]\nusername = \"me\"\npassword = \"secret\"\n\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&config.path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let path = config.path.clone();
+        drop(config);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn writes_login_config_only_for_accounts() {
+        let accounts = vec![
+            ("podnapisi".to_string(), account("me", "pw")),
+            ("opensubtitles".to_string(), account("", "pw")),
+            ("opensubtitles".to_string(), account("a\"b", "c\\d")),
+        ];
+        assert_eq!(LoginConfig::contents(&accounts), "[provider.opensubtitles]\nusername = \"a\\\"b\"\npassword = \"c\\\\d\"\n\n");
+        assert!(LoginConfig::write(&accounts[..2]).unwrap().is_none());
+    }
+
+    #[test]
+    fn parses_downloaded_subtitle_lines() {
+        let cases = [
+            ("- [87.5%] en subtitle from opensubtitles (match on title, year)", Some(("en", "opensubtitles", Some(87.5)))),
+            ("  - [92] pt-br subtitle from podnapisi", Some(("pt-br", "podnapisi", Some(92.0)))),
+            ("- [n/a] fr subtitle from addic7ed (match on series)", Some(("fr", "addic7ed", None))),
+            ("- [87%] en subtitle", None),
+            ("downloaded 1 subtitle", None),
+            ("- en subtitle from opensubtitles", None),
+            ("", None),
+        ];
+        for (line, expected) in cases {
+            let parsed = SubliminalReport::parse_subtitle_line(line);
+            let expected = expected.map(|(language, provider, score)| DownloadedSubtitle {
+                language: language.to_string(),
+                provider: provider.to_string(),
+                score,
+            });
+            assert_eq!(parsed, expected, "{}", line);
+        }
+    }
+}
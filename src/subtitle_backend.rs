@@ -0,0 +1,64 @@
+//! Subtitle download backends
+//!
+//! The download pipeline talks to a `SubtitleBackend` so it doesn't depend on
+//! where subtitles come from. The Subliminal command line is the default
+//! backend; the native OpenSubtitles.com client works without Python.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::opensubtitles::OpenSubtitlesClient;
+use crate::provider_accounts::{ProviderAccount, ProviderAccounts};
+use crate::settings::{DownloadBackend, Settings};
+use crate::subliminal::{Subliminal, SubliminalOptions, SubliminalReport};
+
+/// A source of subtitles that writes `{stem}.{lang}.srt` sidecars next to the
+/// video, or to `SubliminalOptions::directory` when set
+pub trait SubtitleBackend: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Download subtitles for the requested languages and report what was written
+    fn download(&self, options: &SubliminalOptions, video_path: &Path) -> Result<SubliminalReport, String>;
+}
+
+/// The Subliminal command line
+pub struct SubliminalBackend {
+    env_vars: HashMap<String, String>,
+    accounts: Vec<(String, ProviderAccount)>,
+}
+
+impl SubliminalBackend {
+    pub fn new(accounts: &ProviderAccounts) -> Self {
+        Self { env_vars: Subliminal::build_env(), accounts: accounts.configured() }
+    }
+}
+
+impl SubtitleBackend for SubliminalBackend {
+    fn name(&self) -> &'static str {
+        "subliminal"
+    }
+
+    fn download(&self, options: &SubliminalOptions, video_path: &Path) -> Result<SubliminalReport, String> {
+        let options = SubliminalOptions { accounts: self.accounts.clone(), ..options.clone() };
+        let report = Subliminal::download(&options, video_path, &self.env_vars)
+            .map_err(|e| format!("Failed to run subliminal: {}", e))?;
+        crate::info!("Subliminal output for {}:\n{}", video_path.display(), report.combined_output);
+        crate::info!("END subliminal output");
+        Ok(report)
+    }
+}
+
+/// Construction of the configured backend
+pub struct SubtitleBackends;
+
+impl SubtitleBackends {
+    /// Create the backend chosen in the settings, shared by all jobs of a download session
+    pub fn from_settings(settings: &Settings, accounts: &ProviderAccounts) -> Arc<dyn SubtitleBackend> {
+        match settings.backend.backend {
+            DownloadBackend::Subliminal => Arc::new(SubliminalBackend::new(accounts)),
+            DownloadBackend::OpenSubtitles => Arc::new(OpenSubtitlesClient::new(&settings.backend, accounts)),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! In-memory subtitle model and text format parsing
+//!
+//! This module provides a simple cue-based representation of subtitle files
+//! together with readers for SRT, WebVTT and ASS/SSA and an SRT writer.
+
+use std::path::Path;
+
+/// A single timed subtitle cue
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+impl Cue {
+    /// Duration of the cue in milliseconds (negative for broken cues)
+    pub fn duration_ms(&self) -> i64 {
+        self.end_ms - self.start_ms
+    }
+}
+
+/// Text subtitle formats understood by the parser
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "ass" | "ssa" => Some(SubtitleFormat::Ass),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed subtitle file
+#[derive(Clone, Debug, Default)]
+pub struct Subtitle {
+    pub cues: Vec<Cue>,
+}
+
+impl Subtitle {
+    /// Decode raw subtitle bytes, falling back to Latin-1 for legacy encodings
+    pub fn decode(bytes: &[u8]) -> String {
+        let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => bytes.iter().map(|&b| b as char).collect(),
+        }
+    }
+
+    /// Load and parse a subtitle file, detecting the format from its extension
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let format = SubtitleFormat::from_path(path)
+            .ok_or_else(|| format!("Unsupported subtitle format: {}", path.display()))?;
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Ok(Self::parse(&Self::decode(&bytes), format))
+    }
+
+    /// Parse subtitle text in the given format
+    pub fn parse(text: &str, format: SubtitleFormat) -> Self {
+        match format {
+            SubtitleFormat::Srt | SubtitleFormat::Vtt => Self::parse_srt_like(text),
+            SubtitleFormat::Ass => Self::parse_ass(text),
+        }
+    }
+
+    /// Parse SRT and WebVTT, which share the same "start --> end" block layout
+    fn parse_srt_like(text: &str) -> Self {
+        let mut cues = Vec::new();
+        let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+        for block in normalized.split("\n\n") {
+            let mut lines = block.lines().skip_while(|l| !l.contains("-->"));
+            let Some(timing) = lines.next() else { continue };
+            let mut parts = timing.split("-->");
+            let start = parts.next().and_then(|s| Self::parse_timestamp(s.trim()));
+            // WebVTT allows cue settings after the end timestamp
+            let end = parts.next()
+                .and_then(|s| s.split_whitespace().next())
+                .and_then(Self::parse_timestamp);
+            if let (Some(start_ms), Some(end_ms)) = (start, end) {
+                let text = lines.collect::<Vec<_>>().join("\n");
+                cues.push(Cue { start_ms, end_ms, text });
+            }
+        }
+        Subtitle { cues }
+    }
+
+    /// Parse the [Events] section of an ASS/SSA file
+    fn parse_ass(text: &str) -> Self {
+        let mut cues = Vec::new();
+        let mut format: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("Format:") {
+                format = rest.split(',').map(|f| f.trim().to_lowercase()).collect();
+            } else if let Some(rest) = line.strip_prefix("Dialogue:") {
+                if format.is_empty() {
+                    continue;
+                }
+                // The text field is last and may itself contain commas
+                let fields: Vec<&str> = rest.splitn(format.len(), ',').collect();
+                let field = |name: &str| format.iter().position(|f| f == name).and_then(|i| fields.get(i)).map(|s| s.trim());
+                let start = field("start").and_then(Self::parse_timestamp);
+                let end = field("end").and_then(Self::parse_timestamp);
+                if let (Some(start_ms), Some(end_ms)) = (start, end) {
+                    let text = field("text").unwrap_or("").replace("\\N", "\n").replace("\\n", "\n");
+                    cues.push(Cue { start_ms, end_ms, text });
+                }
+            }
+        }
+        Subtitle { cues }
+    }
+
+    /// Parse "hh:mm:ss,mmm", "hh:mm:ss.mmm", "mm:ss.mmm" or ASS "h:mm:ss.cc" timestamps
+    pub fn parse_timestamp(s: &str) -> Option<i64> {
+        let s = s.trim().replace(',', ".");
+        let (clock, fraction) = match s.split_once('.') {
+            Some((clock, fraction)) => (clock.to_string(), fraction.to_string()),
+            None => (s.clone(), String::new()),
+        };
+        let parts: Vec<i64> = clock.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        let (h, m, sec) = match parts.as_slice() {
+            [h, m, s] => (*h, *m, *s),
+            [m, s] => (0, *m, *s),
+            _ => return None,
+        };
+        let millis = if fraction.is_empty() {
+            0
+        } else {
+            // Scale "5", "50" and "500" to the same number of milliseconds
+            let digits: String = fraction.chars().take(3).collect();
+            let value: i64 = digits.parse().ok()?;
+            value * 10_i64.pow(3 - digits.len() as u32)
+        };
+        Some(((h * 60 + m) * 60 + sec) * 1000 + millis)
+    }
+
+    /// Format milliseconds as an SRT timestamp
+    pub fn format_srt_timestamp(ms: i64) -> String {
+        let ms = ms.max(0);
+        format!("{:02}:{:02}:{:02},{:03}", ms / 3_600_000, (ms / 60_000) % 60, (ms / 1000) % 60, ms % 1000)
+    }
+
+    /// Serialize the cues as SRT
+    pub fn to_srt(&self) -> String {
+        let mut out = String::new();
+        for (i, cue) in self.cues.iter().enumerate() {
+            out.push_str(&format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                Self::format_srt_timestamp(cue.start_ms),
+                Self::format_srt_timestamp(cue.end_ms),
+                cue.text
+            ));
+        }
+        out
+    }
+
+    /// End time of the last cue, if any
+    pub fn last_end_ms(&self) -> Option<i64> {
+        self.cues.iter().map(|c| c.end_ms).max()
+    }
+}
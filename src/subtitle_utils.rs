@@ -3,47 +3,40 @@
 //! This module provides functions for finding subtitle files, detecting
 //! embedded subtitles, and handling language code conversions.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+
+use crate::media_probe::{MediaProbe, MediaStream};
+use crate::sidecar_map::SidecarMap;
 
 /// Utilities for working with subtitle files and language detection
 pub struct SubtitleUtils;
 
 impl SubtitleUtils {
-    /// Find all subtitle files for a video and a set of languages
-    pub fn find_all_subtitle_files(video_path: &Path, langs: &[String]) -> Vec<PathBuf> {
-        let folder = match video_path.parent() {
-            Some(f) => f,
-            None => return Vec::new(),
-        };
-        let stem = match video_path.file_stem().and_then(|s| s.to_str()) {
-            Some(s) => s,
-            None => return Vec::new(),
+    /// Stem of a video used to look up its sidecars
+    fn video_stem(video_path: &Path) -> Option<&str> {
+        video_path.file_stem().and_then(|s| s.to_str())
+    }
+
+    /// Find all subtitle files for a video and a set of languages in the map of its folder
+    pub fn find_all_subtitle_files(video_path: &Path, sidecars: &SidecarMap, langs: &[String]) -> Vec<PathBuf> {
+        let Some(stem) = Self::video_stem(video_path) else {
+            return Vec::new();
         };
-        let subtitle_extensions = ["srt", "sub", "ssa", "ass", "vtt"];
         let mut found_subtitles = Vec::new();
         
-        crate::debug!("Searching for subtitle files for {} in {}", video_path.display(), folder.display());
+        crate::debug!("Searching for subtitle files for {}", video_path.display());
         
-        // Try language-specific first
+        // Language-specific first, then one generic subtitle (e.g. video.srt)
         for lang in langs {
-            for ext in &subtitle_extensions {
-                let candidate = folder.join(format!("{}.{}.{}", stem, lang, ext));
-                if candidate.exists() {
-                    crate::debug!("Found language-specific subtitle: {}", candidate.display());
-                    found_subtitles.push(candidate);
-                    break; // Found one for this language, move to next
-                }
+            if let Some(subtitle) = sidecars.find(stem, lang) {
+                crate::debug!("Found language-specific subtitle: {}", subtitle.display());
+                found_subtitles.push(subtitle);
             }
         }
-        // Then try generic
-        for ext in &subtitle_extensions {
-            let candidate = folder.join(format!("{}.{}", stem, ext));
-            if candidate.exists() {
-                crate::debug!("Found generic subtitle: {}", candidate.display());
-                found_subtitles.push(candidate);
-                break; // Found one generic, stop
-            }
+        if let Some(subtitle) = sidecars.find(stem, "") {
+            crate::debug!("Found generic subtitle: {}", subtitle.display());
+            found_subtitles.push(subtitle);
         }
         
         if found_subtitles.is_empty() {
@@ -55,6 +48,36 @@ impl SubtitleUtils {
         found_subtitles
     }
 
+    /// Find the language-specific sidecar subtitle for a video (e.g. video.en.srt)
+    /// in the map of its folder
+    pub fn find_language_subtitle(video_path: &Path, sidecars: &SidecarMap, lang: &str) -> Option<PathBuf> {
+        sidecars.find(Self::video_stem(video_path)?, lang)
+    }
+
+    /// Get the language code of a sidecar subtitle (e.g. "en" for video.en.srt)
+    pub fn subtitle_language(video_path: &Path, subtitle_path: &Path) -> Option<String> {
+        let video_stem = video_path.file_stem()?.to_str()?;
+        let sub_stem = subtitle_path.file_stem()?.to_str()?;
+        let lang = sub_stem.strip_prefix(video_stem)?.strip_prefix('.')?;
+        if lang.is_empty() { None } else { Some(lang.to_string()) }
+    }
+
+    /// Build the fallback chain for each selected language (e.g. pt-br, then pt, then es).
+    /// Languages that already appear earlier in a chain are dropped.
+    pub fn language_chains(selected_languages: &[String], fallbacks: &BTreeMap<String, Vec<String>>) -> Vec<Vec<String>> {
+        selected_languages.iter()
+            .map(|lang| {
+                let mut chain = vec![lang.clone()];
+                for fallback in fallbacks.get(lang).into_iter().flatten() {
+                    if !chain.contains(fallback) {
+                        chain.push(fallback.clone());
+                    }
+                }
+                chain
+            })
+            .collect()
+    }
+
     /// Convert a language code to a human-readable name
     pub fn language_code_to_name(code: &str) -> &str {
         match code {
@@ -150,92 +173,171 @@ impl SubtitleUtils {
         }
     }
 
-    /// Check for embedded subtitles using ffprobe
-    pub fn has_embedded_subtitle(video_path: &std::path::Path, langs: &[String]) -> Option<String> {
-        let mut cmd = Command::new("ffprobe");
-        cmd.arg("-v")
-            .arg("error")
-            .arg("-select_streams")
-            .arg("s")
-            .arg("-show_entries")
-            .arg("stream=index:stream_tags=language")
-            .arg("-of")
-            .arg("csv=p=0")
-            .arg(video_path);
-        // Hide the window on Windows
-        #[cfg(windows)]
-        {
-            use std::os::windows::process::CommandExt;
-            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    /// ISO 639-2 codes (bibliographic and terminologic) for a two-letter language code
+    pub fn iso639_2_codes(code: &str) -> &'static [&'static str] {
+        // Regional variants such as pt-br share the base language's codes
+        let base = code.split('-').next().unwrap_or(code);
+        match base {
+            "en" => &["eng"], "fr" => &["fre", "fra"], "es" => &["spa"], "de" => &["ger", "deu"],
+            "it" => &["ita"], "pt" => &["por"], "nl" => &["dut", "nld"], "pl" => &["pol"],
+            "ru" => &["rus"], "sv" => &["swe"], "fi" => &["fin"], "da" => &["dan"],
+            "no" => &["nor", "nob", "nno"], "cs" => &["cze", "ces"], "hu" => &["hun"], "ro" => &["rum", "ron"],
+            "bg" => &["bul"], "hr" => &["hrv"], "et" => &["est"], "el" => &["gre", "ell"],
+            "is" => &["ice", "isl"], "lv" => &["lav"], "lt" => &["lit"], "mt" => &["mlt"],
+            "sk" => &["slo", "slk"], "sl" => &["slv"], "tr" => &["tur"], "uk" => &["ukr"],
+            "he" => &["heb"], "ar" => &["ara"], "ja" => &["jpn"], "ko" => &["kor"],
+            "zh" => &["chi", "zho"], "th" => &["tha"], "vi" => &["vie"], "id" => &["ind"],
+            "ms" => &["may", "msa"], "fil" => &["fil", "tgl"], "bn" => &["ben"], "hi" => &["hin"],
+            "ur" => &["urd"], "fa" => &["per", "fas"], "af" => &["afr"], "sw" => &["swa"],
+            "zu" => &["zul"], "xh" => &["xho"], "ku" => &["kur"], "az" => &["aze"],
+            "ka" => &["geo", "kat"], "am" => &["amh"], "ta" => &["tam"], "te" => &["tel"],
+            "kn" => &["kan"], "ml" => &["mal"], "gu" => &["guj"], "pa" => &["pan"],
+            "or" => &["ori"], "mn" => &["mon"], "my" => &["bur", "mya"], "lo" => &["lao"],
+            "km" => &["khm"],
+            _ => &[],
         }
-        
-        // On Unix systems, just redirect output
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        {
-            use std::process::Stdio;
-            cmd.stdout(Stdio::piped());
-            cmd.stderr(Stdio::piped());
-        }
-        let output = cmd.output();
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    // Each line: index,language (e.g., 0,eng)
-                    let parts: Vec<&str> = line.split(',').collect();
-                    if parts.len() >= 2 {
-                        let lang = parts[1].trim().to_lowercase();
-                        for req in langs {
-                            // Accept both 2-letter and 3-letter codes
-                            if lang == req.to_lowercase() || lang.starts_with(&req.to_lowercase()) {
-                                return Some(Self::language_code_to_name(req).to_string());
-                            }
-                        }
-                    }
+    }
+
+    /// Check whether a container language tag (e.g. "eng", "en", "pt-BR") matches a selected language code
+    pub fn language_matches(stream_lang: &str, code: &str) -> bool {
+        let stream_lang = stream_lang.trim().to_lowercase();
+        let code = code.to_lowercase();
+        let base = code.split('-').next().unwrap_or(&code);
+        stream_lang == code
+            || stream_lang == base
+            || stream_lang.split(['-', '_']).next() == Some(base)
+            || Self::iso639_2_codes(&code).contains(&stream_lang.as_str())
+    }
+
+    /// Check for embedded subtitles using ffprobe. Bitmap tracks (PGS, VobSub)
+    /// only count when they can be converted with OCR.
+    pub fn has_embedded_subtitle(video_path: &std::path::Path, langs: &[String], ocr_enabled: bool) -> Option<String> {
+        Self::embedded_subtitle_language(&MediaProbe::streams(video_path, "s"), langs, ocr_enabled)
+    }
+
+    /// Name of the first requested language with a full embedded track among probed streams
+    pub fn embedded_subtitle_language(streams: &[MediaStream], langs: &[String], ocr_enabled: bool) -> Option<String> {
+        for stream in streams {
+            // Forced tracks only cover foreign parts, so they don't count as full subtitles
+            if stream.is_forced() || (stream.is_bitmap_subtitle() && !ocr_enabled) {
+                continue;
+            }
+            let Some(lang) = stream.language.as_deref() else { continue };
+            for req in langs {
+                // Accept both 2-letter and 3-letter codes
+                if Self::language_matches(lang, req) {
+                    return Some(Self::language_code_to_name(req).to_string());
                 }
             }
         }
         None
     }
 
-    /// Check if a video is missing subtitles for any selected language
-    pub fn video_missing_subtitle(video_path: &Path, selected_languages: &[String]) -> bool {
-        if let Some(stem) = video_path.file_stem().and_then(|s| s.to_str()) {
-            let folder = video_path.parent().unwrap_or_else(|| Path::new(""));
-            
-            // Check for common subtitle extensions
-            let subtitle_extensions = ["srt", "sub", "ssa", "ass", "vtt"];
-            
-            // Check if any of the selected languages are missing
-            for lang in selected_languages {
-                let mut lang_found = false;
-                
-                // Check for language-specific patterns first (e.g., video.en.srt)
-                for ext in &subtitle_extensions {
-                    let subtitle_path = folder.join(format!("{}.{}.{}", stem, lang, ext));
-                    if subtitle_path.exists() {
-                        lang_found = true;
-                        break;
-                    }
-                }
-                
-                // If language-specific not found, check basic pattern (e.g., video.srt)
-                if !lang_found {
-                    for ext in &subtitle_extensions {
-                        let subtitle_path = folder.join(format!("{}.{}", stem, ext));
-                        if subtitle_path.exists() {
-                            lang_found = true;
-                            break;
-                        }
-                    }
-                }
-                
-                // If this language is missing, return true (missing subtitles)
-                if !lang_found {
-                    return true;
-                }
-            }
+    /// Check if a probed subtitle stream list has a track in a language, either
+    /// a forced one or a full one. Bitmap tracks only count with OCR enabled.
+    pub fn embedded_covers(streams: &[MediaStream], lang: &str, forced: bool, ocr_enabled: bool) -> bool {
+        streams.iter().any(|stream| {
+            stream.is_forced() == forced
+                && (ocr_enabled || !stream.is_bitmap_subtitle())
+                && stream.language.as_deref().is_some_and(|l| Self::language_matches(l, lang))
+        })
+    }
+
+    /// Check if a video is missing subtitles for any language chain. A chain is
+    /// satisfied by a subtitle in any of its languages.
+    pub fn video_missing_subtitle(video_path: &Path, language_chains: &[Vec<String>]) -> bool {
+        !Self::missing_languages(video_path, language_chains).is_empty()
+    }
+
+    /// Preferred language of each chain that has no subtitle yet
+    pub fn missing_languages(video_path: &Path, language_chains: &[Vec<String>]) -> Vec<String> {
+        let Some(stem) = Self::video_stem(video_path) else {
+            return Vec::new();
+        };
+        let sidecars = SidecarMap::for_video(video_path);
+        Self::missing_languages_in(stem, sidecars.sidecars(stem), language_chains)
+    }
+
+    /// Preferred language of each chain without a subtitle among the known sidecar
+    /// names of a video, as `missing_languages` but without touching the disk
+    pub fn missing_languages_in(stem: &str, sidecars: &[String], language_chains: &[Vec<String>]) -> Vec<String> {
+        let languages: Vec<String> = sidecars.iter().filter_map(|name| SidecarMap::language(stem, name)).collect();
+        if languages.iter().any(|lang| lang.is_empty()) {
+            return Vec::new();
+        }
+        language_chains.iter()
+            .filter(|chain| !chain.iter().any(|lang| languages.contains(&lang.to_lowercase())))
+            .filter_map(|chain| chain.first().cloned())
+            .collect()
+    }
+
+    /// Check the known sidecar names of a video for a missing forced subtitle, as
+    /// `video_missing_forced_subtitle` but without touching the disk
+    pub fn missing_forced_in(stem: &str, sidecars: &[String], forced_languages: &[String]) -> bool {
+        forced_languages.iter().any(|lang| {
+            let forced = format!("{}.forced", lang.to_lowercase());
+            !sidecars.iter().any(|name| SidecarMap::language(stem, name).as_deref() == Some(forced.as_str()))
+        })
+    }
+
+    /// Check if a video is missing a forced sidecar (e.g. video.en.forced.srt) for any forced language
+    pub fn video_missing_forced_subtitle(video_path: &Path, forced_languages: &[String]) -> bool {
+        let Some(stem) = Self::video_stem(video_path) else {
+            return !forced_languages.is_empty();
+        };
+        let sidecars = SidecarMap::for_video(video_path);
+        Self::missing_forced_in(stem, sidecars.sidecars(stem), forced_languages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(language: &str, forced: bool) -> MediaStream {
+        MediaStream {
+            codec_type: "subtitle".to_string(),
+            codec_name: "subrip".to_string(),
+            language: Some(language.to_string()),
+            forced,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn machine_translations_satisfy_their_language() {
+        let chains = vec![vec!["fr".to_string()], vec!["de".to_string()]];
+        let sidecars = vec!["video.fr.machine-translated.srt".to_string()];
+        assert_eq!(SubtitleUtils::missing_languages_in("video", &sidecars, &chains), vec!["de".to_string()]);
+    }
+
+    #[test]
+    fn embedded_tracks_cover_languages() {
+        let streams = [stream("eng", false), stream("fre", true)];
+        let cases = [
+            ("en", false, true),
+            ("en", true, false),
+            ("fr", false, false),
+            ("fr", true, true),
+            ("de", false, false),
+        ];
+        for (lang, forced, expected) in cases {
+            assert_eq!(SubtitleUtils::embedded_covers(&streams, lang, forced, false), expected, "{} forced={}", lang, forced);
         }
-        false // All selected languages have subtitles
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn bitmap_tracks_count_only_with_ocr() {
+        let pgs = MediaStream { codec_name: "hdmv_pgs_subtitle".to_string(), ..stream("eng", false) };
+        let vobsub = MediaStream { codec_name: "dvd_subtitle".to_string(), ..stream("fre", false) };
+        let streams = [pgs, vobsub];
+        let langs = vec!["fr".to_string(), "en".to_string()];
+        assert_eq!(SubtitleUtils::embedded_subtitle_language(&streams, &langs, false), None);
+        assert_eq!(SubtitleUtils::embedded_subtitle_language(&streams, &langs, true).as_deref(), Some("English"));
+        assert!(!SubtitleUtils::embedded_covers(&streams, "en", false, false));
+        assert!(SubtitleUtils::embedded_covers(&streams, "en", false, true));
+
+        let text = [stream("fre", false)];
+        assert_eq!(SubtitleUtils::embedded_subtitle_language(&text, &langs, false).as_deref(), Some("French"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Sanity checks for downloaded subtitle files
+//!
+//! This module detects subtitles that are unlikely to match the video, such as
+//! files for a different cut, broken timing, empty files, HTML error pages
+//! saved as subtitles and text that did not survive an encoding conversion.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{
+    SUBTITLE_DURATION_OVERRUN_SECS, SUBTITLE_MIN_COVERAGE_RATIO,
+    SUBTITLE_MAX_OVERLAP_RATIO, SUBTITLE_MAX_REPLACEMENT_CHAR_RATIO,
+};
+use crate::subtitle_model::{Subtitle, SubtitleFormat};
+
+/// A problem found while validating a subtitle file
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationIssue {
+    Empty,
+    HtmlPage,
+    Garbage(f64),
+    OverlappingCues(usize),
+    NegativeDuration(usize),
+    DurationMismatch { subtitle_secs: f64, video_secs: f64 },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::Empty => write!(f, "subtitle file is empty"),
+            ValidationIssue::HtmlPage => write!(f, "file is an HTML page, not a subtitle"),
+            ValidationIssue::Garbage(ratio) => write!(f, "{:.0}% of the text is unreadable", ratio * 100.0),
+            ValidationIssue::OverlappingCues(count) => write!(f, "{} overlapping cues", count),
+            ValidationIssue::NegativeDuration(count) => write!(f, "{} cues end before they start", count),
+            ValidationIssue::DurationMismatch { subtitle_secs, video_secs } => write!(
+                f,
+                "last cue at {} but video is {} long (wrong cut or episode?)",
+                Self::format_secs(*subtitle_secs),
+                Self::format_secs(*video_secs)
+            ),
+        }
+    }
+}
+
+impl ValidationIssue {
+    fn format_secs(secs: f64) -> String {
+        let secs = secs.max(0.0) as u64;
+        format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    }
+}
+
+/// Validation of downloaded subtitle files
+pub struct SubtitleValidator;
+
+impl SubtitleValidator {
+    /// Validate a subtitle file, optionally against the video duration in seconds
+    pub fn validate(subtitle_path: &Path, video_duration: Option<f64>) -> Vec<ValidationIssue> {
+        let bytes = match std::fs::read(subtitle_path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                crate::warn!("Failed to read subtitle for validation {}: {}", subtitle_path.display(), e);
+                return Vec::new();
+            }
+        };
+        if bytes.iter().all(|b| b.is_ascii_whitespace()) {
+            return vec![ValidationIssue::Empty];
+        }
+
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_lowercase();
+        if head.contains("<!doctype html") || head.contains("<html") {
+            return vec![ValidationIssue::HtmlPage];
+        }
+
+        let text = Subtitle::decode(&bytes);
+        let mut issues = Vec::new();
+
+        // Only valid UTF-8 can contain literal replacement characters; legacy
+        // encodings are decoded as Latin-1 and never produce them
+        let total_chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        let replacement_chars = text.chars().filter(|&c| c == '\u{FFFD}').count();
+        if total_chars > 0 {
+            let ratio = replacement_chars as f64 / total_chars as f64;
+            if ratio > SUBTITLE_MAX_REPLACEMENT_CHAR_RATIO {
+                issues.push(ValidationIssue::Garbage(ratio));
+            }
+        }
+
+        // Bitmap formats such as .sub cannot be inspected further
+        let Some(format) = SubtitleFormat::from_path(subtitle_path) else {
+            return issues;
+        };
+        let subtitle = Subtitle::parse(&text, format);
+        if subtitle.cues.is_empty() {
+            issues.push(ValidationIssue::Empty);
+            return issues;
+        }
+
+        let negative = subtitle.cues.iter().filter(|c| c.duration_ms() < 0).count();
+        if negative > 0 {
+            issues.push(ValidationIssue::NegativeDuration(negative));
+        }
+
+        // ASS files legitimately overlap (signs, multiple speakers), so only
+        // flag files where overlaps are a large share of all cues
+        let mut sorted: Vec<_> = subtitle.cues.iter().collect();
+        sorted.sort_by_key(|c| c.start_ms);
+        let overlapping = sorted.windows(2).filter(|w| w[1].start_ms < w[0].end_ms).count();
+        if overlapping as f64 / subtitle.cues.len() as f64 > SUBTITLE_MAX_OVERLAP_RATIO {
+            issues.push(ValidationIssue::OverlappingCues(overlapping));
+        }
+
+        if let (Some(video_secs), Some(last_end_ms)) = (video_duration, subtitle.last_end_ms()) {
+            let subtitle_secs = last_end_ms as f64 / 1000.0;
+            if video_secs > 0.0
+                && (subtitle_secs > video_secs + SUBTITLE_DURATION_OVERRUN_SECS
+                    || subtitle_secs < video_secs * SUBTITLE_MIN_COVERAGE_RATIO)
+            {
+                issues.push(ValidationIssue::DurationMismatch { subtitle_secs, video_secs });
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Write a subtitle to a file unique to this test run
+    fn write_subtitle(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rustitles_validation_{}_{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// File name, contents, video duration and the expected issues
+    type Case = (&'static str, Vec<u8>, Option<f64>, Vec<ValidationIssue>);
+
+    fn srt(cues: &[(&str, &str)]) -> String {
+        cues.iter().enumerate()
+            .map(|(i, (start, end))| format!("{}\n{} --> {}\nLine {}\n", i + 1, start, end, i + 1))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn validates_subtitle_files() {
+        let good = srt(&[("00:00:01,000", "00:00:02,000"), ("00:00:03,000", "00:44:00,000")]);
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend_from_slice(good.as_bytes());
+        let garbage = format!("1\n00:00:01,000 --> 00:44:00,000\n{}\n", "\u{FFFD}".repeat(10) + "ok");
+        let cases: Vec<Case> = vec![
+            ("good.srt", good.clone().into_bytes(), Some(2700.0), vec![]),
+            ("bom.srt", bom, Some(2700.0), vec![]),
+            ("crlf.srt", good.replace('\n', "\r\n").into_bytes(), None, vec![]),
+            ("blank.srt", b" \n\r\n".to_vec(), None, vec![ValidationIssue::Empty]),
+            ("no_cues.srt", b"just some text".to_vec(), None, vec![ValidationIssue::Empty]),
+            ("broken_timestamps.srt", b"1\n00:00:xx,000 --> 00:00:02,000\nHello\n".to_vec(), None, vec![ValidationIssue::Empty]),
+            ("page.srt", b"<!DOCTYPE html><html><body>Error</body></html>".to_vec(), None, vec![ValidationIssue::HtmlPage]),
+            (
+                "negative.srt",
+                srt(&[("00:00:05,000", "00:00:04,000"), ("00:00:06,000", "00:00:07,000")]).into_bytes(),
+                None,
+                vec![ValidationIssue::NegativeDuration(1)],
+            ),
+            (
+                "overlap.srt",
+                srt(&[("00:00:01,000", "00:00:05,000"), ("00:00:02,000", "00:00:06,000"), ("00:00:03,000", "00:00:07,000")]).into_bytes(),
+                None,
+                vec![ValidationIssue::OverlappingCues(2)],
+            ),
+            (
+                "other_cut.srt",
+                good.clone().into_bytes(),
+                Some(7200.0),
+                vec![ValidationIssue::DurationMismatch { subtitle_secs: 2640.0, video_secs: 7200.0 }],
+            ),
+            (
+                "overrun.srt",
+                good.into_bytes(),
+                Some(1200.0),
+                vec![ValidationIssue::DurationMismatch { subtitle_secs: 2640.0, video_secs: 1200.0 }],
+            ),
+        ];
+        for (name, bytes, duration, expected) in cases {
+            let path = write_subtitle(name, &bytes);
+            assert_eq!(SubtitleValidator::validate(&path, duration), expected, "{}", name);
+            let _ = std::fs::remove_file(path);
+        }
+
+        let path = write_subtitle("garbage.srt", garbage.as_bytes());
+        let issues = SubtitleValidator::validate(&path, None);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(issues.as_slice(), [ValidationIssue::Garbage(ratio)] if *ratio > SUBTITLE_MAX_REPLACEMENT_CHAR_RATIO), "{:?}", issues);
+    }
+
+    #[test]
+    fn bitmap_subtitles_are_only_checked_for_emptiness() {
+        let path = write_subtitle("bitmap.sub", &[0x00, 0x00, 0x01, 0xBA, 0x44]);
+        assert_eq!(SubtitleValidator::validate(&path, Some(100.0)), vec![]);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,182 @@
+//! Machine translation of subtitles into missing languages
+//!
+//! This module translates the cues of an existing subtitle with a
+//! user-configured backend, either a LibreTranslate compatible HTTP endpoint
+//! or a local command, keeping the original timing. Cues are sent in batches
+//! and the result is written as `{stem}.{lang}.machine-translated.srt`.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use crate::helper_functions::Utils;
+use crate::settings::{TranslationBackend, TranslationSettings};
+use crate::subtitle_model::{Cue, Subtitle};
+
+/// Tag added to the file name of machine-translated subtitles
+pub const MACHINE_TRANSLATED_TAG: &str = "machine-translated";
+
+/// Subtitle translation with the configured backend
+pub struct SubtitleTranslator;
+
+impl SubtitleTranslator {
+    /// Output path for a machine-translated subtitle (video.fr.machine-translated.srt)
+    pub fn output_path(video_path: &Path, lang: &str) -> Option<PathBuf> {
+        let stem = video_path.file_stem()?.to_str()?;
+        Some(video_path.with_file_name(format!("{}.{}.{}.srt", stem, lang, MACHINE_TRANSLATED_TAG)))
+    }
+
+    /// Translate a batch of texts with a LibreTranslate compatible endpoint
+    fn translate_http(texts: &[String], source: &str, target: &str, settings: &TranslationSettings) -> Result<Vec<String>, String> {
+        let base = settings.endpoint.trim().trim_end_matches('/');
+        let url = if base.ends_with("/translate") { base.to_string() } else { format!("{}/translate", base) };
+        let mut body = serde_json::json!({
+            "q": texts,
+            "source": source,
+            "target": target,
+            "format": "text",
+        });
+        if !settings.api_key.is_empty() {
+            body["api_key"] = serde_json::Value::String(settings.api_key.clone());
+        }
+        let response = reqwest::blocking::Client::new()
+            .post(&url)
+            .header("User-Agent", "rustitles-translate")
+            .json(&body)
+            .send()
+            .map_err(|e| format!("HTTP error: {}", e))?;
+        let status = response.status();
+        let json: serde_json::Value = response.json().map_err(|e| format!("Failed to parse response: {}", e))?;
+        if !status.is_success() {
+            let message = json.get("error").and_then(|e| e.as_str()).unwrap_or("unknown error");
+            return Err(format!("translation endpoint returned {}: {}", status, message));
+        }
+        json.get("translatedText")
+            .and_then(|t| t.as_array())
+            .ok_or("response has no translatedText list")?
+            .iter()
+            .map(|t| t.as_str().map(|s| s.to_string()).ok_or_else(|| "non-text translation in response".to_string()))
+            .collect()
+    }
+
+    /// Split a command line into the program and its arguments. Single or double
+    /// quotes group words with spaces ("C:\Program Files\tool.exe"); backslashes
+    /// are kept as they are so Windows paths need no escaping.
+    fn split_command(command: &str) -> Result<Vec<String>, String> {
+        let mut parts = Vec::new();
+        let mut current: Option<String> = None;
+        let mut quote: Option<char> = None;
+        for c in command.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    current.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => parts.extend(current.take()),
+                (None, c) => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(q) = quote {
+            return Err(format!("unclosed {} in translation command", q));
+        }
+        parts.extend(current);
+        Ok(parts)
+    }
+
+    /// Translate a batch of texts with a local command. Each cue is written to
+    /// stdin on its own line and one translated line per cue is read back.
+    fn translate_command(texts: &[String], source: &str, target: &str, settings: &TranslationSettings) -> Result<Vec<String>, String> {
+        let mut parts = Self::split_command(&settings.command)?.into_iter()
+            .map(|p| p.replace("{source}", source).replace("{target}", target));
+        let program = parts.next().ok_or("no translation command configured")?;
+        let mut child = Utils::hidden_command(&program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        // Line breaks inside a cue would break the line protocol, so the cue is sent as one line
+        let input = texts.iter().map(|t| t.replace('\n', " ")).collect::<Vec<_>>().join("\n") + "\n";
+        let mut stdin = child.stdin.take().ok_or("failed to open stdin")?;
+        // Write from another thread so a chatty command can't fill its stdout pipe and block us
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output().map_err(|e| format!("Failed to read from {}: {}", program, e))?;
+        if let Ok(Err(e)) = writer.join() {
+            return Err(format!("Failed to write to {}: {}", program, e));
+        }
+        if !output.status.success() {
+            return Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+    }
+
+    /// Translate texts in batches, checking that every batch comes back complete
+    fn translate_texts(texts: &[String], source: &str, target: &str, settings: &TranslationSettings) -> Result<Vec<String>, String> {
+        let mut translated = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(settings.batch_size.max(1)) {
+            let result = match settings.backend {
+                TranslationBackend::LibreTranslate => Self::translate_http(batch, source, target, settings)?,
+                TranslationBackend::Command => Self::translate_command(batch, source, target, settings)?,
+            };
+            if result.len() != batch.len() {
+                return Err(format!("sent {} cues but got {} translations back", batch.len(), result.len()));
+            }
+            translated.extend(result);
+        }
+        Ok(translated)
+    }
+
+    /// Translate a subtitle file into another language, keeping its timing
+    pub fn translate_file(
+        video_path: &Path,
+        source_path: &Path,
+        source_lang: &str,
+        target_lang: &str,
+        settings: &TranslationSettings,
+    ) -> Result<PathBuf, String> {
+        let output = Self::output_path(video_path, target_lang).ok_or("invalid video file name")?;
+        let subtitle = Subtitle::load(source_path)?;
+        if subtitle.cues.is_empty() {
+            return Err(format!("{} has no cues to translate", source_path.display()));
+        }
+        // Language codes are sent without the region (pt-br -> pt)
+        let base = |lang: &str| lang.split('-').next().unwrap_or(lang).to_string();
+        crate::info!("Translating {} cues from {} to {} for {}", subtitle.cues.len(), source_lang, target_lang, video_path.display());
+        let texts: Vec<String> = subtitle.cues.iter().map(|c| c.text.clone()).collect();
+        let translated = Self::translate_texts(&texts, &base(source_lang), &base(target_lang), settings)?;
+
+        let cues = subtitle.cues.iter()
+            .zip(translated)
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(cue, text)| Cue { start_ms: cue.start_ms, end_ms: cue.end_ms, text: text.trim().to_string() })
+            .collect();
+        std::fs::write(&output, Subtitle { cues }.to_srt())
+            .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+        crate::info!("Wrote machine-translated subtitle {}", output.display());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_commands_with_quoted_paths() {
+        let cases: [(&str, &[&str]); 5] = [
+            ("argos-translate --from {source} --to {target}", &["argos-translate", "--from", "{source}", "--to", "{target}"]),
+            (r#""C:\Program Files\Argos\translate.exe" -f {source}"#, &[r"C:\Program Files\Argos\translate.exe", "-f", "{source}"]),
+            ("/opt/my tools/tr 'two words' x\"y\"z", &["/opt/my", "tools/tr", "two words", "xyz"]),
+            ("  spaced   out  ", &["spaced", "out"]),
+            ("tool \"\"", &["tool", ""]),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(SubtitleTranslator::split_command(command).unwrap(), expected, "{}", command);
+        }
+        assert!(SubtitleTranslator::split_command("tool \"unclosed").is_err());
+        assert!(SubtitleTranslator::split_command("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! Video file hashes used by providers to match releases
+//!
+//! This module computes the OpenSubtitles 64-bit hash and the NapiProjekt MD5
+//! of the first 10 MiB, each only when it is asked for. Hashes are cached on
+//! disk by path, size and modification time so that re-scanning a network
+//! share doesn't read every file again. The hashes also identify the same video stored in several
+//! folders; only videos of the same size are hashed for that.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use md5::{Digest, Md5};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::helper_functions::Utils;
+
+/// Bytes hashed at the start and at the end of the file for the OpenSubtitles hash
+const OPENSUBTITLES_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Bytes hashed from the start of the file for the NapiProjekt hash
+const NAPIPROJEKT_HASH_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Name of the hash cache file in the application cache directory
+const CACHE_FILE_NAME: &str = "video_hashes.json";
+
+/// Hashes providers match video files by
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum HashKind {
+    /// OpenSubtitles hash as 16 hex digits (files of at least 128 KiB)
+    OpenSubtitles,
+    /// MD5 of the first 10 MiB as 32 hex digits
+    NapiProjekt,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    size: u64,
+    modified: u64,
+    /// Hashes computed so far, None for those the file can't have
+    hashes: BTreeMap<HashKind, Option<String>>,
+}
+
+#[derive(Default)]
+struct HashCache {
+    entries: HashMap<String, CacheEntry>,
+    loaded: bool,
+    dirty: bool,
+}
+
+static CACHE: Lazy<Mutex<HashCache>> = Lazy::new(|| Mutex::new(HashCache::default()));
+
+/// Hash computation and caching for video files
+pub struct VideoHasher;
+
+impl VideoHasher {
+    /// OpenSubtitles hash: the file size plus the sums of the first and last
+    /// 64 KiB read as little-endian 64-bit words, as 16 hex digits
+    pub fn opensubtitles_hash(path: &Path) -> std::io::Result<String> {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        if size < OPENSUBTITLES_CHUNK_SIZE * 2 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "file too small to hash"));
+        }
+        let mut hash = size;
+        let mut buffer = vec![0u8; OPENSUBTITLES_CHUNK_SIZE as usize];
+        for offset in [0, size - OPENSUBTITLES_CHUNK_SIZE] {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buffer)?;
+            for word in buffer.chunks_exact(8) {
+                hash = hash.wrapping_add(u64::from_le_bytes(word.try_into().unwrap_or_default()));
+            }
+        }
+        Ok(format!("{:016x}", hash))
+    }
+
+    /// NapiProjekt hash: MD5 of the first 10 MiB
+    pub fn napiprojekt_hash(path: &Path) -> std::io::Result<String> {
+        let mut reader = File::open(path)?.take(NAPIPROJEKT_HASH_SIZE);
+        let mut md5 = Md5::new();
+        std::io::copy(&mut reader, &mut md5)?;
+        Ok(md5.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn cache_path() -> std::io::Result<PathBuf> {
+        Utils::app_cache_path(CACHE_FILE_NAME)
+    }
+
+    fn ensure_loaded(cache: &mut HashCache) {
+        if cache.loaded {
+            return;
+        }
+        cache.loaded = true;
+        let Ok(path) = Self::cache_path() else {
+            return;
+        };
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            match serde_json::from_str(&content) {
+                Ok(entries) => cache.entries = entries,
+                Err(e) => {
+                    crate::warn!("Ignoring unreadable hash cache {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// Size and modification time (seconds since the epoch) used to validate cached hashes
+    fn file_key(path: &Path) -> Option<(u64, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some((metadata.len(), modified))
+    }
+
+    /// One hash of a video, from the cache when its size and modification time
+    /// are unchanged. Other hashes are only computed when they are asked for.
+    pub fn hash(path: &Path, kind: HashKind) -> Option<String> {
+        Self::cached_hash(&CACHE, path, kind)
+    }
+
+    fn cached_hash(cache: &Mutex<HashCache>, path: &Path, kind: HashKind) -> Option<String> {
+        let (size, modified) = Self::file_key(path)?;
+        let key = path.to_string_lossy().to_string();
+        {
+            let mut cache = cache.lock().unwrap();
+            Self::ensure_loaded(&mut cache);
+            if let Some(hash) = cache.entries.get(&key)
+                .filter(|e| e.size == size && e.modified == modified)
+                .and_then(|e| e.hashes.get(&kind)) {
+                return hash.clone();
+            }
+        }
+
+        // Hash outside the lock so scans and downloads don't wait on each other
+        let hash = match kind {
+            HashKind::OpenSubtitles => Self::opensubtitles_hash(path),
+            HashKind::NapiProjekt => Self::napiprojekt_hash(path),
+        }.ok();
+        crate::debug!("Hashed {} ({:?}): {:?}", path.display(), kind, hash);
+        let mut cache = cache.lock().unwrap();
+        let entry = cache.entries.entry(key)
+            .and_modify(|e| if e.size != size || e.modified != modified {
+                *e = CacheEntry { size, modified, hashes: BTreeMap::new() };
+            })
+            .or_insert_with(|| CacheEntry { size, modified, hashes: BTreeMap::new() });
+        entry.hashes.insert(kind, hash.clone());
+        cache.dirty = true;
+        hash
+    }
+
+    /// Write the hash cache to disk if anything was added
+    pub fn save_cache() {
+        let mut cache = CACHE.lock().unwrap();
+        if !cache.dirty {
+            return;
+        }
+        let result = Self::cache_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| {
+                let json = serde_json::to_string(&cache.entries).map_err(|e| e.to_string())?;
+                std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+            });
+        match result {
+            Ok(()) => cache.dirty = false,
+            Err(e) => {
+                crate::warn!("Failed to save hash cache: {}", e);
+            }
+        }
+    }
+
+    /// Groups of videos with the same size and OpenSubtitles hash. Only files
+    /// that share their size with another one can be copies, so the others are
+    /// never read.
+    pub fn find_duplicates(videos: &[(PathBuf, u64)]) -> Vec<Vec<PathBuf>> {
+        Self::duplicates_in(&CACHE, videos)
+    }
+
+    fn duplicates_in(cache: &Mutex<HashCache>, videos: &[(PathBuf, u64)]) -> Vec<Vec<PathBuf>> {
+        let mut by_size: BTreeMap<u64, Vec<&PathBuf>> = BTreeMap::new();
+        for (video, size) in videos {
+            by_size.entry(*size).or_default().push(video);
+        }
+        let candidates: Vec<(u64, &PathBuf)> = by_size.into_iter()
+            .filter(|(_, group)| group.len() > 1)
+            .flat_map(|(size, group)| group.into_iter().map(move |video| (size, video)))
+            .collect();
+        crate::debug!("Hashing {} of {} videos that share their size with another", candidates.len(), videos.len());
+
+        let mut groups: BTreeMap<(u64, String), Vec<PathBuf>> = BTreeMap::new();
+        for (size, video) in candidates {
+            let Some(hash) = Self::cached_hash(cache, video, HashKind::OpenSubtitles) else {
+                continue;
+            };
+            groups.entry((size, hash)).or_default().push(video.clone());
+        }
+        groups.into_values().filter(|group| group.len() > 1).collect()
+    }
+
+    /// Drop cached hashes of files under a scanned folder that the scan no longer found
+    pub fn prune_cache(root: &Path, videos: &[PathBuf]) {
+        Self::prune(&mut CACHE.lock().unwrap(), root, videos);
+    }
+
+    fn prune(cache: &mut HashCache, root: &Path, videos: &[PathBuf]) {
+        let found: HashSet<String> = videos.iter().map(|v| v.to_string_lossy().to_string()).collect();
+        Self::ensure_loaded(cache);
+        let before = cache.entries.len();
+        cache.entries.retain(|key, _| !Path::new(key).starts_with(root) || found.contains(key));
+        let removed = before - cache.entries.len();
+        if removed > 0 {
+            crate::debug!("Removed {} stale entries from the hash cache", removed);
+            cache.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn napiprojekt_hash_matches_rfc_1321_test_suite() {
+        let folder = std::env::temp_dir().join(format!("rustitles-md5-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let cases = [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            ("abcdefghijklmnopqrstuvwxyz", "c3fcd3d76192e4007dfb496cca67e13b"),
+            ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789", "d174ab98d277d9f5a5611c2c9f419d9f"),
+            ("12345678901234567890123456789012345678901234567890123456789012345678901234567890", "57edf4a22be3c955ac49da2e2107b67a"),
+        ];
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            let path = folder.join(format!("{}.bin", index));
+            std::fs::write(&path, input).unwrap();
+            assert_eq!(VideoHasher::napiprojekt_hash(&path).unwrap(), expected, "{:?}", input);
+        }
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn hashes_only_videos_that_share_a_size() {
+        let folder = std::env::temp_dir().join(format!("rustitles-duplicates-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let content: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut other = content.clone();
+        // The OpenSubtitles hash only covers the first and last 64 KiB
+        other[10] ^= 0xFF;
+        let files = [
+            ("a.mkv", content.clone()),
+            ("b.mkv", content.clone()),
+            ("c.mkv", other),
+            ("d.mkv", content[..150 * 1024].to_vec()),
+        ];
+        let videos: Vec<(PathBuf, u64)> = files.iter()
+            .map(|(name, data)| {
+                let path = folder.join(name);
+                std::fs::write(&path, data).unwrap();
+                (path, data.len() as u64)
+            })
+            .collect();
+        let key = |name: &str| folder.join(name).to_string_lossy().to_string();
+        // A cache of its own, never loaded from or saved to the user's cache file
+        let cache = Mutex::new(HashCache { loaded: true, ..HashCache::default() });
+
+        let groups = VideoHasher::duplicates_in(&cache, &videos);
+        assert_eq!(groups, vec![vec![folder.join("a.mkv"), folder.join("b.mkv")]]);
+        {
+            let cache = cache.lock().unwrap();
+            // Finding duplicates only needs the OpenSubtitles hash
+            assert_eq!(cache.entries[&key("c.mkv")].hashes.keys().collect::<Vec<_>>(), vec![&HashKind::OpenSubtitles]);
+            assert!(!cache.entries.contains_key(&key("d.mkv")));
+        }
+
+        assert_eq!(VideoHasher::cached_hash(&cache, &folder.join("c.mkv"), HashKind::NapiProjekt).map(|h| h.len()), Some(32));
+        assert_eq!(cache.lock().unwrap().entries[&key("c.mkv")].hashes.len(), 2);
+
+        VideoHasher::prune(&mut cache.lock().unwrap(), &folder, &[folder.join("a.mkv")]);
+        let cache = cache.into_inner().unwrap();
+        assert_eq!(cache.entries.keys().collect::<Vec<_>>(), vec![&key("a.mkv")]);
+        assert!(cache.dirty);
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn reads_caches_written_before_hashes_were_lazy() {
+        let json = r#"{"size":1,"modified":2,"hashes":{"opensubtitles":null,"napiprojekt":"d41d8cd98f00b204e9800998ecf8427e"}}"#;
+        let entry: CacheEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.hashes.get(&HashKind::OpenSubtitles), Some(&None));
+        assert_eq!(entry.hashes[&HashKind::NapiProjekt].as_deref(), Some("d41d8cd98f00b204e9800998ecf8427e"));
+    }
+}
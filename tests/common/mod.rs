@@ -0,0 +1,159 @@
+//! Helpers shared by the integration tests
+//!
+//! `MockServer` is a minimal HTTP/1.1 server on a local port. Every request is
+//! recorded and answered by a handler closure, with the connection closed
+//! after each response.
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request received by the mock server
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path including the query string
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Path without the query string
+    pub fn route(&self) -> &str {
+        self.path.split('?').next().unwrap_or(&self.path)
+    }
+
+    /// Decoded value of a query parameter
+    pub fn query(&self, name: &str) -> Option<String> {
+        let query = self.path.split_once('?')?.1;
+        query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.replace("%2C", ",").replace('+', " "))
+    }
+}
+
+/// A response status and body
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self { status, body: body.to_string() }
+    }
+
+    pub fn text(body: &str) -> Self {
+        Self { status: 200, body: body.to_string() }
+    }
+}
+
+type Handler = dyn Fn(&MockRequest, &str) -> MockResponse + Send + Sync;
+
+/// Local HTTP server answering with a handler and recording requests
+pub struct MockServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    /// Start serving. The handler gets each request and the server's base URL.
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest, &str) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        {
+            let requests = Arc::clone(&requests);
+            let base_url = base_url.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let request = match Self::read_request(&stream) {
+                        Some(request) => request,
+                        None => continue,
+                    };
+                    let response = handler(&request, &base_url);
+                    requests.lock().unwrap().push(request);
+                    Self::write_response(stream, &response);
+                }
+            });
+        }
+        Self { base_url, requests }
+    }
+
+    fn read_request(stream: &TcpStream) -> Option<MockRequest> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let path = parts.next()?.to_string();
+        let mut headers = Vec::new();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).ok()?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let (name, value) = header.split_once(':')?;
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        let length = headers.iter()
+            .find(|(n, _)| n == "content-length")
+            .and_then(|(_, v)| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body = vec![0u8; length];
+        reader.read_exact(&mut body).ok()?;
+        Some(MockRequest { method, path, headers, body: String::from_utf8_lossy(&body).to_string() })
+    }
+
+    fn write_response(mut stream: TcpStream, response: &MockResponse) {
+        let head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.body.len()
+        );
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(response.body.as_bytes());
+        let _ = stream.flush();
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Requests received for a route (path without query)
+    pub fn requests_to(&self, route: &str) -> Vec<MockRequest> {
+        self.requests().into_iter().filter(|r| r.route() == route).collect()
+    }
+}
+
+/// A fresh, empty folder under the system temp dir
+pub fn temp_folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("rustitles-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&folder);
+    std::fs::create_dir_all(&folder).unwrap();
+    folder
+}
+
+/// Write a file of the given size filled with a repeating byte pattern
+pub fn write_video(path: &std::path::Path, size: usize) {
+    let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+    std::fs::write(path, data).unwrap();
+}
@@ -0,0 +1,177 @@
+//! The OpenSubtitles.com client against a local mock of the REST API
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::{temp_folder, write_video, MockResponse, MockServer};
+use rustitles::opensubtitles::OpenSubtitlesClient;
+use rustitles::provider_accounts::{ProviderAccount, ProviderAccounts};
+use rustitles::settings::{BackendSettings, DownloadBackend};
+use rustitles::subliminal::SubliminalOptions;
+use rustitles::subtitle_backend::SubtitleBackend;
+use serde_json::json;
+
+const SUBTITLE: &str = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+
+fn client(server: &MockServer, with_account: bool) -> OpenSubtitlesClient {
+    let settings = BackendSettings {
+        backend: DownloadBackend::OpenSubtitles,
+        opensubtitles_api_key: "key".to_string(),
+        opensubtitles_base_url: format!("{}/", server.base_url),
+    };
+    let mut accounts = ProviderAccounts::default();
+    if with_account {
+        accounts.accounts.insert("opensubtitlescom".to_string(), ProviderAccount {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        });
+    }
+    OpenSubtitlesClient::new(&settings, &accounts)
+}
+
+fn options(languages: &[&str]) -> SubliminalOptions {
+    SubliminalOptions { languages: languages.iter().map(|l| l.to_string()).collect(), ..SubliminalOptions::default() }
+}
+
+fn search_result(language: &str, file_id: u64, hash_match: bool) -> serde_json::Value {
+    json!({ "attributes": {
+        "language": language,
+        "hearing_impaired": false,
+        "moviehash_match": hash_match,
+        "download_count": 10,
+        "files": [{ "file_id": file_id }],
+    }})
+}
+
+#[test]
+fn logs_in_searches_by_hash_and_downloads() {
+    let server = MockServer::start(|request, base| match request.route() {
+        "/login" => MockResponse::json(200, json!({ "token": "token-1" })),
+        "/subtitles" => MockResponse::json(200, json!({ "data": [search_result("en", 7, true), search_result("en", 8, false)] })),
+        "/download" => MockResponse::json(200, json!({ "link": format!("{}/file/7", base), "remaining": 19 })),
+        "/file/7" => MockResponse::text(SUBTITLE),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let folder = temp_folder("os-hash");
+    let video = folder.join("Some.Movie.2010.mkv");
+    write_video(&video, 256 * 1024);
+
+    let report = client(&server, true).download(&options(&["en"]), &video).unwrap();
+    assert_eq!(std::fs::read_to_string(folder.join("Some.Movie.2010.en.srt")).unwrap(), SUBTITLE);
+    assert_eq!(report.downloaded.len(), 1);
+    assert_eq!(report.downloaded[0].provider, "opensubtitlescom");
+    assert!(report.limit_hits.is_empty());
+
+    let login = server.requests_to("/login");
+    assert_eq!(login.len(), 1);
+    assert_eq!(login[0].method, "POST");
+    assert!(login[0].body.contains("\"username\":\"user\""));
+    let searches = server.requests_to("/subtitles");
+    assert_eq!(searches.len(), 1, "a hash match for every language needs no title search");
+    assert!(searches[0].query("moviehash").is_some_and(|h| h.len() == 16));
+    assert_eq!(searches[0].header("api-key"), Some("key"));
+    let downloads = server.requests_to("/download");
+    assert_eq!(downloads[0].header("authorization"), Some("Bearer token-1"));
+    assert!(downloads[0].body.contains("\"file_id\":7"));
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn searches_by_title_when_the_file_cant_be_hashed() {
+    let server = MockServer::start(|request, base| match request.route() {
+        "/subtitles" => MockResponse::json(200, json!({ "data": [search_result("fr", 3, false)] })),
+        "/download" => MockResponse::json(200, json!({ "link": format!("{}/file/3", base) })),
+        "/file/3" => MockResponse::text(SUBTITLE),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let folder = temp_folder("os-query");
+    let video = folder.join("Some.Show.S02E05.mkv");
+    write_video(&video, 1024);
+
+    let report = client(&server, false).download(&options(&["fr", "de"]), &video).unwrap();
+    assert!(folder.join("Some.Show.S02E05.fr.srt").exists());
+    assert!(!folder.join("Some.Show.S02E05.de.srt").exists());
+    assert!(report.combined_output.contains("no de subtitle found"));
+
+    assert!(server.requests_to("/login").is_empty());
+    let searches = server.requests_to("/subtitles");
+    assert_eq!(searches.len(), 1);
+    assert_eq!(searches[0].query("query").as_deref(), Some("Some Show"));
+    assert_eq!(searches[0].query("season_number").as_deref(), Some("2"));
+    assert_eq!(searches[0].query("episode_number").as_deref(), Some("5"));
+    assert_eq!(searches[0].query("languages").as_deref(), Some("de,fr"));
+    assert_eq!(server.requests_to("/download")[0].header("authorization"), None);
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn logs_in_again_once_when_the_token_is_rejected() {
+    let logins = Arc::new(AtomicUsize::new(0));
+    let server = {
+        let logins = Arc::clone(&logins);
+        MockServer::start(move |request, base| match request.route() {
+            "/login" => {
+                let n = logins.fetch_add(1, Ordering::SeqCst) + 1;
+                MockResponse::json(200, json!({ "token": format!("token-{}", n) }))
+            }
+            "/subtitles" => MockResponse::json(200, json!({ "data": [search_result("en", 5, false)] })),
+            "/download" if request.header("authorization") == Some("Bearer token-1") => {
+                MockResponse::json(401, json!({ "message": "token expired" }))
+            }
+            "/download" => MockResponse::json(200, json!({ "link": format!("{}/file/5", base) })),
+            "/file/5" => MockResponse::text(SUBTITLE),
+            _ => MockResponse::json(404, json!({ "message": "not found" })),
+        })
+    };
+    let folder = temp_folder("os-relogin");
+    let video = folder.join("Movie.mkv");
+    write_video(&video, 1024);
+
+    let report = client(&server, true).download(&options(&["en"]), &video).unwrap();
+    assert_eq!(report.downloaded.len(), 1);
+    assert_eq!(logins.load(Ordering::SeqCst), 2);
+    let downloads = server.requests_to("/download");
+    assert_eq!(downloads.len(), 2);
+    assert_eq!(downloads[1].header("authorization"), Some("Bearer token-2"));
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn gives_up_when_a_fresh_token_is_rejected_too() {
+    let server = MockServer::start(|request, _| match request.route() {
+        "/login" => MockResponse::json(200, json!({ "token": "token" })),
+        "/subtitles" => MockResponse::json(200, json!({ "data": [search_result("en", 5, false)] })),
+        "/download" => MockResponse::json(401, json!({ "message": "invalid token" })),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let folder = temp_folder("os-unauthorized");
+    let video = folder.join("Movie.mkv");
+    write_video(&video, 1024);
+
+    let error = client(&server, true).download(&options(&["en"]), &video).unwrap_err();
+    assert!(error.contains("authentication failed"), "{}", error);
+    assert_eq!(server.requests_to("/login").len(), 2);
+    assert_eq!(server.requests_to("/download").len(), 2);
+    assert!(!folder.join("Movie.en.srt").exists());
+    std::fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn reports_a_rejected_login_without_retrying() {
+    let server = MockServer::start(|request, _| match request.route() {
+        "/login" => MockResponse::json(401, json!({ "message": "invalid credentials" })),
+        "/subtitles" => MockResponse::json(200, json!({ "data": [search_result("en", 5, false)] })),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let folder = temp_folder("os-bad-login");
+    let video = folder.join("Movie.mkv");
+    write_video(&video, 1024);
+
+    let error = client(&server, true).download(&options(&["en"]), &video).unwrap_err();
+    assert!(error.contains("invalid credentials"), "{}", error);
+    assert_eq!(server.requests_to("/login").len(), 1);
+    assert!(server.requests_to("/download").is_empty());
+    std::fs::remove_dir_all(&folder).unwrap();
+}
@@ -0,0 +1,53 @@
+//! Download quota responses of the OpenSubtitles.com REST API
+//!
+//! A limit pauses the provider for the whole process, so rate-limit responses
+//! are tested in a separate test binary.
+
+mod common;
+
+use common::{temp_folder, write_video, MockResponse, MockServer};
+use rustitles::opensubtitles::OpenSubtitlesClient;
+use rustitles::provider_accounts::ProviderAccounts;
+use rustitles::provider_limits::{LimitKind, ProviderLimitHit};
+use rustitles::settings::{BackendSettings, DownloadBackend};
+use rustitles::subliminal::SubliminalOptions;
+use rustitles::subtitle_backend::SubtitleBackend;
+use serde_json::json;
+
+#[test]
+fn pauses_the_provider_when_the_quota_is_used_up() {
+    let server = MockServer::start(|request, _| match request.route() {
+        "/subtitles" => MockResponse::json(200, json!({ "data": [{ "attributes": {
+            "language": "en",
+            "files": [{ "file_id": 1 }],
+        }}] })),
+        // The API answers 406 once the daily download quota is used up
+        "/download" => MockResponse::json(406, json!({ "message": "You have downloaded your allowed 5 subtitles for 24h" })),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let settings = BackendSettings {
+        backend: DownloadBackend::OpenSubtitles,
+        opensubtitles_api_key: "key".to_string(),
+        opensubtitles_base_url: server.base_url.clone(),
+    };
+    let client = OpenSubtitlesClient::new(&settings, &ProviderAccounts::default());
+    let options = SubliminalOptions { languages: vec!["en".to_string()], ..SubliminalOptions::default() };
+    let folder = temp_folder("os-quota");
+
+    // 406 on download: quota used up, nothing written
+    let limited = folder.join("Limited.mkv");
+    write_video(&limited, 1024);
+    let report = client.download(&options, &limited).unwrap();
+    assert_eq!(report.limit_hits, vec![ProviderLimitHit { provider: "opensubtitlescom".to_string(), kind: LimitKind::Quota }]);
+    assert!(report.downloaded.is_empty());
+
+    // While paused, the next video doesn't reach the API at all
+    let requests_before = server.requests().len();
+    let movie = folder.join("Movie.mkv");
+    write_video(&movie, 1024);
+    let report = client.download(&options, &movie).unwrap();
+    assert_eq!(report.limit_hits.len(), 1);
+    assert_eq!(server.requests().len(), requests_before);
+    assert!(!folder.join("Movie.en.srt").exists());
+    std::fs::remove_dir_all(&folder).unwrap();
+}
@@ -0,0 +1,51 @@
+//! Rate-limit responses of the OpenSubtitles.com REST API
+//!
+//! A limit pauses the provider for the whole process, so quota responses are
+//! tested in a separate test binary.
+
+mod common;
+
+use common::{temp_folder, write_video, MockResponse, MockServer};
+use rustitles::opensubtitles::OpenSubtitlesClient;
+use rustitles::provider_accounts::ProviderAccounts;
+use rustitles::provider_limits::{LimitKind, ProviderLimitHit};
+use rustitles::settings::{BackendSettings, DownloadBackend};
+use rustitles::subliminal::SubliminalOptions;
+use rustitles::subtitle_backend::SubtitleBackend;
+use serde_json::json;
+
+#[test]
+fn pauses_the_provider_on_rate_limit_responses() {
+    let server = MockServer::start(|request, _| match request.route() {
+        "/subtitles" if request.query("query").as_deref() == Some("Limited") => {
+            MockResponse::json(429, json!({ "message": "Throttle limit reached" }))
+        }
+        "/subtitles" => MockResponse::json(200, json!({ "data": [] })),
+        _ => MockResponse::json(404, json!({ "message": "not found" })),
+    });
+    let settings = BackendSettings {
+        backend: DownloadBackend::OpenSubtitles,
+        opensubtitles_api_key: "key".to_string(),
+        opensubtitles_base_url: server.base_url.clone(),
+    };
+    let client = OpenSubtitlesClient::new(&settings, &ProviderAccounts::default());
+    let options = SubliminalOptions { languages: vec!["en".to_string()], ..SubliminalOptions::default() };
+    let folder = temp_folder("os-rate-limit");
+
+    // 429 on search: rate limited, nothing written
+    let limited = folder.join("Limited.mkv");
+    write_video(&limited, 1024);
+    let report = client.download(&options, &limited).unwrap();
+    assert_eq!(report.limit_hits, vec![ProviderLimitHit { provider: "opensubtitlescom".to_string(), kind: LimitKind::RateLimit }]);
+    assert!(report.downloaded.is_empty());
+
+    // While paused, the next video doesn't reach the API at all
+    let requests_before = server.requests().len();
+    let movie = folder.join("Movie.mkv");
+    write_video(&movie, 1024);
+    let report = client.download(&options, &movie).unwrap();
+    assert_eq!(report.limit_hits.len(), 1);
+    assert_eq!(server.requests().len(), requests_before);
+    assert!(!folder.join("Movie.en.srt").exists());
+    std::fs::remove_dir_all(&folder).unwrap();
+}